@@ -0,0 +1,122 @@
+use anyhow::{bail, Result};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Overrides `auth.token_sha256`, so the token can come from a container's
+/// environment rather than a config file.
+pub const TOKEN_ENV: &str = "TKR_WEB_TOKEN_SHA256";
+
+/// The `auth:` section of the web config. Secrets are never stored in plain
+/// text: passwords as salted Argon2 hashes (`tkr web --hash-password`), and
+/// the token as a hex SHA-256 digest (`printf %s "$token" | sha256sum`).
+/// A plain digest is only safe for a long random token, which can't be
+/// guessed from it; passwords people choose need the slow, salted hash.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// Digest of the token accepted as `Authorization: Bearer <token>`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_sha256: Option<String>,
+    /// HTTP basic auth users and the Argon2 hashes of their passwords
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub users: BTreeMap<String, String>,
+    /// Digests of credentials already checked against `users`, since
+    /// browsers resend them with every request and Argon2 is slow by design
+    #[serde(skip)]
+    verified: Arc<Mutex<HashSet<Vec<u8>>>>,
+}
+
+impl AuthConfig {
+    /// This config with `TKR_WEB_TOKEN_SHA256` applied, checked for digests
+    /// that could never match.
+    pub fn resolve(mut self) -> Result<Self> {
+        let mut token_key = "auth.token_sha256";
+        if let Some(token) = std::env::var(TOKEN_ENV).ok().filter(|token| !token.trim().is_empty()) {
+            self.token_sha256 = Some(token.trim().to_string());
+            token_key = TOKEN_ENV;
+        }
+        if let Some(digest) = &self.token_sha256 {
+            if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+                bail!("{} must be a hex SHA-256 digest, e.g. from `printf %s \"$token\" | sha256sum`", token_key);
+            }
+        }
+        for (user, hash) in &self.users {
+            if !PasswordHash::new(hash).is_ok_and(|hash| hash.algorithm.as_str().starts_with("argon2")) {
+                bail!("auth.users.{} must be an Argon2 hash from `tkr web --hash-password`", user);
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.token_sha256.is_some() || !self.users.is_empty()
+    }
+
+    /// Whether an `Authorization` header carries valid credentials: the
+    /// bearer token, a user's basic auth password, or the token as the basic
+    /// auth password for any user name (so browsers can log in with it).
+    pub fn allows(&self, authorization: Option<&str>) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+        let Some((scheme, credentials)) = authorization.and_then(|header| header.trim().split_once(' ')) else {
+            return false;
+        };
+        let token_matches = |secret: &str| self.token_sha256.as_deref().is_some_and(|digest| digest_matches(digest, secret));
+
+        if scheme.eq_ignore_ascii_case("bearer") {
+            return token_matches(credentials.trim());
+        }
+        if !scheme.eq_ignore_ascii_case("basic") {
+            return false;
+        }
+        let Some(decoded) = BASE64.decode(credentials.trim()).ok().and_then(|bytes| String::from_utf8(bytes).ok()) else {
+            return false;
+        };
+        let Some((user, password)) = decoded.split_once(':') else {
+            return false;
+        };
+        token_matches(password) || self.user_matches(user, password)
+    }
+
+    fn user_matches(&self, user: &str, password: &str) -> bool {
+        let Some(hash) = self.users.get(user).and_then(|hash| PasswordHash::new(hash).ok()) else {
+            return false;
+        };
+        let key = Sha256::new().chain_update(user).chain_update([0]).chain_update(password).finalize().to_vec();
+        let mut verified = self.verified.lock().unwrap_or_else(|e| e.into_inner());
+        if verified.contains(&key) {
+            return true;
+        }
+        let matches = Argon2::default().verify_password(password.as_bytes(), &hash).is_ok();
+        if matches {
+            verified.insert(key);
+        }
+        matches
+    }
+}
+
+/// A salted Argon2 hash of `password`, for `auth.users`.
+pub fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::encode_b64(uuid::Uuid::new_v4().as_bytes())
+        .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))?;
+    let hash = Argon2::default().hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))?;
+    Ok(hash.to_string())
+}
+
+/// Compare digests without an early exit, so response times don't reveal how
+/// much of a guess was right.
+fn digest_matches(expected: &str, secret: &str) -> bool {
+    let actual = Sha256::digest(secret.as_bytes());
+    let actual: String = actual.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let expected = expected.to_ascii_lowercase();
+    expected.len() == actual.len()
+        && expected.bytes().zip(actual.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
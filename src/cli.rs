@@ -1,5 +1,13 @@
 use clap::{Parser, Subcommand};
-use crate::ticket::{TicketManager, CreateOptions};
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+use crate::config::parse_field_args;
+use crate::graph::{render, DepGraph, GraphFormat};
+use crate::history;
+use crate::jq;
+use crate::output::OutputOptions;
+use crate::query::{parse_since, Query};
+use crate::ticket::{Ticket, TicketManager, CreateOptions, LinkKind, TicketFilter};
 
 #[derive(Parser)]
 #[command(name = "tkr")]
@@ -17,6 +25,13 @@ pub struct Cli {
     #[arg(long = "category", env = "TICKET_CATEGORY")]
     pub category: Option<String>,
 
+    /// Don't auto-commit this command's changes; `tkr commit` commits them later
+    #[arg(long = "no-commit", global = true)]
+    pub no_commit: bool,
+
+    #[command(flatten)]
+    pub output: OutputOptions,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -42,6 +57,12 @@ pub enum Commands {
         external_ref: Option<String>,
         #[arg(long = "parent")]
         parent: Option<String>,
+        /// Another name the ticket can be referred to by (repeatable)
+        #[arg(long = "alias")]
+        aliases: Vec<String>,
+        /// Set a custom field (repeatable)
+        #[arg(long = "field", value_name = "KEY=VALUE")]
+        fields: Vec<String>,
     },
     /// Set ticket status to in_progress
     Start { id: String },
@@ -52,18 +73,32 @@ pub enum Commands {
     /// Update ticket status
     Status { id: String, status: String },
     /// Add dependency
-    Dep { id: String, dep_id: String },
-    /// Show dependency tree
-    DepTree {
+    Dep {
         id: String,
+        dep_id: String,
+        /// Add it even if the target is unknown, the ticket itself, or closes a cycle
+        #[arg(long)]
+        force: bool,
+    },
+    /// Show dependency tree (of one ticket, or of every open ticket)
+    DepTree {
+        id: Option<String>,
+        /// Include closed dependencies and the tickets that depend on this one
         #[arg(long, default_value = "false")]
-        full: bool
+        full: bool,
+        #[arg(long, value_enum, default_value = "tree")]
+        format: GraphFormat,
     },
     /// Remove dependency
     Undep { id: String, dep_id: String },
-    /// Link tickets together
-    Link { ids: Vec<String> },
-    /// Remove link between tickets
+    /// Link the first ticket to each of the others (the inverse link is added too)
+    Link {
+        #[arg(num_args = 2.., required = true)]
+        ids: Vec<String>,
+        #[arg(short = 't', long = "type", value_enum, default_value = "relates-to")]
+        kind: LinkKind,
+    },
+    /// Remove link between tickets (on both sides)
     Unlink { id: String, target_id: String },
     /// List tickets
     List {
@@ -75,6 +110,13 @@ pub enum Commands {
         project: Option<String>,
         #[arg(long = "category")]
         category: Option<String>,
+        /// Filter by custom field (repeatable)
+        #[arg(long = "field", value_name = "KEY=VALUE")]
+        fields: Vec<String>,
+        /// Query expression, e.g. 'status:open priority<=1 assignee:me'
+        query: Vec<String>,
+        #[command(flatten)]
+        output: OutputOptions,
     },
     /// Alias for 'list' command
     Ls {
@@ -86,16 +128,39 @@ pub enum Commands {
         project: Option<String>,
         #[arg(long = "category")]
         category: Option<String>,
+        /// Filter by custom field (repeatable)
+        #[arg(long = "field", value_name = "KEY=VALUE")]
+        fields: Vec<String>,
+        /// Query expression, e.g. 'status:open priority<=1 assignee:me'
+        query: Vec<String>,
+        #[command(flatten)]
+        output: OutputOptions,
     },
     /// List ready tickets (no open dependencies)
-    Ready,
-    /// List blocked tickets
-    Blocked,
-    /// List recently closed tickets
-    Closed,
+    Ready {
+        #[command(flatten)]
+        output: OutputOptions,
+    },
+    /// List tickets that can't proceed and the dependencies blocking them
+    Blocked {
+        #[command(flatten)]
+        output: OutputOptions,
+    },
+    /// List recently closed tickets, most recently closed first
+    Closed {
+        /// Only tickets closed on or after this date (YYYY-MM-DD, today, yesterday, or 7d/2w/3m ago)
+        #[arg(long)]
+        since: Option<String>,
+        #[command(flatten)]
+        output: OutputOptions,
+    },
     /// Show ticket details
-    Show { id: String },
-    /// Edit ticket in $EDITOR
+    Show {
+        id: String,
+        #[command(flatten)]
+        output: OutputOptions,
+    },
+    /// Edit ticket in $VISUAL or $EDITOR (saved only once it is valid)
     Edit { id: String },
     /// Add note to ticket
     AddNote {
@@ -103,16 +168,67 @@ pub enum Commands {
         #[arg(trailing_var_arg = true)]
         note: Vec<String>,
     },
-    /// Query tickets as JSON
+    /// Show the change history of a ticket, or of all tickets
+    History {
+        id: Option<String>,
+        /// Show only the most recent N operations
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Print the events as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Revert the most recent change (repeat to go further back)
+    Undo,
+    /// Commit changes left uncommitted by --no-commit or a failed auto-commit
+    Commit,
+    /// Run a jq-style filter over all tickets as a JSON array
     Query {
         #[arg(default_value = ".")]
         filter: String,
+        /// Only include tickets matching a query expression (see `list`)
+        #[arg(long = "where", value_name = "QUERY")]
+        where_query: Option<String>,
+        /// Print strings without JSON quotes
+        #[arg(short = 'r', long = "raw-output")]
+        raw: bool,
+        /// Print each result on one line
+        #[arg(short = 'c', long = "compact-output")]
+        compact: bool,
+        /// Print the results, which must be tickets, in this format instead
+        #[command(flatten)]
+        output: OutputOptions,
+    },
+    /// Full-text search, ranked by relevance
+    Search {
+        /// Words, "quoted phrases" and field-scoped terms like title:login or notes:timeout
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// Only search tickets matching a query expression (see `list`)
+        #[arg(long = "where", value_name = "QUERY")]
+        where_query: Option<String>,
+        /// Print the results, with scores and snippets, as JSON
+        #[arg(long)]
+        json: bool,
+        /// Print the matching tickets, best first, in this format instead
+        /// (--limit defaults to 20)
+        #[command(flatten)]
+        output: OutputOptions,
     },
     /// Migrate from beads or bash tk format
     Migrate {
         #[arg(long, default_value = "auto")]
         from: String,
     },
+    /// Check the tickets directory for problems
+    Doctor {
+        /// Repair the problems that can be repaired safely
+        #[arg(long)]
+        fix: bool,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Display version and build information
     Version,
     /// Start web server with kanban board
@@ -121,13 +237,25 @@ pub enum Commands {
         host: String,
         #[arg(long = "port", default_value = "8080")]
         port: u16,
+        /// Serve the board and API without allowing changes
+        #[arg(long = "read-only")]
+        read_only: bool,
+        /// Serve the board from this directory instead of the copy built
+        /// into tkr, for working on the frontend
+        #[arg(long = "web-root")]
+        web_root: Option<String>,
+        /// Read a password from stdin and print its hash for `auth.users`,
+        /// instead of starting the server
+        #[arg(long = "hash-password")]
+        hash_password: bool,
     },
     /// Start terminal user interface (TUI)
     Tui,
 }
 
 impl Commands {
-    pub async fn execute(self, manager: &mut TicketManager) -> anyhow::Result<()> {
+    /// Run the command; `global` holds output options given before it.
+    pub async fn execute(self, manager: &mut TicketManager, global: &OutputOptions) -> anyhow::Result<()> {
         match self {
             Commands::Create {
                 title,
@@ -138,7 +266,9 @@ impl Commands {
                 priority,
                 assignee,
                 external_ref,
-                parent
+                parent,
+                aliases,
+                fields,
             } => {
                 let options = CreateOptions {
                     issue_type,
@@ -149,87 +279,145 @@ impl Commands {
                     assignee,
                     external_ref,
                     parent,
+                    aliases,
+                    fields: parse_field_args(&fields)?,
                 };
-                manager.create_ticket(title, options)?;
+                let id = manager.create_ticket(title, options)?;
+                println!("{}", id);
             },
             Commands::Start { id } => {
-                manager.update_status(&id, "in_progress")?;
+                let _op = manager.begin_operation("start");
+                let status = manager.config.workflow.start_status.clone();
+                let ticket = manager.update_status(&id, &status)?;
+                println!("Started {}", ticket.id);
             },
             Commands::Close { id } => {
-                manager.update_status(&id, "closed")?;
+                let _op = manager.begin_operation("close");
+                let status = manager.config.workflow.close_status.clone();
+                let ticket = manager.update_status(&id, &status)?;
+                println!("Closed {}", ticket.id);
             },
             Commands::Reopen { id } => {
-                manager.update_status(&id, "open")?;
+                let _op = manager.begin_operation("reopen");
+                let status = manager.config.workflow.initial_status.clone();
+                let ticket = manager.update_status(&id, &status)?;
+                println!("Reopened {}", ticket.id);
             },
             Commands::Status { id, status } => {
-                manager.update_status(&id, &status)?;
+                let ticket = manager.update_status(&id, &status)?;
+                println!("Updated {} -> {}", ticket.id, ticket.status);
             },
-            Commands::Dep { id, dep_id } => {
-                manager.add_dependency(&id, &dep_id)?;
+            Commands::Dep { id, dep_id, force } => {
+                manager.add_dependency(&id, &dep_id, force)?;
             },
-            Commands::DepTree { id: _, full: _ } => {
-                eprintln!("Dependency tree command not yet implemented");
+            Commands::DepTree { id, full, format } => {
+                let tickets = manager.list_tickets()?;
+                let graph = DepGraph::new(&tickets, &manager.config.workflow, full);
+                let trees = match &id {
+                    Some(id) => vec![graph.tree(&manager.resolve_id(id)?)],
+                    None => graph.forest(),
+                };
+
+                if format == GraphFormat::Tree {
+                    match &id {
+                        Some(_) => println!("Dependency tree for {}", trees[0].id),
+                        None => println!("Dependency tree"),
+                    }
+                    if trees.is_empty() {
+                        println!("No tickets found");
+                    }
+                }
+                print!("{}", render(&trees, format)?);
+                if format == GraphFormat::Json {
+                    println!();
+                }
             },
             Commands::Undep { id, dep_id } => {
                 manager.remove_dependency(&id, &dep_id)?;
             },
-            Commands::Link { ids: _ } => {
-                eprintln!("Link command not yet implemented");
-            },
-            Commands::Unlink { id: _, target_id: _ } => {
-                eprintln!("Unlink command not yet implemented");
-            },
-            Commands::List {
-                status: _,
-                issue_type: _,
-                project: _,
-                category: _
-            } => {
-                let tickets = manager.list_tickets()?;
-                if tickets.is_empty() {
-                    println!("No tickets found");
-                } else {
-                    for ticket in tickets {
-                        println!("{} - {} ({})", ticket.id, ticket.title, ticket.status);
-                    }
+            Commands::Link { ids, kind } => {
+                // One operation, so `undo` removes all of the links
+                let _op = manager.begin_operation("link");
+                for target_id in &ids[1..] {
+                    let (id, target_id) = manager.link_tickets(&ids[0], target_id, kind)?;
+                    println!("Linked {} {} {}", id, kind.as_str(), target_id);
                 }
             },
-            Commands::Ls {
-                status: _,
-                issue_type: _,
-                project: _,
-                category: _
-            } => {
-                let tickets = manager.list_tickets()?;
-                if tickets.is_empty() {
-                    println!("No tickets found");
-                } else {
-                    for ticket in tickets {
-                        println!("{} - {} ({})", ticket.id, ticket.title, ticket.status);
-                    }
-                }
+            Commands::Unlink { id, target_id } => {
+                let (id, target_id) = manager.unlink_tickets(&id, &target_id)?;
+                println!("Unlinked {} and {}", id, target_id);
+            },
+            Commands::List { status, issue_type, project, category, fields, query, output }
+            | Commands::Ls { status, issue_type, project, category, fields, query, output } => {
+                let filter = TicketFilter {
+                    status,
+                    issue_type,
+                    project,
+                    category,
+                    fields: parse_field_args(&fields)?,
+                };
+                let output = output.or(global);
+                let query = Query::parse(&query.join(" "))?;
+                let ctx = manager.query_context();
+                let tickets: Vec<_> = manager.filter_tickets(&filter)?
+                    .into_iter()
+                    .filter(|ticket| query.matches(ticket, &ctx))
+                    .collect();
+                output.print(tickets, &manager.config, "No tickets found")?;
             },
-            Commands::Ready => {
+            Commands::Ready { output } => {
                 let tickets = manager.list_ready_tickets()?;
-                if tickets.is_empty() {
-                    println!("No ready tickets found");
-                } else {
-                    for ticket in tickets {
-                        println!("{} - {} ({})", ticket.id, ticket.title, ticket.status);
-                    }
-                }
+                output.or(global).print(tickets, &manager.config, "No ready tickets found")?;
             },
-            Commands::Blocked => {
-                eprintln!("Blocked command not yet implemented");
+            Commands::Blocked { output } => {
+                let output = output.or(global);
+                let blocked = manager.list_blocked_tickets()?;
+                let blockers: BTreeMap<String, Vec<String>> = blocked.iter()
+                    .map(|(ticket, deps)| (ticket.id.clone(), deps.clone()))
+                    .collect();
+                let tickets = blocked.into_iter().map(|(ticket, _)| ticket).collect();
+                output.print_with(
+                    tickets,
+                    &manager.config,
+                    "No blocked tickets found",
+                    &["id", "status", "priority", "title", "blocked_by"],
+                    |ticket| {
+                        let deps = blockers.get(&ticket.id).cloned().unwrap_or_default();
+                        serde_json::Map::from_iter([("blocked_by".to_string(), deps.into())])
+                    },
+                )?;
             },
-            Commands::Closed => {
-                eprintln!("Closed command not yet implemented");
+            Commands::Closed { since, output } => {
+                let mut output = output.or(global);
+                let since = since.map(|since| {
+                    parse_since(&since).ok_or_else(|| {
+                        anyhow::anyhow!("Invalid --since '{}'. Expected YYYY-MM-DD, today, yesterday or e.g. 7d", since)
+                    })
+                }).transpose()?;
+                // Without a range, "recently" means the last 20
+                if since.is_none() && output.limit.is_none() {
+                    output.limit = Some(20);
+                }
+                let tickets = manager.list_closed_tickets(since)?;
+                output.print_with(
+                    tickets,
+                    &manager.config,
+                    "No closed tickets found",
+                    &["id", "closed_at", "closed_by", "priority", "title"],
+                    |_| serde_json::Map::new(),
+                )?;
             },
-            Commands::Show { id } => {
-                manager.show_ticket(&id)?;
+            Commands::Show { id, output } => {
+                let output = output.or(global);
+                if output.format.is_none() {
+                    manager.show_ticket(&id)?;
+                } else {
+                    let ticket = manager.load_ticket(&id)?;
+                    output.print(vec![ticket], &manager.config, "")?;
+                }
             },
-            Commands::Edit { id: _ } => {
-                eprintln!("Edit command not yet implemented");
+            Commands::Edit { id } => {
+                crate::edit::run(manager, &id)?;
             },
             Commands::AddNote { id, note } => {
                 let note_content = if note.is_empty() {
@@ -243,18 +431,133 @@ impl Commands {
                 };
                 manager.add_note(&id, &note_content)?;
             },
-            Commands::Query { filter: _ } => {
-                eprintln!("Query command not yet implemented");
+            Commands::Query { filter, where_query, raw, compact, output } => {
+                let output = output.or(global);
+                let filter = jq::Filter::parse(&filter)?;
+                let query = Query::parse(where_query.as_deref().unwrap_or(""))?;
+                let tickets = serde_json::to_value(manager.query_tickets(&query)?)?;
+                let results = filter.run(&tickets)?;
+                if output.format.is_some() {
+                    // Arrays of tickets count as the tickets in them, so
+                    // both `.[] | select(...)` and `map(...)` work
+                    let mut matched = Vec::new();
+                    for value in results {
+                        let values = match value {
+                            serde_json::Value::Array(values) => values,
+                            value => vec![value],
+                        };
+                        for value in values {
+                            let ticket = serde_json::from_value::<Ticket>(value).map_err(|_| {
+                                anyhow::anyhow!("--format needs the filter to produce tickets; leave it out to print other values")
+                            })?;
+                            matched.push(ticket);
+                        }
+                    }
+                    output.print(matched, &manager.config, "No tickets found")?;
+                } else {
+                    for value in results {
+                        match value {
+                            serde_json::Value::String(s) if raw => println!("{}", s),
+                            _ if compact => println!("{}", serde_json::to_string(&value)?),
+                            _ => println!("{}", serde_json::to_string_pretty(&value)?),
+                        }
+                    }
+                }
+            },
+            Commands::Search { query, where_query, json, output } => {
+                let output = output.or(global);
+                let filter = where_query.as_deref().map(Query::parse).transpose()?;
+                let limit = output.limit.unwrap_or(20);
+                let hits = manager.search_tickets(&query.join(" "), filter.as_ref(), limit)?;
+                if !json && output.format.is_some() {
+                    // Best match first, unless --sort asks otherwise
+                    let tickets = hits.iter()
+                        .map(|hit| manager.load_ticket(&hit.id))
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    output.print(tickets, &manager.config, "No matching tickets found")?;
+                } else if json {
+                    println!("{}", serde_json::to_string_pretty(&hits)?);
+                } else if hits.is_empty() {
+                    println!("No matching tickets found");
+                } else {
+                    let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+                    for hit in &hits {
+                        hit.print(color);
+                    }
+                }
+            },
+            Commands::History { id, limit, json } => {
+                let mut events = history::load(&manager.tickets_dir)?;
+                if let Some(id) = id {
+                    // Deleted tickets can still be looked up by their exact ID
+                    let id = manager.resolve_id(&id).unwrap_or(id);
+                    events.retain(|event| event.ticket == id);
+                }
+                if let Some(limit) = limit {
+                    // Newest operations first, counting each once
+                    let mut ops: Vec<String> = Vec::new();
+                    for event in events.iter().rev() {
+                        if !ops.contains(&event.op) {
+                            if ops.len() == limit {
+                                break;
+                            }
+                            ops.push(event.op.clone());
+                        }
+                    }
+                    events.retain(|event| ops.contains(&event.op));
+                }
+
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&events)?);
+                } else if events.is_empty() {
+                    println!("No history found");
+                } else {
+                    let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+                    history::print(&events, color);
+                }
+            },
+            Commands::Undo => {
+                let undone = history::undo(manager)?;
+                let actor = undone.actor.map(|a| format!(" by {}", a)).unwrap_or_default();
+                println!("Undid '{}'{} at {}: {}",
+                    undone.command, actor, undone.at.format("%Y-%m-%d %H:%M:%S"), undone.tickets.join(", "));
+            },
+            Commands::Commit => {
+                match crate::git::commit(&manager.tickets_dir)? {
+                    Some(subject) => println!("Committed: {}", subject),
+                    None => println!("Nothing to commit"),
+                }
             },
             Commands::Migrate { from } => {
                 manager.migrate_tickets(&from)?;
             },
+            Commands::Doctor { fix, json } => {
+                let report = crate::doctor::run(manager, fix)?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                } else {
+                    report.print();
+                }
+                if !report.ok {
+                    anyhow::bail!("{} problem(s) need attention", report.unresolved());
+                }
+            },
             Commands::Version => {
                 println!("tkr {}", env!("CARGO_PKG_VERSION"));
                 println!("A ticket management system with dependency tracking and mono-repo support");
             },
-            Commands::Web { host, port } => {
-                crate::web::start_web_server(manager, host, port).await?;
+            Commands::Web { host, port, read_only, web_root, hash_password } => {
+                if hash_password {
+                    let mut password = String::new();
+                    std::io::stdin().read_line(&mut password)?;
+                    let password = password.trim_end_matches(['\r', '\n']);
+                    if password.is_empty() {
+                        anyhow::bail!("No password given on stdin");
+                    }
+                    println!("{}", crate::auth::hash_password(password)?);
+                } else {
+                    crate::web::start_web_server(manager, host, port, read_only, web_root).await?;
+                }
             },
             Commands::Tui => {
                 crate::tui::run_tui(manager).await?;
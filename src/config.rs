@@ -0,0 +1,367 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::git::GitConfig;
+use crate::web::WebConfig;
+
+/// Contents of `config.yml`.
+///
+/// Web server settings live at the top level for compatibility with older
+/// config files; everything else gets its own key.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(flatten)]
+    pub web: WebConfig,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom_fields: BTreeMap<String, CustomField>,
+    #[serde(default)]
+    pub workflow: WorkflowConfig,
+    #[serde(default)]
+    pub git: GitConfig,
+}
+
+/// Statuses and the transitions allowed between them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkflowConfig {
+    /// Every valid status, in board order; each gets its own directory
+    pub statuses: Vec<String>,
+    /// Statuses that count as done when checking dependencies
+    pub closed_statuses: Vec<String>,
+    /// Allowed target statuses per status; statuses not listed can move anywhere
+    pub transitions: BTreeMap<String, Vec<String>>,
+    /// Conditions a ticket must meet before entering a status
+    pub guards: BTreeMap<String, Vec<Guard>>,
+    /// Status of new and reopened tickets
+    pub initial_status: String,
+    /// Status `tkr start` moves to; entering it first records `started_at`
+    pub start_status: String,
+    /// Status `tkr close` moves to; one of `closed_statuses`
+    pub close_status: String,
+    /// Statuses of tickets still to be done: they can be ready, and they
+    /// hold up the tickets that depend on them
+    pub active_statuses: Vec<String>,
+    /// Status of tickets marked as blocked; `blocked` if the workflow has it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocked_status: Option<String>,
+    /// Where blocked tickets go when a dependency closes; `ready` if the
+    /// workflow has it, otherwise the initial status
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unblocked_status: Option<String>,
+}
+
+impl Default for WorkflowConfig {
+    fn default() -> Self {
+        Self {
+            statuses: ["open", "in_progress", "closed", "blocked", "ready", "icebox", "archive"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            closed_statuses: vec!["closed".to_string(), "archive".to_string()],
+            transitions: BTreeMap::new(),
+            guards: BTreeMap::new(),
+            initial_status: "open".to_string(),
+            start_status: "in_progress".to_string(),
+            close_status: "closed".to_string(),
+            active_statuses: vec!["open".to_string(), "in_progress".to_string()],
+            blocked_status: None,
+            unblocked_status: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Guard {
+    /// Every dependency must be in a closed status
+    DepsClosed,
+    /// The ticket must have an assignee
+    Assignee,
+    /// The named custom field must be set
+    Field(String),
+}
+
+impl WorkflowConfig {
+    pub fn is_valid(&self, status: &str) -> bool {
+        self.statuses.iter().any(|s| s == status)
+    }
+
+    pub fn is_closed(&self, status: &str) -> bool {
+        self.closed_statuses.iter().any(|s| s == status)
+    }
+
+    pub fn allows(&self, from: &str, to: &str) -> bool {
+        match self.transitions.get(from) {
+            Some(targets) => targets.iter().any(|s| s == to),
+            None => true,
+        }
+    }
+
+    pub fn guards_for(&self, status: &str) -> &[Guard] {
+        self.guards.get(status).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn is_active(&self, status: &str) -> bool {
+        self.active_statuses.iter().any(|s| s == status)
+    }
+
+    pub fn blocked_status(&self) -> Option<&str> {
+        match &self.blocked_status {
+            Some(status) => Some(status),
+            None => Some("blocked").filter(|status| self.is_valid(status)),
+        }
+    }
+
+    pub fn unblocked_status(&self) -> &str {
+        match &self.unblocked_status {
+            Some(status) => status,
+            None if self.is_valid("ready") => "ready",
+            None => &self.initial_status,
+        }
+    }
+
+    /// Check that the statuses given a role are part of the workflow, so
+    /// tickets never end up in a directory tkr doesn't look in.
+    pub fn validate(&self) -> Result<()> {
+        let roles = [
+            ("initial_status", Some(&self.initial_status)),
+            ("start_status", Some(&self.start_status)),
+            ("close_status", Some(&self.close_status)),
+            ("blocked_status", self.blocked_status.as_ref()),
+            ("unblocked_status", self.unblocked_status.as_ref()),
+        ];
+        let roles = roles.into_iter()
+            .filter_map(|(key, status)| Some((key, status?)))
+            .chain(self.active_statuses.iter().map(|status| ("active_statuses", status)));
+        for (key, status) in roles {
+            if !self.is_valid(status) {
+                anyhow::bail!("Invalid config: workflow.{} '{}' is not one of workflow.statuses ({})",
+                    key, status, self.statuses.join(", "));
+            }
+        }
+        if !self.is_closed(&self.close_status) {
+            anyhow::bail!("Invalid config: workflow.close_status '{}' is not one of workflow.closed_statuses", self.close_status);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    #[default]
+    String,
+    Int,
+    Date,
+    Enum,
+    List,
+}
+
+/// A user-defined frontmatter field declared under `custom_fields:`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CustomField {
+    #[serde(rename = "type", default)]
+    pub field_type: FieldType,
+    #[serde(default)]
+    pub required: bool,
+    /// Allowed values for `enum` fields
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+impl CustomField {
+    /// Convert a command-line value into the YAML value stored in frontmatter.
+    pub fn parse_value(&self, name: &str, raw: &str) -> Result<Value> {
+        let raw = raw.trim();
+        match self.field_type {
+            FieldType::String => Ok(Value::String(raw.to_string())),
+            FieldType::Int => raw.parse::<i64>()
+                .map(Value::from)
+                .map_err(|_| anyhow::anyhow!("Field '{}' expects an integer, got '{}'", name, raw)),
+            FieldType::Date => chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                .map(|date| Value::String(date.format("%Y-%m-%d").to_string()))
+                .map_err(|_| anyhow::anyhow!("Field '{}' expects a date (YYYY-MM-DD), got '{}'", name, raw)),
+            FieldType::Enum => {
+                if self.values.iter().any(|v| v == raw) {
+                    Ok(Value::String(raw.to_string()))
+                } else {
+                    anyhow::bail!("Invalid value '{}' for field '{}'. Valid values: {}",
+                        raw, name, self.values.join(", "))
+                }
+            }
+            FieldType::List => Ok(Value::Sequence(
+                raw.split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(|item| Value::String(item.to_string()))
+                    .collect(),
+            )),
+        }
+    }
+
+    /// Whether a stored value matches a filter value from the command line.
+    pub fn matches(&self, value: &Value, wanted: &str) -> bool {
+        match (self.field_type, value) {
+            (FieldType::List, Value::Sequence(items)) => {
+                items.iter().any(|item| value_to_string(item).eq_ignore_ascii_case(wanted))
+            }
+            _ => value_matches(value, wanted),
+        }
+    }
+}
+
+impl Config {
+    /// Build the custom field map for a new ticket, applying defaults and
+    /// checking required fields.
+    pub fn build_fields(&self, raw_fields: &BTreeMap<String, String>) -> Result<BTreeMap<String, Value>> {
+        let mut fields = BTreeMap::new();
+
+        for (name, raw) in raw_fields {
+            let field = self.custom_fields.get(name).ok_or_else(|| {
+                anyhow::anyhow!("Unknown custom field '{}'. Declared fields: {}",
+                    name, self.declared_fields())
+            })?;
+            fields.insert(name.clone(), field.parse_value(name, raw)?);
+        }
+
+        for (name, field) in &self.custom_fields {
+            if fields.contains_key(name) {
+                continue;
+            }
+            if let Some(default) = &field.default {
+                fields.insert(name.clone(), field.parse_value(name, default)?);
+            } else if field.required {
+                anyhow::bail!("Missing required field '{}' (use --field {}=VALUE)", name, name);
+            }
+        }
+
+        Ok(fields)
+    }
+
+    /// Check custom field values read back from frontmatter, e.g. after a
+    /// hand edit, against their declarations.
+    pub fn check_fields(&self, fields: &BTreeMap<String, Value>) -> Result<()> {
+        for (name, field) in &self.custom_fields {
+            match fields.get(name) {
+                Some(Value::Sequence(_)) if field.field_type == FieldType::List => {}
+                Some(value) => {
+                    field.parse_value(name, &value_to_string(value))?;
+                }
+                None if field.required => anyhow::bail!("Missing required field '{}'", name),
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a ticket's field value matches a `KEY=VALUE` filter.
+    pub fn field_matches(&self, name: &str, value: Option<&Value>, wanted: &str) -> bool {
+        match (value, self.custom_fields.get(name)) {
+            (Some(value), Some(field)) => field.matches(value, wanted),
+            (Some(value), None) => value_matches(value, wanted),
+            (None, _) => false,
+        }
+    }
+
+    fn declared_fields(&self) -> String {
+        if self.custom_fields.is_empty() {
+            "(none)".to_string()
+        } else {
+            self.custom_fields.keys().cloned().collect::<Vec<_>>().join(", ")
+        }
+    }
+}
+
+/// Split `KEY=VALUE` arguments into a map.
+pub fn parse_field_args(args: &[String]) -> Result<BTreeMap<String, String>> {
+    args.iter()
+        .map(|arg| {
+            arg.split_once('=')
+                .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+                .ok_or_else(|| anyhow::anyhow!("Invalid field '{}'. Expected KEY=VALUE", arg))
+        })
+        .collect()
+}
+
+/// Render a frontmatter value for display.
+pub fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        Value::Sequence(items) => items.iter().map(value_to_string).collect::<Vec<_>>().join(", "),
+        other => serde_yaml::to_string(other).unwrap_or_default().trim().to_string(),
+    }
+}
+
+fn value_matches(value: &Value, wanted: &str) -> bool {
+    match value {
+        Value::Sequence(items) => items.iter().any(|item| value_matches(item, wanted)),
+        _ => value_to_string(value).eq_ignore_ascii_case(wanted),
+    }
+}
+
+/// The config from `.config/tkr/config.yml` at or above the current
+/// directory (up to the git root), else from `$XDG_CONFIG_HOME/tkr` or
+/// `~/.config/tkr`; `None` if there is none. A config that can't be read or
+/// parsed is an error rather than ignored, since falling back to the
+/// defaults would silently drop auth, custom field rules and workflows.
+pub fn load_config() -> Result<Option<Config>> {
+    // First try git root config as override
+    if let Some(path) = find_git_root_config() {
+        return read_config(&path).map(Some);
+    }
+
+    // Fallback to XDG_CONFIG_HOME or ~/.config
+    let Some(config_dir) = std::env::var("XDG_CONFIG_HOME")
+        .ok()
+        .or_else(|| std::env::var("HOME").ok().map(|h| format!("{}/.config", h)))
+    else {
+        return Ok(None);
+    };
+
+    let config_path = PathBuf::from(config_dir).join("tkr").join("config.yml");
+    if config_path.exists() {
+        read_config(&config_path).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+fn read_config(path: &Path) -> Result<Config> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config {}", path.display()))?;
+    serde_yaml::from_str(&content)
+        .with_context(|| format!("Invalid config {}", path.display()))
+}
+
+fn find_git_root_config() -> Option<PathBuf> {
+    // Try to find git root and check for .config/tkr/config.yml
+    let mut current = std::env::current_dir().ok()?;
+
+    loop {
+        let config_path = current.join(".config").join("tkr").join("config.yml");
+
+        if config_path.exists() {
+            return Some(config_path);
+        }
+
+        if current.join(".git").exists() {
+            // Found git root, break the loop
+            break;
+        }
+
+        if !current.pop() {
+            // Reached filesystem root
+            break;
+        }
+    }
+
+    None
+}
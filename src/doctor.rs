@@ -0,0 +1,472 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::document::TicketDocument;
+use crate::graph::find_cycles;
+use crate::history::{self, FileChange};
+use crate::ticket::{Link, Ticket, TicketManager};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemKind {
+    /// The same ID has files in several status directories
+    DuplicateId,
+    /// Frontmatter `status` disagrees with the containing directory
+    StatusMismatch,
+    /// `deps`, `links` or `parent` name a ticket that does not exist
+    DanglingReference,
+    /// A link without the matching inverse link on the other ticket
+    OneSidedLink,
+    /// The file could not be parsed
+    InvalidTicket,
+    /// A file at the top level of `.tickets` instead of in a status directory
+    LegacyFile,
+    /// Tickets that (transitively) depend on themselves
+    DependencyCycle,
+}
+
+impl ProblemKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ProblemKind::DuplicateId => "duplicate_id",
+            ProblemKind::StatusMismatch => "status_mismatch",
+            ProblemKind::DanglingReference => "dangling_reference",
+            ProblemKind::OneSidedLink => "one_sided_link",
+            ProblemKind::InvalidTicket => "invalid_ticket",
+            ProblemKind::LegacyFile => "legacy_file",
+            ProblemKind::DependencyCycle => "dependency_cycle",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Problem {
+    pub kind: ProblemKind,
+    /// Ticket IDs involved, in order (for cycles, the cycle path)
+    pub tickets: Vec<String>,
+    /// Files involved, relative to the tickets directory
+    pub paths: Vec<PathBuf>,
+    pub message: String,
+    /// What `--fix` did about it, if anything
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fixed: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DoctorReport {
+    /// True when no unfixed problems remain
+    pub ok: bool,
+    pub problems: Vec<Problem>,
+}
+
+impl DoctorReport {
+    pub fn unresolved(&self) -> usize {
+        self.problems.iter().filter(|p| p.fixed.is_none()).count()
+    }
+
+    pub fn print(&self) {
+        if self.problems.is_empty() {
+            println!("No problems found");
+            return;
+        }
+
+        for problem in &self.problems {
+            println!("[{}] {}", problem.kind.as_str(), problem.message);
+            if let Some(fixed) = &problem.fixed {
+                println!("    fixed: {}", fixed);
+            }
+        }
+
+        let fixed = self.problems.len() - self.unresolved();
+        println!("{} problem(s) found, {} fixed", self.problems.len(), fixed);
+    }
+}
+
+/// A ticket file found in one of the status directories.
+struct Entry {
+    id: String,
+    dir_status: String,
+    path: PathBuf,
+    ticket: Result<Ticket, String>,
+}
+
+/// Check the tickets tree for inconsistencies, repairing what can be
+/// repaired safely when `fix` is set.
+///
+/// Structural fixes run first (legacy files, duplicates, misplaced files) and
+/// the tree is rescanned after each, so later checks see the repaired state.
+pub fn run(manager: &TicketManager, fix: bool) -> Result<DoctorReport> {
+    // Also keeps the scan consistent with concurrent status moves
+    let _lock = manager.lock()?;
+    let _op = fix.then(|| manager.begin_operation("doctor --fix"));
+    let mut report = DoctorReport::default();
+    let mut doctor = Doctor { manager, fix, report: &mut report };
+
+    doctor.check_legacy_files()?;
+
+    let entries = doctor.scan()?;
+    doctor.check_invalid(&entries);
+    doctor.check_duplicates(&entries)?;
+
+    let entries = if fix { doctor.scan()? } else { entries };
+    doctor.check_status_dirs(&entries)?;
+
+    let entries = if fix { doctor.scan()? } else { entries };
+    doctor.check_references(&entries)?;
+
+    let entries = if fix { doctor.scan()? } else { entries };
+    doctor.check_links(&entries)?;
+    doctor.check_cycles(&entries);
+
+    report.ok = report.unresolved() == 0;
+    Ok(report)
+}
+
+struct Doctor<'a> {
+    manager: &'a TicketManager,
+    fix: bool,
+    report: &'a mut DoctorReport,
+}
+
+impl Doctor<'_> {
+    fn relative(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.manager.tickets_dir)
+            .unwrap_or(path)
+            .to_path_buf()
+    }
+
+    fn record_move(&self, id: &str, from: &Path, to: &Path) -> Result<()> {
+        history::touch(from);
+        history::touch(to);
+        let moved = FileChange::Moved { from: self.relative(from), to: self.relative(to) };
+        history::record_file(&self.manager.tickets_dir, id, moved, || self.manager.current_user())
+    }
+
+    fn push(&mut self, kind: ProblemKind, tickets: Vec<String>, paths: &[&Path], message: String, fixed: Option<String>) {
+        let paths = paths.iter().map(|p| self.relative(p)).collect();
+        self.report.problems.push(Problem { kind, tickets, paths, message, fixed });
+    }
+
+    /// Every `.md` file in the configured status directories, in status order.
+    fn scan(&self) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+
+        for status in self.manager.statuses() {
+            let status_dir = self.manager.get_status_dir(status);
+            if !status_dir.exists() {
+                continue;
+            }
+
+            let mut paths: Vec<PathBuf> = fs::read_dir(&status_dir)?
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| is_ticket_file(path))
+                .collect();
+            paths.sort();
+
+            for path in paths {
+                let id = file_id(&path);
+                let ticket = fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|content| {
+                        TicketDocument::parse(&content)
+                            .map(|doc| doc.ticket)
+                            .map_err(|e| e.to_string())
+                    });
+                entries.push(Entry { id, dir_status: status.clone(), path, ticket });
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn check_legacy_files(&mut self) -> Result<()> {
+        let tickets_dir = &self.manager.tickets_dir;
+        if !tickets_dir.exists() {
+            return Ok(());
+        }
+
+        let mut paths: Vec<PathBuf> = fs::read_dir(tickets_dir)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && is_ticket_file(path))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let id = file_id(&path);
+            let content = fs::read_to_string(&path)?;
+            let existing = self.manager.statuses().iter()
+                .map(|status| self.manager.get_status_dir(status).join(format!("{}.md", id)))
+                .find(|p| p.exists());
+
+            let (message, fixed) = if content.starts_with("---") {
+                let message = format!("{} is an old flat-layout ticket outside the status directories", id);
+                let fixed = match (&existing, TicketDocument::parse(&content)) {
+                    (None, Ok(doc)) if self.fix && self.manager.config.workflow.is_valid(&doc.ticket.status) => {
+                        let target = self.manager.get_status_dir(&doc.ticket.status).join(format!("{}.md", id));
+                        fs::create_dir_all(self.manager.get_status_dir(&doc.ticket.status))?;
+                        fs::rename(&path, &target)?;
+                        self.record_move(&id, &path, &target)?;
+                        Some(format!("moved to {}", self.relative(&target).display()))
+                    }
+                    _ => None,
+                };
+                (message, fixed)
+            } else {
+                let message = format!("{} is a bash tk file that has not been migrated", id);
+                let fixed = if self.fix && existing.is_none() {
+                    let ticket = self.manager.migrate_bash_tk_file(&path)?;
+                    Some(format!("migrated into {}/", ticket.status))
+                } else {
+                    None
+                };
+                (message, fixed)
+            };
+
+            let message = match &existing {
+                Some(existing) => format!("{} (a ticket with this ID already exists at {})",
+                    message, self.relative(existing).display()),
+                None => message,
+            };
+            self.push(ProblemKind::LegacyFile, vec![id], &[&path], message, fixed);
+        }
+
+        Ok(())
+    }
+
+    fn check_invalid(&mut self, entries: &[Entry]) {
+        for entry in entries {
+            if let Err(e) = &entry.ticket {
+                let message = format!("{} cannot be parsed: {}", entry.id, first_line(e));
+                self.push(ProblemKind::InvalidTicket, vec![entry.id.clone()], &[&entry.path], message, None);
+            }
+        }
+    }
+
+    fn check_duplicates(&mut self, entries: &[Entry]) -> Result<()> {
+        let mut by_id: BTreeMap<&str, Vec<&Entry>> = BTreeMap::new();
+        for entry in entries {
+            by_id.entry(&entry.id).or_default().push(entry);
+        }
+
+        for (id, copies) in by_id {
+            if copies.len() < 2 {
+                continue;
+            }
+
+            let dirs: Vec<&str> = copies.iter().map(|e| e.dir_status.as_str()).collect();
+            let message = format!("{} exists in {} status directories: {}", id, copies.len(), dirs.join(", "));
+
+            let fixed = if self.fix {
+                // Keep the newest parseable copy whose frontmatter agrees with its directory
+                let keep = copies.iter()
+                    .max_by_key(|e| {
+                        let parsed = e.ticket.is_ok();
+                        let consistent = matches!(&e.ticket, Ok(t) if t.status == e.dir_status);
+                        (parsed, consistent, modified(&e.path))
+                    })
+                    .map(|e| e.path.clone())
+                    .expect("at least two copies");
+
+                for copy in &copies {
+                    if copy.path != keep {
+                        // Kept in the history, so undo can bring it back
+                        let content = fs::read_to_string(&copy.path)?;
+                        fs::remove_file(&copy.path)?;
+                        history::touch(&copy.path);
+                        let removed = FileChange::Removed { path: self.relative(&copy.path), content };
+                        history::record_file(&self.manager.tickets_dir, id, removed, || self.manager.current_user())?;
+                    }
+                }
+                Some(format!("kept {}, removed the other copies", self.relative(&keep).display()))
+            } else {
+                None
+            };
+
+            let paths: Vec<&Path> = copies.iter().map(|e| e.path.as_path()).collect();
+            self.push(ProblemKind::DuplicateId, vec![id.to_string()], &paths, message, fixed);
+        }
+
+        Ok(())
+    }
+
+    fn check_status_dirs(&mut self, entries: &[Entry]) -> Result<()> {
+        for entry in entries {
+            let Ok(ticket) = &entry.ticket else { continue };
+            if ticket.status == entry.dir_status {
+                continue;
+            }
+
+            let valid = self.manager.config.workflow.is_valid(&ticket.status);
+            let message = if valid {
+                format!("{} has status '{}' but is in {}/", entry.id, ticket.status, entry.dir_status)
+            } else {
+                format!("{} has unknown status '{}' (in {}/)", entry.id, ticket.status, entry.dir_status)
+            };
+
+            // The frontmatter is written before the file is moved, so it wins
+            let target = self.manager.get_status_dir(&ticket.status).join(format!("{}.md", entry.id));
+            let fixed = if self.fix && valid && !target.exists() {
+                fs::create_dir_all(self.manager.get_status_dir(&ticket.status))?;
+                fs::rename(&entry.path, &target)?;
+                self.record_move(&entry.id, &entry.path, &target)?;
+                Some(format!("moved to {}", self.relative(&target).display()))
+            } else {
+                None
+            };
+
+            self.push(ProblemKind::StatusMismatch, vec![entry.id.clone()], &[&entry.path], message, fixed);
+        }
+
+        Ok(())
+    }
+
+    fn check_references(&mut self, entries: &[Entry]) -> Result<()> {
+        // Unparseable tickets still exist, so references to them are not dangling
+        let known: BTreeSet<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+        let mut seen = BTreeSet::new();
+
+        for entry in entries {
+            let Ok(ticket) = &entry.ticket else { continue };
+            if !seen.insert(entry.id.as_str()) {
+                continue;
+            }
+
+            let missing = |id: &String| !known.contains(id.as_str());
+            let dangling_deps: Vec<&String> = ticket.deps.iter().filter(|id| missing(id)).collect();
+            let dangling_links: Vec<&String> = ticket.links.iter().map(|l| &l.id).filter(|id| missing(id)).collect();
+            let dangling_parent = ticket.parent.as_ref().filter(|id| missing(id));
+
+            let mut parts = Vec::new();
+            if !dangling_deps.is_empty() {
+                parts.push(format!("deps {}", join(&dangling_deps)));
+            }
+            if !dangling_links.is_empty() {
+                parts.push(format!("links {}", join(&dangling_links)));
+            }
+            if let Some(parent) = dangling_parent {
+                parts.push(format!("parent {}", parent));
+            }
+            if parts.is_empty() {
+                continue;
+            }
+
+            let message = format!("{} refers to missing tickets: {}", entry.id, parts.join("; "));
+            // Saving a misplaced ticket would move it, so leave those alone
+            let fixed = if self.fix && ticket.status == entry.dir_status {
+                let mut updated = ticket.clone();
+                updated.deps.retain(|id| !missing(id));
+                updated.links.retain(|link| !missing(&link.id));
+                if dangling_parent.is_some() {
+                    updated.parent = None;
+                }
+                self.manager.save_ticket(&updated)?;
+                Some("removed the missing references".to_string())
+            } else {
+                None
+            };
+
+            self.push(ProblemKind::DanglingReference, vec![entry.id.clone()], &[&entry.path], message, fixed);
+        }
+
+        Ok(())
+    }
+
+    fn check_links(&mut self, entries: &[Entry]) -> Result<()> {
+        let mut tickets: BTreeMap<&str, (&Ticket, &Entry)> = BTreeMap::new();
+        for entry in entries {
+            if let Ok(ticket) = &entry.ticket {
+                tickets.entry(&entry.id).or_insert((ticket, entry));
+            }
+        }
+
+        // Inverse links to add, per target ticket
+        let mut missing: BTreeMap<&str, Vec<Link>> = BTreeMap::new();
+        for (id, (ticket, _)) in &tickets {
+            for link in &ticket.links {
+                let Some((target, _)) = tickets.get(link.id.as_str()) else { continue };
+                let inverse = Link { id: id.to_string(), kind: link.kind.inverse() };
+                if !target.links.contains(&inverse) {
+                    missing.entry(link.id.as_str()).or_default().push(inverse);
+                }
+            }
+        }
+
+        for (target_id, inverses) in missing {
+            let (target, entry) = tickets[target_id];
+            let fixed = if self.fix && target.status == entry.dir_status {
+                let mut updated = target.clone();
+                for inverse in &inverses {
+                    updated.links.retain(|link| link.id != inverse.id);
+                    updated.links.push(inverse.clone());
+                }
+                self.manager.save_ticket(&updated)?;
+                Some(format!("added the inverse links to {}", target_id))
+            } else {
+                None
+            };
+
+            for inverse in inverses {
+                let message = format!("{} links to {} as {}, but {} has no {} link back",
+                    inverse.id, target_id, inverse.kind.inverse().as_str(), target_id, inverse.kind.as_str());
+                self.push(ProblemKind::OneSidedLink, vec![inverse.id.clone(), target_id.to_string()],
+                    &[&entry.path], message, fixed.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_cycles(&mut self, entries: &[Entry]) {
+        let mut graph: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut paths: BTreeMap<String, PathBuf> = BTreeMap::new();
+        for entry in entries {
+            if let Ok(ticket) = &entry.ticket {
+                graph.entry(entry.id.clone()).or_insert_with(|| ticket.deps.clone());
+                paths.entry(entry.id.clone()).or_insert_with(|| entry.path.clone());
+            }
+        }
+
+        for cycle in find_cycles(&graph) {
+            let message = format!("Dependency cycle: {} -> {}", cycle.join(" -> "), cycle[0]);
+            let cycle_paths: Vec<&Path> = cycle.iter()
+                .filter_map(|id| paths.get(id).map(PathBuf::as_path))
+                .collect();
+            self.push(ProblemKind::DependencyCycle, cycle.clone(), &cycle_paths, message, None);
+        }
+    }
+}
+
+fn is_ticket_file(path: &Path) -> bool {
+    let hidden = path.file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| n.starts_with('.'))
+        .unwrap_or(true);
+    !hidden && path.extension().map(|ext| ext == "md").unwrap_or(false)
+}
+
+fn file_id(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn modified(path: &Path) -> SystemTime {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+fn first_line(s: &str) -> &str {
+    s.lines().next().unwrap_or(s)
+}
+
+fn join(ids: &[&String]) -> String {
+    ids.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ")
+}
@@ -0,0 +1,321 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use crate::ticket::{Note, Ticket};
+
+/// The kind of a body section, derived from its heading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SectionKind {
+    /// Anything between the frontmatter and the `# title` heading
+    Preamble,
+    /// The `# title` heading line
+    Title,
+    /// Free text between the title and the first `##` heading
+    Description,
+    Design,
+    Acceptance,
+    Notes,
+    /// Any other `## heading`, kept verbatim
+    Extra(String),
+}
+
+impl SectionKind {
+    fn from_heading(heading: &str) -> Self {
+        match heading.trim().to_lowercase().as_str() {
+            "design" => SectionKind::Design,
+            "acceptance" | "acceptance criteria" => SectionKind::Acceptance,
+            "notes" => SectionKind::Notes,
+            _ => SectionKind::Extra(heading.trim().to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub kind: SectionKind,
+    /// Exact source text of the section, including its heading and trailing whitespace
+    pub raw: String,
+}
+
+/// A ticket file split into its frontmatter and body sections.
+///
+/// Rendering a document against the ticket it was parsed from reproduces the
+/// original file byte-for-byte; only sections whose backing field changed are
+/// regenerated, so hand-written markdown survives every save.
+#[derive(Debug, Clone)]
+pub struct TicketDocument {
+    /// Ticket as parsed from the frontmatter
+    pub ticket: Ticket,
+    /// Frontmatter including both `---` delimiters
+    pub frontmatter_raw: String,
+    pub sections: Vec<Section>,
+}
+
+impl TicketDocument {
+    pub fn parse(content: &str) -> Result<Self> {
+        let (frontmatter_raw, yaml_content, body) = split_frontmatter(content)?;
+
+        let ticket: Ticket = serde_yaml::from_str(yaml_content.trim())
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to parse YAML: {}\nYAML content:\n{}",
+                    e,
+                    yaml_content.trim()
+                )
+            })?;
+
+        Ok(Self {
+            ticket,
+            frontmatter_raw: frontmatter_raw.to_string(),
+            sections: parse_sections(body),
+        })
+    }
+
+    /// Build a document for a ticket that has no file yet.
+    pub fn new(ticket: &Ticket) -> Result<Self> {
+        Ok(Self {
+            ticket: ticket.clone(),
+            frontmatter_raw: render_frontmatter(ticket)?,
+            sections: vec![Section { kind: SectionKind::Preamble, raw: "\n\n".to_string() }],
+        }
+        .with_ticket(ticket, true))
+    }
+
+    /// Render the file for `ticket`, keeping every untouched part verbatim.
+    pub fn render(&self, ticket: &Ticket) -> Result<String> {
+        // The revision is bookkeeping, not content
+        let unchanged = Ticket { revision: self.ticket.revision.clone(), ..ticket.clone() } == self.ticket;
+        let frontmatter = if unchanged {
+            self.frontmatter_raw.clone()
+        } else {
+            render_frontmatter(ticket)?
+        };
+
+        let doc = self.clone().with_ticket(ticket, false);
+        let mut content = frontmatter;
+        for section in &doc.sections {
+            content.push_str(&section.raw);
+        }
+        Ok(content)
+    }
+
+    /// Regenerate the sections whose field differs from the parsed ticket
+    /// (or all of them when `force` is set).
+    fn with_ticket(mut self, ticket: &Ticket, force: bool) -> Self {
+        let old = self.ticket.clone();
+
+        if force || ticket.title != old.title {
+            self.replace_title(&ticket.title);
+        }
+        if force || ticket.description != old.description {
+            self.replace_section(SectionKind::Description, ticket.description.clone());
+        }
+        if force || ticket.design != old.design {
+            let text = ticket.design.as_ref().map(|d| format!("## Design\n\n{}", d));
+            self.replace_section(SectionKind::Design, text);
+        }
+        if force || ticket.acceptance != old.acceptance {
+            let text = ticket.acceptance.as_ref().map(|a| format!("## Acceptance Criteria\n\n{}", a));
+            self.replace_section(SectionKind::Acceptance, text);
+        }
+        if force || ticket.notes != old.notes {
+            self.update_notes(old.notes.as_deref().unwrap_or(&[]), ticket.notes.as_deref());
+        }
+
+        self.ticket = ticket.clone();
+        self
+    }
+
+    fn replace_title(&mut self, title: &str) {
+        let text = format!("# {}", title);
+        match self.sections.iter().position(|s| s.kind == SectionKind::Title) {
+            Some(pos) => {
+                // Only the heading line changes; keep its line ending as-is
+                let raw = &self.sections[pos].raw;
+                let rest = raw.find('\n').map_or("\n", |i| &raw[i..]);
+                self.sections[pos].raw = format!("{}{}", text, rest);
+            }
+            None => self.insert_section(SectionKind::Title, text),
+        }
+    }
+
+    fn replace_section(&mut self, kind: SectionKind, text: Option<String>) {
+        let pos = self.sections.iter().position(|s| s.kind == kind);
+
+        // A whitespace-only placeholder is treated like a missing section
+        let pos = match pos {
+            Some(pos) if self.sections[pos].raw.trim().is_empty() => {
+                let placeholder = self.sections.remove(pos);
+                if text.is_none() {
+                    self.sections.insert(pos, placeholder);
+                    return;
+                }
+                None
+            }
+            pos => pos,
+        };
+
+        match (pos, text) {
+            (Some(pos), Some(text)) => {
+                let raw = &self.sections[pos].raw;
+                let leading = if kind == SectionKind::Description { leading_whitespace(raw) } else { "" };
+                let trailing = match trailing_whitespace(raw) {
+                    "" => "\n",
+                    trailing => trailing,
+                };
+                self.sections[pos].raw = format!("{}{}{}", leading, text, trailing);
+            }
+            (None, Some(text)) => self.insert_section(kind, text),
+            (Some(pos), None) => {
+                self.sections.remove(pos);
+            }
+            (None, None) => {}
+        }
+    }
+
+    fn update_notes(&mut self, old_notes: &[Note], new_notes: Option<&[Note]>) {
+        let new_notes = match new_notes {
+            Some(notes) if !notes.is_empty() => notes,
+            _ => return self.replace_section(SectionKind::Notes, None),
+        };
+
+        let pos = self.sections.iter().position(|s| s.kind == SectionKind::Notes);
+
+        // Appending notes keeps the existing section text and adds lines at its end
+        if let Some(pos) = pos {
+            if new_notes.starts_with(old_notes) {
+                let section = &mut self.sections[pos];
+                let trailing = trailing_whitespace(&section.raw).to_string();
+                let mut raw = section.raw[..section.raw.len() - trailing.len()].to_string();
+                for note in &new_notes[old_notes.len()..] {
+                    raw.push('\n');
+                    raw.push_str(&render_note(note));
+                }
+                raw.push_str(if trailing.is_empty() { "\n" } else { &trailing });
+                section.raw = raw;
+                return;
+            }
+        }
+
+        let lines: Vec<String> = new_notes.iter().map(render_note).collect();
+        self.replace_section(SectionKind::Notes, Some(format!("## Notes\n\n{}", lines.join("\n"))));
+    }
+
+    /// Insert a new section at its canonical position.
+    fn insert_section(&mut self, kind: SectionKind, text: String) {
+        let order = |k: &SectionKind| match k {
+            SectionKind::Preamble => 0,
+            SectionKind::Title => 1,
+            SectionKind::Description => 2,
+            SectionKind::Design => 3,
+            SectionKind::Acceptance => 4,
+            SectionKind::Extra(_) => 5,
+            SectionKind::Notes => 6,
+        };
+        let rank = order(&kind);
+        let pos = self.sections.iter()
+            .position(|s| order(&s.kind) > rank)
+            .unwrap_or(self.sections.len());
+
+        // Make sure the previous section is separated by a blank line
+        if pos > 0 {
+            let prev = &mut self.sections[pos - 1];
+            let newlines = prev.raw.len() - prev.raw.trim_end_matches(['\n', '\r']).len();
+            if newlines < 2 {
+                prev.raw.push_str(&"\n".repeat(2 - newlines));
+            }
+        }
+
+        // ...and from the next one, if any
+        let raw = if pos < self.sections.len() {
+            format!("{}\n\n", text)
+        } else {
+            format!("{}\n", text)
+        };
+        self.sections.insert(pos, Section { kind, raw });
+    }
+}
+
+/// Split file content into (frontmatter incl. delimiters, yaml, body).
+fn split_frontmatter(content: &str) -> Result<(&str, &str, &str)> {
+    if !content.starts_with("---") {
+        anyhow::bail!("Invalid ticket format - expected frontmatter with --- separators");
+    }
+    let after_open = 3;
+
+    // A closing delimiter on its own line
+    let mut search_from = after_open;
+    while let Some(offset) = content[search_from..].find("\n---") {
+        let start = search_from + offset + 1;
+        let end = start + 3;
+        let rest = &content[end..];
+        if rest.is_empty() || rest.starts_with('\n') || rest.starts_with("\r\n") {
+            return Ok((&content[..end], &content[after_open..start], rest));
+        }
+        search_from = end;
+    }
+
+    // Older tkr versions glued the closing delimiter onto the last YAML line
+    if let Some(offset) = content[after_open..].find("---") {
+        let start = after_open + offset;
+        let end = start + 3;
+        return Ok((&content[..end], &content[after_open..start], &content[end..]));
+    }
+
+    anyhow::bail!("Invalid ticket format - expected frontmatter with --- separators")
+}
+
+fn parse_sections(body: &str) -> Vec<Section> {
+    let mut sections = vec![Section { kind: SectionKind::Preamble, raw: String::new() }];
+    let mut in_fence = false;
+
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+
+        if !in_fence {
+            let has_title = sections.iter().any(|s| s.kind == SectionKind::Title);
+            let in_preamble = sections.len() == 1;
+
+            if let Some(heading) = trimmed.strip_prefix("## ") {
+                sections.push(Section { kind: SectionKind::from_heading(heading), raw: line.to_string() });
+                continue;
+            }
+            if trimmed.starts_with("# ") && !has_title && in_preamble {
+                sections.push(Section { kind: SectionKind::Title, raw: line.to_string() });
+                sections.push(Section { kind: SectionKind::Description, raw: String::new() });
+                continue;
+            }
+        }
+
+        if let Some(last) = sections.last_mut() {
+            last.raw.push_str(line);
+        }
+    }
+
+    sections
+}
+
+fn render_frontmatter(ticket: &Ticket) -> Result<String> {
+    let yaml_content = serde_yaml::to_string(ticket)?;
+    Ok(format!("---\n{}\n---", yaml_content.trim()))
+}
+
+fn render_note(note: &Note) -> String {
+    format!("**{}**: {}", format_note_timestamp(&note.timestamp), note.content)
+}
+
+fn format_note_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+fn trailing_whitespace(s: &str) -> &str {
+    &s[s.trim_end().len()..]
+}
+
+fn leading_whitespace(s: &str) -> &str {
+    &s[..s.len() - s.trim_start().len()]
+}
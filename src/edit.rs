@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::document::TicketDocument;
+use crate::ticket::{revision_of, ConflictError, TicketManager};
+
+/// Marks the lines `tkr edit` adds above the frontmatter; they are stripped
+/// before the file is parsed.
+const COMMENT: &str = "#";
+
+/// Open a ticket in `$VISUAL`/`$EDITOR` and save the result.
+///
+/// The editor works on a scratch copy. Nothing is written until the edited
+/// content parses and passes the same checks as other changes (workflow,
+/// custom fields, aliases); otherwise the editor is reopened with the error
+/// at the top. Emptying the file cancels the edit.
+pub fn run(manager: &TicketManager, id: &str) -> Result<()> {
+    let path = manager.ticket_path(id)?;
+    let original = fs::read_to_string(&path)?;
+    let mut before = TicketDocument::parse(&original)
+        .map_err(|e| anyhow::anyhow!("Invalid ticket {}: {}", id, e))?
+        .ticket;
+    before.revision = Some(revision_of(&original));
+
+    let mut scratch = Scratch::create(&before.id, &original)?;
+
+    loop {
+        launch_editor(&scratch.path)?;
+        let content = strip_error(&fs::read_to_string(&scratch.path)?);
+
+        if content.trim().is_empty() {
+            println!("Edit cancelled; {} not changed", before.id);
+            return Ok(());
+        }
+        if content == original {
+            println!("No changes to {}", before.id);
+            return Ok(());
+        }
+
+        let edited = match manager.validate_edit(&before, &content) {
+            Ok(edited) => edited,
+            Err(e) => {
+                // YAML errors go on to quote the whole frontmatter; the first line is enough
+                let error = e.to_string().lines().next().map(str::to_string);
+                fs::write(&scratch.path, with_error(&content, error.as_deref()))?;
+                continue;
+            }
+        };
+
+        return match manager.apply_edit(edited, &content) {
+            Ok(ticket) if ticket.status != before.status => {
+                println!("Updated {} -> {}", ticket.id, ticket.status);
+                Ok(())
+            }
+            Ok(ticket) => {
+                println!("Updated {}", ticket.id);
+                Ok(())
+            }
+            Err(e) if e.is::<ConflictError>() => {
+                // Don't throw the user's work away with the scratch file
+                scratch.keep = true;
+                Err(anyhow::anyhow!("{}; your edits were kept in {}", e, scratch.path.display()))
+            }
+            Err(e) => Err(e),
+        };
+    }
+}
+
+/// Run the user's editor on `path` and wait for it to exit.
+fn launch_editor(path: &Path) -> Result<()> {
+    let editor = ["VISUAL", "EDITOR"].iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+
+    // Through the shell, so editors with arguments (`code --wait`) work
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", editor))
+        .arg(&editor)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to run editor '{}'", editor))?;
+
+    if !status.success() {
+        anyhow::bail!("Editor '{}' failed ({}); ticket not changed", editor, status);
+    }
+    Ok(())
+}
+
+/// Put `error` as comment lines above the content.
+fn with_error(content: &str, error: Option<&str>) -> String {
+    let Some(error) = error else { return content.to_string() };
+
+    let mut out = format!("{} This ticket was not saved:\n", COMMENT);
+    out.push_str(&format!("{}   {}\n", COMMENT, error));
+    out.push_str(&format!("{} Fix the problem and save, or delete everything to cancel.\n", COMMENT));
+    out.push_str(content);
+    out
+}
+
+/// Drop comment lines before the frontmatter.
+fn strip_error(content: &str) -> String {
+    let mut rest = content;
+    while rest.starts_with(COMMENT) {
+        rest = rest.split_once('\n').map_or("", |(_, after)| after);
+    }
+    rest.to_string()
+}
+
+/// The scratch copy, removed when the edit is over unless `keep` is set.
+struct Scratch {
+    path: PathBuf,
+    keep: bool,
+}
+
+impl Scratch {
+    /// Create the scratch copy under an unpredictable name. The file must
+    /// not exist yet, so a symlink planted in the temp directory is never
+    /// followed.
+    fn create(id: &str, content: &str) -> Result<Self> {
+        let path = std::env::temp_dir()
+            .join(format!("tkr-{}-{}.md", id, uuid::Uuid::new_v4().simple()));
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        let scratch = Scratch { path, keep: false };
+        file.write_all(content.as_bytes())?;
+        Ok(scratch)
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
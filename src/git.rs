@@ -0,0 +1,163 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::lock::RepoLock;
+
+/// The `git:` section of `config.yml`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GitConfig {
+    /// Commit the files each command changed as soon as it finishes
+    pub auto_commit: bool,
+}
+
+/// How the operations of this process are committed.
+#[derive(Debug, Clone)]
+pub struct CommitMode {
+    pub tickets_dir: PathBuf,
+    /// Queue changes for `tkr commit` instead of committing them (`--no-commit`)
+    pub defer: bool,
+}
+
+/// One finished operation waiting to be committed, as a line of
+/// `.tickets/.pending`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pending {
+    /// e.g. `t-1d5a start (open -> in_progress)`
+    pub summary: String,
+    /// Changes to other tickets made by the same operation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+    /// Files the operation touched, relative to the tickets directory; the
+    /// old and new path of a moved ticket are both listed
+    pub paths: BTreeSet<PathBuf>,
+}
+
+fn pending_path(tickets_dir: &Path) -> PathBuf {
+    tickets_dir.join(".pending")
+}
+
+/// Queue a finished operation and, unless the mode defers, commit everything
+/// queued. A failed commit leaves the queue in place for `tkr commit`.
+pub fn finish(mode: &CommitMode, pending: Pending) -> Result<()> {
+    if pending.paths.is_empty() {
+        return Ok(());
+    }
+
+    let _lock = RepoLock::acquire(&mode.tickets_dir)?;
+    let mut line = serde_json::to_string(&pending)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(pending_path(&mode.tickets_dir))?
+        .write_all(line.as_bytes())?;
+
+    if !mode.defer {
+        commit(&mode.tickets_dir)?;
+    }
+    Ok(())
+}
+
+/// Commit the queued operations, staging only the files they touched.
+/// Returns the commit's subject, or `None` if there was nothing to commit.
+pub fn commit(tickets_dir: &Path) -> Result<Option<String>> {
+    let _lock = RepoLock::acquire(tickets_dir)?;
+    let queued = load(tickets_dir)?;
+    if queued.is_empty() {
+        return Ok(None);
+    }
+
+    let paths: BTreeSet<PathBuf> = queued.iter().flat_map(|p| p.paths.iter().cloned()).collect();
+    let paths = stageable(tickets_dir, &paths)?;
+    let changed = !paths.is_empty() && {
+        git(tickets_dir, &["add", "-A", "--"], &paths)?;
+        // Exits 1 when the staged files differ from HEAD
+        !Command::new("git")
+            .current_dir(tickets_dir)
+            .args(["diff", "--cached", "--quiet", "--"])
+            .args(&paths)
+            .status()
+            .context("Failed to run git")?
+            .success()
+    };
+
+    let subject = if changed {
+        let (subject, body) = message(&queued);
+        let mut args = vec!["commit", "--quiet", "-m", &subject];
+        if !body.is_empty() {
+            args.extend(["-m", &body]);
+        }
+        args.push("--");
+        git(tickets_dir, &args, &paths)?;
+        Some(subject)
+    } else {
+        None
+    };
+
+    // Committed, or there turned out to be nothing to commit
+    fs::remove_file(pending_path(tickets_dir))?;
+    Ok(subject)
+}
+
+fn load(tickets_dir: &Path) -> Result<Vec<Pending>> {
+    let content = match fs::read_to_string(pending_path(tickets_dir)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(content.lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// The subject and body of the commit for the queued operations.
+fn message(queued: &[Pending]) -> (String, String) {
+    if let [single] = queued {
+        return (format!("tkr: {}", single.summary), single.details.join("\n"));
+    }
+
+    let body = queued.iter()
+        .flat_map(|pending| {
+            std::iter::once(format!("- {}", pending.summary))
+                .chain(pending.details.iter().map(|detail| format!("  {}", detail)))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    (format!("tkr: {} changes", queued.len()), body)
+}
+
+/// The paths git can stage: tracked files (including ones since deleted) and
+/// untracked files that aren't ignored. A ticket created and moved again
+/// before a commit leaves behind a path git has never seen.
+fn stageable(tickets_dir: &Path, paths: &BTreeSet<PathBuf>) -> Result<Vec<PathBuf>> {
+    let mut found = BTreeSet::new();
+    for extra in [&[][..], &["--others", "--exclude-standard"][..]] {
+        let mut args = vec!["ls-files", "-z"];
+        args.extend(extra);
+        args.push("--");
+        let output = git(tickets_dir, &args, paths)?;
+        found.extend(output.split('\0').filter(|p| !p.is_empty()).map(PathBuf::from));
+    }
+    Ok(found.into_iter().collect())
+}
+
+/// Run git in the tickets directory, failing with its error output.
+fn git(dir: &Path, args: &[&str], paths: impl IntoIterator<Item = impl AsRef<std::ffi::OsStr>>) -> Result<String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .args(paths)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!("git {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
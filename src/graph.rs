@@ -0,0 +1,377 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::config::WorkflowConfig;
+use crate::ticket::Ticket;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum GraphFormat {
+    /// Indented ASCII tree
+    Tree,
+    Json,
+    /// Mermaid flowchart, for markdown docs and PRs
+    Mermaid,
+    /// Graphviz
+    Dot,
+}
+
+/// One ticket in a rendered dependency tree.
+#[derive(Debug, Serialize)]
+pub struct TreeNode {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+    /// Why the node was not expanded: "missing", "cycle" or "repeated"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<&'static str>,
+    pub deps: Vec<TreeNode>,
+    /// Tickets that depend on this one (root only, with `--full`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependents: Vec<TreeNode>,
+}
+
+/// Dependency relations between a set of tickets.
+pub struct DepGraph<'a> {
+    tickets: BTreeMap<&'a str, &'a Ticket>,
+    dependents: BTreeMap<&'a str, Vec<&'a str>>,
+    workflow: &'a WorkflowConfig,
+    /// Include closed tickets and reverse dependents
+    full: bool,
+}
+
+impl<'a> DepGraph<'a> {
+    pub fn new(tickets: &'a [Ticket], workflow: &'a WorkflowConfig, full: bool) -> Self {
+        let mut by_id = BTreeMap::new();
+        let mut dependents: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for ticket in tickets {
+            by_id.insert(ticket.id.as_str(), ticket);
+            for dep in &ticket.deps {
+                dependents.entry(dep.as_str()).or_default().push(ticket.id.as_str());
+            }
+        }
+        Self { tickets: by_id, dependents, workflow, full }
+    }
+
+    /// The tree of everything `id` (transitively) depends on.
+    pub fn tree(&self, id: &str) -> TreeNode {
+        let mut seen = BTreeSet::new();
+        let mut root = self.expand(id, false, &mut Vec::new(), &mut seen);
+        if self.full {
+            let mut seen = BTreeSet::new();
+            root.dependents = self.expand(id, true, &mut Vec::new(), &mut seen).deps;
+        }
+        root
+    }
+
+    /// Trees for every ticket nothing else depends on.
+    pub fn forest(&self) -> Vec<TreeNode> {
+        let mut seen = BTreeSet::new();
+        let visible: Vec<&Ticket> = self.tickets.values().copied().filter(|t| self.visible(t)).collect();
+        let is_root = |ticket: &Ticket| {
+            self.dependents.get(ticket.id.as_str())
+                .map(|ds| ds.iter().all(|d| !self.tickets.get(d).map(|t| self.visible(t)).unwrap_or(false)))
+                .unwrap_or(true)
+        };
+
+        let mut trees: Vec<TreeNode> = visible.iter()
+            .filter(|t| is_root(t))
+            .map(|t| self.expand(&t.id, false, &mut Vec::new(), &mut seen))
+            .collect();
+
+        // Tickets that are only reachable through a cycle have no root
+        for ticket in visible {
+            if !seen.contains(&ticket.id) {
+                trees.push(self.expand(&ticket.id, false, &mut Vec::new(), &mut seen));
+            }
+        }
+        trees
+    }
+
+    fn visible(&self, ticket: &Ticket) -> bool {
+        self.full || !self.workflow.is_closed(&ticket.status)
+    }
+
+    fn expand(&self, id: &str, reverse: bool, path: &mut Vec<String>, seen: &mut BTreeSet<String>) -> TreeNode {
+        let ticket = self.tickets.get(id);
+        let mut node = TreeNode {
+            id: id.to_string(),
+            title: ticket.map(|t| t.title.clone()),
+            status: ticket.map(|t| t.status.clone()),
+            priority: ticket.map(|t| t.priority),
+            note: None,
+            deps: Vec::new(),
+            dependents: Vec::new(),
+        };
+
+        let Some(ticket) = ticket else {
+            node.note = Some("missing");
+            return node;
+        };
+        if path.iter().any(|p| p == id) {
+            node.note = Some("cycle");
+            return node;
+        }
+        // Shared dependencies are expanded once and referenced afterwards
+        if !seen.insert(id.to_string()) {
+            node.note = Some("repeated");
+            return node;
+        }
+
+        let children: Vec<&str> = if reverse {
+            self.dependents.get(id).cloned().unwrap_or_default()
+        } else {
+            ticket.deps.iter().map(String::as_str).collect()
+        };
+
+        path.push(id.to_string());
+        for child in children {
+            let hidden = self.tickets.get(child).map(|t| !self.visible(t)).unwrap_or(false);
+            if !hidden {
+                node.deps.push(self.expand(child, reverse, path, seen));
+            }
+        }
+        path.pop();
+
+        node
+    }
+}
+
+/// Render trees in the requested format.
+pub fn render(trees: &[TreeNode], format: GraphFormat) -> Result<String> {
+    Ok(match format {
+        GraphFormat::Tree => render_tree(trees),
+        GraphFormat::Json => match trees {
+            [tree] => serde_json::to_string_pretty(tree)?,
+            trees => serde_json::to_string_pretty(trees)?,
+        },
+        GraphFormat::Mermaid => render_mermaid(trees),
+        GraphFormat::Dot => render_dot(trees),
+    })
+}
+
+fn render_tree(trees: &[TreeNode]) -> String {
+    let mut out = String::new();
+    for tree in trees {
+        out.push_str(&node_line(tree));
+        out.push('\n');
+        write_children(&tree.deps, "", &mut out);
+
+        if !tree.dependents.is_empty() {
+            out.push_str(&format!("Dependents of {}\n", tree.id));
+            write_children(&tree.dependents, "", &mut out);
+        }
+    }
+    out
+}
+
+fn write_children(children: &[TreeNode], prefix: &str, out: &mut String) {
+    for (i, child) in children.iter().enumerate() {
+        let last = i + 1 == children.len();
+        out.push_str(prefix);
+        out.push_str(if last { "└── " } else { "├── " });
+        out.push_str(&node_line(child));
+        out.push('\n');
+        let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+        write_children(&child.deps, &prefix, out);
+    }
+}
+
+fn node_line(node: &TreeNode) -> String {
+    let mut line = match (&node.status, node.priority, &node.title) {
+        (Some(status), Some(priority), Some(title)) => {
+            format!("{} [{} P{}] {}", node.id, status, priority, title)
+        }
+        _ => node.id.clone(),
+    };
+    if let Some(note) = node.note {
+        line.push_str(&format!(" ({})", note));
+    }
+    line
+}
+
+/// Every node and `depends on` edge in the trees, for the graph formats.
+fn collect_graph(trees: &[TreeNode]) -> (BTreeMap<&str, &TreeNode>, BTreeSet<(&str, &str)>) {
+    fn walk<'t>(
+        node: &'t TreeNode,
+        reverse: bool,
+        nodes: &mut BTreeMap<&'t str, &'t TreeNode>,
+        edges: &mut BTreeSet<(&'t str, &'t str)>,
+    ) {
+        // Prefer the expanded copy of a node for its label
+        let entry = nodes.entry(&node.id).or_insert(node);
+        if entry.note.is_some() && node.note.is_none() {
+            *entry = node;
+        }
+        for child in &node.deps {
+            if reverse {
+                edges.insert((&child.id, &node.id));
+            } else {
+                edges.insert((&node.id, &child.id));
+            }
+            walk(child, reverse, nodes, edges);
+        }
+    }
+
+    let mut nodes = BTreeMap::new();
+    let mut edges = BTreeSet::new();
+    for tree in trees {
+        walk(tree, false, &mut nodes, &mut edges);
+        for dependent in &tree.dependents {
+            edges.insert((&dependent.id, &tree.id));
+            walk(dependent, true, &mut nodes, &mut edges);
+        }
+    }
+    (nodes, edges)
+}
+
+fn render_mermaid(trees: &[TreeNode]) -> String {
+    let (nodes, edges) = collect_graph(trees);
+    // Ticket IDs can hold characters Mermaid IDs can't, and sanitizing them
+    // could make two tickets collide, so nodes are numbered instead
+    let node_ids: BTreeMap<&str, String> = nodes.keys()
+        .enumerate()
+        .map(|(i, id)| (*id, format!("n{}", i)))
+        .collect();
+
+    let mut out = String::from("graph TD\n");
+    for (id, node) in &nodes {
+        let label = match (&node.title, &node.status, node.priority) {
+            (Some(title), Some(status), Some(priority)) => {
+                format!("{}: {}<br/>{} · P{}", mermaid_escape(id), mermaid_escape(title), mermaid_escape(status), priority)
+            }
+            _ => format!("{} (missing)", mermaid_escape(id)),
+        };
+        out.push_str(&format!("    {}[\"{}\"]\n", node_ids[id], label));
+    }
+    for (from, to) in &edges {
+        out.push_str(&format!("    {} --> {}\n", node_ids[from], node_ids[to]));
+    }
+    out
+}
+
+/// Escape text for a quoted Mermaid label, where quotes, brackets, pipes and
+/// HTML would otherwise end the label or be rendered.
+fn mermaid_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("#quot;"),
+            '#' => out.push_str("#35;"),
+            '&' => out.push_str("#amp;"),
+            '<' => out.push_str("#lt;"),
+            '>' => out.push_str("#gt;"),
+            '[' => out.push_str("#91;"),
+            ']' => out.push_str("#93;"),
+            '{' => out.push_str("#123;"),
+            '}' => out.push_str("#125;"),
+            '|' => out.push_str("#124;"),
+            '`' => out.push_str("#96;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn render_dot(trees: &[TreeNode]) -> String {
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let (nodes, edges) = collect_graph(trees);
+
+    let mut out = String::from("digraph deps {\n    rankdir=LR;\n    node [shape=box];\n");
+    for (id, node) in &nodes {
+        let label = match (&node.title, &node.status, node.priority) {
+            (Some(title), Some(status), Some(priority)) => {
+                format!("{}\\n{}\\n{} · P{}", escape(id), escape(title), escape(status), priority)
+            }
+            _ => format!("{}\\n(missing)", escape(id)),
+        };
+        let style = if node.title.is_none() { ", style=dashed" } else { "" };
+        out.push_str(&format!("    \"{}\" [label=\"{}\"{}];\n", escape(id), label, style));
+    }
+    for (from, to) in &edges {
+        out.push_str(&format!("    \"{}\" -> \"{}\";\n", escape(from), escape(to)));
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Shortest chain of dependencies leading from `from` to `to`, both included.
+pub fn find_path(graph: &BTreeMap<String, Vec<String>>, from: &str, to: &str) -> Option<Vec<String>> {
+    let mut previous: BTreeMap<&str, &str> = BTreeMap::new();
+    let mut queue = std::collections::VecDeque::from([from]);
+    let mut visited = BTreeSet::from([from]);
+
+    while let Some(id) = queue.pop_front() {
+        if id == to {
+            let mut path = vec![to.to_string()];
+            let mut current = to;
+            while let Some(prev) = previous.get(current) {
+                path.push(prev.to_string());
+                current = prev;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for dep in graph.get(id).into_iter().flatten() {
+            if visited.insert(dep) {
+                previous.insert(dep, id);
+                queue.push_back(dep);
+            }
+        }
+    }
+
+    None
+}
+
+/// Find dependency cycles, each reported once and rotated to start at its
+/// smallest ID.
+pub fn find_cycles(graph: &BTreeMap<String, Vec<String>>) -> Vec<Vec<String>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mark {
+        Visiting,
+        Done,
+    }
+
+    fn visit<'a>(
+        id: &'a str,
+        graph: &'a BTreeMap<String, Vec<String>>,
+        marks: &mut BTreeMap<&'a str, Mark>,
+        stack: &mut Vec<&'a str>,
+        cycles: &mut BTreeSet<Vec<String>>,
+    ) {
+        marks.insert(id, Mark::Visiting);
+        stack.push(id);
+
+        for dep in graph.get(id).into_iter().flatten() {
+            match marks.get(dep.as_str()) {
+                Some(Mark::Visiting) => {
+                    let start = stack.iter().position(|s| *s == dep).unwrap_or(0);
+                    let mut cycle: Vec<String> = stack[start..].iter().map(|s| s.to_string()).collect();
+                    let min = cycle.iter().enumerate().min_by_key(|(_, id)| *id).map_or(0, |(i, _)| i);
+                    cycle.rotate_left(min);
+                    cycles.insert(cycle);
+                }
+                Some(Mark::Done) => {}
+                None if graph.contains_key(dep) => visit(dep, graph, marks, stack, cycles),
+                None => {}
+            }
+        }
+
+        stack.pop();
+        marks.insert(id, Mark::Done);
+    }
+
+    let mut marks = BTreeMap::new();
+    let mut cycles = BTreeSet::new();
+    for id in graph.keys() {
+        if !marks.contains_key(id.as_str()) {
+            visit(id, graph, &mut marks, &mut Vec::new(), &mut cycles);
+        }
+    }
+
+    cycles.into_iter().collect()
+}
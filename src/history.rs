@@ -0,0 +1,535 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::document::TicketDocument;
+use crate::git::{self, CommitMode, Pending};
+use crate::ticket::{revision_of, Ticket, TicketManager};
+
+/// Bookkeeping that changes on every save and isn't worth recording
+const IGNORED_FIELDS: &[&str] = &["updated"];
+
+thread_local! {
+    /// The operation this thread's changes belong to, and how deeply it is nested
+    static CURRENT: RefCell<Option<(OperationInfo, usize)>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Create,
+    Update,
+    Delete,
+}
+
+/// One line of `.tickets/.history.jsonl`: a change to one ticket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    /// Shared by every change made by the same command
+    pub op: String,
+    pub at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    pub command: String,
+    pub ticket: String,
+    pub kind: ChangeKind,
+    /// Frontmatter fields that changed; `null` means the field was absent
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub changes: BTreeMap<String, FieldChange>,
+    /// The operation this one reverted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undoes: Option<String>,
+    /// A file moved or removed without saving the ticket, e.g. by `doctor --fix`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<FileChange>,
+}
+
+/// A ticket file changed as a whole. Paths are relative to the tickets directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum FileChange {
+    Moved { from: PathBuf, to: PathBuf },
+    /// The content is kept so undo can write the file back
+    Removed { path: PathBuf, content: String },
+    /// Written back by undo
+    Restored { path: PathBuf },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub from: Value,
+    pub to: Value,
+}
+
+#[derive(Debug, Clone)]
+struct OperationInfo {
+    id: String,
+    command: String,
+    actor: Option<String>,
+    undoes: Option<String>,
+    /// Set when the operation's changes are committed to git
+    git: Option<CommitMode>,
+    /// Files written, moved or removed so far
+    touched: BTreeSet<PathBuf>,
+    /// Events recorded so far
+    events: Vec<Event>,
+}
+
+/// Groups the changes made while it is alive into one operation of the
+/// history, so `tkr undo` reverts them together. Operations started inside
+/// another one join it, like `RepoLock`.
+///
+/// With a `CommitMode`, the files the operation touched are committed to git
+/// when the outermost guard is dropped.
+#[derive(Debug)]
+pub struct Operation;
+
+impl Operation {
+    pub fn begin(command: &str, actor: impl FnOnce() -> Option<String>, git: Option<CommitMode>) -> Self {
+        Self::begin_inner(command, actor, git, None)
+    }
+
+    fn begin_inner(
+        command: &str,
+        actor: impl FnOnce() -> Option<String>,
+        git: Option<CommitMode>,
+        undoes: Option<String>,
+    ) -> Self {
+        CURRENT.with(|current| {
+            let mut current = current.borrow_mut();
+            match current.as_mut() {
+                Some((_, depth)) => *depth += 1,
+                None => {
+                    let info = OperationInfo {
+                        id: uuid::Uuid::new_v4().simple().to_string(),
+                        command: command.to_string(),
+                        actor: actor(),
+                        undoes,
+                        git,
+                        touched: BTreeSet::new(),
+                        events: Vec::new(),
+                    };
+                    *current = Some((info, 1));
+                }
+            }
+        });
+        Operation
+    }
+
+    fn with_current<T>(f: impl FnOnce(&mut OperationInfo) -> T) -> Option<T> {
+        CURRENT.with(|current| current.borrow_mut().as_mut().map(|(info, _)| f(info)))
+    }
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        let finished = CURRENT.with(|current| {
+            let mut current = current.borrow_mut();
+            let (_, depth) = current.as_mut()?;
+            *depth -= 1;
+            if *depth > 0 {
+                return None;
+            }
+            current.take().map(|(info, _)| info)
+        });
+
+        if let Some(OperationInfo { git: Some(mode), command, touched, events, .. }) = finished {
+            let pending = pending(&mode.tickets_dir, &command, &touched, &events);
+            // The tickets are already saved; a failed commit can be retried
+            if let Err(e) = git::finish(&mode, pending) {
+                eprintln!("Warning: Failed to commit '{}': {} (run `tkr commit` to retry)", command, e);
+            }
+        }
+    }
+}
+
+/// Note a file the current operation wrote, moved or removed, so it gets
+/// committed with it.
+pub fn touch(path: &Path) {
+    Operation::with_current(|info| info.touched.insert(path.to_path_buf()));
+}
+
+/// Everything an operation did to one ticket.
+struct NetChange<'a> {
+    id: &'a str,
+    kind: ChangeKind,
+    /// The first `from` and last `to` of the status
+    status: Option<(&'a Value, &'a Value)>,
+    fields: BTreeSet<&'a str>,
+}
+
+/// What to commit for a finished operation: its files, and a message naming
+/// the first ticket it changed and what happened to the others.
+fn pending(tickets_dir: &Path, command: &str, touched: &BTreeSet<PathBuf>, events: &[Event]) -> Pending {
+    let paths = touched.iter()
+        .filter_map(|path| path.strip_prefix(tickets_dir).ok())
+        .map(Path::to_path_buf)
+        .collect();
+
+    // Per ticket, in the order they were first changed
+    let mut tickets: Vec<NetChange> = Vec::new();
+    for event in events {
+        let index = match tickets.iter().position(|t| t.id == event.ticket) {
+            Some(index) => index,
+            None => {
+                tickets.push(NetChange { id: &event.ticket, kind: event.kind, status: None, fields: BTreeSet::new() });
+                tickets.len() - 1
+            }
+        };
+        let ticket = &mut tickets[index];
+        if event.kind != ChangeKind::Update {
+            ticket.kind = event.kind;
+        }
+        if let Some(change) = event.changes.get("status") {
+            let from = ticket.status.map_or(&change.from, |(from, _)| from);
+            ticket.status = Some((from, &change.to));
+        }
+        ticket.fields.extend(event.changes.keys().map(String::as_str));
+        if event.file.is_some() {
+            ticket.fields.insert("path");
+        }
+    }
+
+    let text = |value: &Value| value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+    let mut summary = command.to_string();
+    let mut details = Vec::new();
+    for (i, ticket) in tickets.iter().enumerate() {
+        let change = match (ticket.kind, ticket.status) {
+            (ChangeKind::Create, _) => "created".to_string(),
+            (ChangeKind::Delete, _) => "deleted".to_string(),
+            (_, Some((from, to))) => format!("{} -> {}", text(from), text(to)),
+            _ => ticket.fields.iter().copied().collect::<Vec<_>>().join(", "),
+        };
+        if i == 0 {
+            summary = match (ticket.kind, ticket.status) {
+                (ChangeKind::Update, Some(_)) => format!("{} {} ({})", ticket.id, command, change),
+                _ => format!("{} {}", ticket.id, command),
+            };
+        } else {
+            details.push(format!("{}: {}", ticket.id, change));
+        }
+    }
+
+    Pending { summary, details, paths }
+}
+
+pub fn path(tickets_dir: &Path) -> PathBuf {
+    tickets_dir.join(".history.jsonl")
+}
+
+/// Append a change to the history as part of the current operation, or as
+/// an operation of its own outside one. Changes that only touch ignored
+/// fields aren't recorded.
+pub fn record(
+    tickets_dir: &Path,
+    kind: ChangeKind,
+    id: &str,
+    before: Option<&Ticket>,
+    after: Option<&Ticket>,
+    actor: impl FnOnce() -> Option<String>,
+) -> Result<()> {
+    let changes = diff(before, after)?;
+    if kind == ChangeKind::Update && changes.is_empty() {
+        return Ok(());
+    }
+    append(tickets_dir, kind, id, changes, None, actor)
+}
+
+/// Append a file move or removal to the history, so undo can reverse it.
+pub fn record_file(
+    tickets_dir: &Path,
+    id: &str,
+    file: FileChange,
+    actor: impl FnOnce() -> Option<String>,
+) -> Result<()> {
+    append(tickets_dir, ChangeKind::Update, id, BTreeMap::new(), Some(file), actor)
+}
+
+fn append(
+    tickets_dir: &Path,
+    kind: ChangeKind,
+    id: &str,
+    changes: BTreeMap<String, FieldChange>,
+    file: Option<FileChange>,
+    actor: impl FnOnce() -> Option<String>,
+) -> Result<()> {
+    let _op = Operation::begin("save", actor, None);
+    let event = Operation::with_current(|info| {
+        let event = Event {
+            op: info.id.clone(),
+            at: Utc::now(),
+            actor: info.actor.clone(),
+            command: info.command.clone(),
+            ticket: id.to_string(),
+            kind,
+            changes,
+            undoes: info.undoes.clone(),
+            file,
+        };
+        info.events.push(event.clone());
+        info.touched.insert(path(tickets_dir));
+        event
+    }).expect("an operation was just started");
+
+    let mut line = serde_json::to_string(&event)?;
+    line.push('\n');
+    let mut file = OpenOptions::new().create(true).append(true).open(path(tickets_dir))?;
+    // One write per line, so concurrent appends never interleave within a line
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Read the history, oldest first. Lines that can't be parsed (e.g. one cut
+/// short by a crash) are skipped.
+pub fn load(tickets_dir: &Path) -> Result<Vec<Event>> {
+    let content = match fs::read_to_string(path(tickets_dir)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(content.lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn fields(ticket: Option<&Ticket>) -> Result<Map<String, Value>> {
+    match ticket.map(serde_json::to_value).transpose()? {
+        Some(Value::Object(map)) => Ok(map),
+        _ => Ok(Map::new()),
+    }
+}
+
+fn diff(before: Option<&Ticket>, after: Option<&Ticket>) -> Result<BTreeMap<String, FieldChange>> {
+    let (before, after) = (fields(before)?, fields(after)?);
+    let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    Ok(keys.into_iter()
+        .filter(|key| !IGNORED_FIELDS.contains(&key.as_str()))
+        .filter_map(|key| {
+            let from = before.get(key).cloned().unwrap_or(Value::Null);
+            let to = after.get(key).cloned().unwrap_or(Value::Null);
+            (from != to).then(|| (key.clone(), FieldChange { from, to }))
+        })
+        .collect())
+}
+
+/// What `undo` reverted.
+#[derive(Debug)]
+pub struct Undone {
+    pub command: String,
+    pub actor: Option<String>,
+    pub at: DateTime<Utc>,
+    pub tickets: Vec<String>,
+}
+
+/// Revert the most recent operation that hasn't been undone yet.
+///
+/// Fails without changing anything if one of its tickets has changed since,
+/// rather than overwriting the newer change. Deleted tickets are restored
+/// from their recorded fields. The undo is itself recorded.
+pub fn undo(manager: &TicketManager) -> Result<Undone> {
+    let _lock = manager.lock()?;
+    let events = load(&manager.tickets_dir)?;
+
+    let undone: BTreeSet<&str> = events.iter().filter_map(|e| e.undoes.as_deref()).collect();
+    let Some(last) = events.iter().rev().find(|e| e.undoes.is_none() && !undone.contains(e.op.as_str())) else {
+        anyhow::bail!("Nothing to undo");
+    };
+
+    // The net change per ticket: the first `from` and the last `to` of each field
+    let mut net: BTreeMap<&str, (Option<ChangeKind>, BTreeMap<&str, FieldChange>)> = BTreeMap::new();
+    for event in events.iter().filter(|e| e.op == last.op) {
+        let (kind, fields) = net.entry(&event.ticket).or_default();
+        *kind = match (*kind, event.kind) {
+            // Created and deleted again: nothing to restore either way
+            (Some(ChangeKind::Create), ChangeKind::Delete) => Some(ChangeKind::Update),
+            (Some(ChangeKind::Create), _) => Some(ChangeKind::Create),
+            (_, kind) => Some(kind),
+        };
+        for (field, change) in &event.changes {
+            fields.entry(field)
+                .and_modify(|net| net.to = change.to.clone())
+                .or_insert_with(|| change.clone());
+        }
+    }
+
+    // Check every ticket before touching any of them
+    let mut reverts = Vec::new();
+    for (id, (kind, changes)) in &net {
+        if changes.values().all(|change| change.from == change.to) {
+            continue;
+        }
+        let existing = manager.find_ticket_file(id);
+        if *kind == Some(ChangeKind::Delete) {
+            if existing.is_some() {
+                anyhow::bail!("Can't undo '{}': {} exists again. Edit it by hand instead", last.command, id);
+            }
+            reverts.push((*id, None, *kind, changes));
+            continue;
+        }
+
+        let path = existing
+            .ok_or_else(|| anyhow::anyhow!("Can't undo '{}': {} no longer exists", last.command, id))?;
+        let content = fs::read_to_string(&path)?;
+        let mut ticket = TicketDocument::parse(&content)?.ticket;
+        ticket.revision = Some(revision_of(&content));
+
+        let values = fields(Some(&ticket))?;
+        for (field, change) in changes {
+            if values.get(*field).unwrap_or(&Value::Null) != &change.to {
+                anyhow::bail!("Can't undo '{}': {} has changed since ({}). Edit it by hand instead",
+                    last.command, id, field);
+            }
+        }
+        reverts.push((*id, Some((path, ticket)), *kind, changes));
+    }
+
+    // Moved and removed files are put back newest first, after the fields:
+    // `doctor --fix` moves files before it saves tickets
+    let files: Vec<(&str, &FileChange)> = events.iter()
+        .filter(|e| e.op == last.op)
+        .filter_map(|e| Some((e.ticket.as_str(), e.file.as_ref()?)))
+        .rev()
+        .collect();
+    let mut exists: BTreeMap<&Path, bool> = BTreeMap::new();
+    for (id, file) in &files {
+        // Each path with whether it must exist now, and whether it will after
+        let steps = match file {
+            FileChange::Moved { from, to } => vec![(to, true, false), (from, false, true)],
+            FileChange::Removed { path, .. } => vec![(path, false, true)],
+            FileChange::Restored { .. } => Vec::new(),
+        };
+        for (path, expected, after) in steps {
+            let now = exists.get(path.as_path()).copied()
+                .unwrap_or_else(|| manager.tickets_dir.join(path).exists());
+            if now != expected {
+                anyhow::bail!("Can't undo '{}': {} has changed since ({}). Edit it by hand instead",
+                    last.command, id, path.display());
+            }
+            exists.insert(path, after);
+        }
+    }
+
+    let _op = Operation::begin_inner(
+        &format!("undo {}", last.command),
+        || manager.current_user(),
+        manager.commit_mode(),
+        Some(last.op.clone()),
+    );
+    for (id, existing, kind, changes) in reverts {
+        let (mut values, revision) = match existing {
+            Some((path, ticket)) if kind == Some(ChangeKind::Create) => {
+                fs::remove_file(&path)?;
+                touch(&path);
+                record(&manager.tickets_dir, ChangeKind::Delete, id, Some(&ticket), None, || None)?;
+                continue;
+            }
+            Some((_, ticket)) => (fields(Some(&ticket))?, ticket.revision),
+            // Deleted: every field was recorded, so the ticket is rebuilt from scratch
+            None => (Map::new(), None),
+        };
+
+        for (field, change) in changes {
+            match &change.from {
+                Value::Null => values.remove(*field),
+                from => values.insert(field.to_string(), from.clone()),
+            };
+        }
+        let mut reverted: Ticket = serde_json::from_value(Value::Object(values))?;
+        reverted.revision = revision;
+        // Moves the file back if the status changed
+        manager.save_ticket(&reverted)?;
+    }
+
+    for (id, file) in files {
+        let reverted = match file {
+            FileChange::Moved { from, to } => {
+                let (source, target) = (manager.tickets_dir.join(to), manager.tickets_dir.join(from));
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::rename(&source, &target)?;
+                touch(&source);
+                touch(&target);
+                FileChange::Moved { from: to.clone(), to: from.clone() }
+            }
+            FileChange::Removed { path, content } => {
+                let target = manager.tickets_dir.join(path);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&target, content)?;
+                touch(&target);
+                FileChange::Restored { path: path.clone() }
+            }
+            FileChange::Restored { .. } => continue,
+        };
+        record_file(&manager.tickets_dir, id, reverted, || None)?;
+    }
+
+    Ok(Undone {
+        command: last.command.clone(),
+        actor: last.actor.clone(),
+        at: last.at,
+        tickets: net.keys().map(|id| id.to_string()).collect(),
+    })
+}
+
+/// Print events grouped by operation.
+pub fn print(events: &[Event], color: bool) {
+    let mut previous: Option<&str> = None;
+    for event in events {
+        if previous != Some(event.op.as_str()) {
+            let time = event.at.format("%Y-%m-%d %H:%M:%S").to_string();
+            let actor = event.actor.as_deref().map(|a| format!(" by {}", a)).unwrap_or_default();
+            if color {
+                println!("{}  {}{}", time.dark_grey(), event.command.as_str().bold(), actor);
+            } else {
+                println!("{}  {}{}", time, event.command, actor);
+            }
+            previous = Some(&event.op);
+        }
+
+        match event.kind {
+            ChangeKind::Create => println!("    {}: created", event.ticket),
+            ChangeKind::Delete => println!("    {}: deleted", event.ticket),
+            ChangeKind::Update => {
+                for (field, change) in &event.changes {
+                    // The status line already says it; the JSON has the log entry
+                    if field == "status_log" && event.changes.contains_key("status") {
+                        continue;
+                    }
+                    println!("    {}: {}: {} -> {}", event.ticket, field, show(&change.from), show(&change.to));
+                }
+            }
+        }
+
+        match &event.file {
+            Some(FileChange::Moved { from, to }) =>
+                println!("    {}: moved {} -> {}", event.ticket, from.display(), to.display()),
+            Some(FileChange::Removed { path, .. }) => println!("    {}: removed {}", event.ticket, path.display()),
+            Some(FileChange::Restored { path }) => println!("    {}: restored {}", event.ticket, path.display()),
+            None => {}
+        }
+    }
+}
+
+fn show(value: &Value) -> String {
+    let text = match value {
+        Value::Null => "(none)".to_string(),
+        Value::String(s) => s.replace('\n', " "),
+        other => other.to_string(),
+    };
+    if text.chars().count() > 60 {
+        format!("{}...", text.chars().take(57).collect::<String>())
+    } else {
+        text
+    }
+}
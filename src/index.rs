@@ -0,0 +1,152 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::document::TicketDocument;
+use crate::ticket::{revision_of, Ticket};
+use crate::utils::temp_path;
+
+/// Bumped whenever the cached data would be read differently.
+const INDEX_VERSION: u32 = 3;
+
+/// Files changed this recently may be modified again within the same mtime
+/// tick, so their cached entry is confirmed by content hash instead.
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+/// Parsed tickets cached in `.tickets/.index`.
+///
+/// Entries are keyed by file path and reused while the file's size and
+/// modification time are unchanged, so listing thousands of tickets only
+/// parses the ones that changed. The index is a pure cache: a missing,
+/// corrupt or outdated file is rebuilt from the markdown, and it is safe to
+/// delete at any time.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TicketIndex {
+    version: u32,
+    entries: BTreeMap<String, Entry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    /// Nanoseconds since the epoch
+    modified: u64,
+    size: u64,
+    revision: String,
+    ticket: Ticket,
+}
+
+impl TicketIndex {
+    pub fn path(tickets_dir: &Path) -> PathBuf {
+        tickets_dir.join(".index")
+    }
+
+    /// Read the index, starting empty if it's missing or unreadable.
+    pub fn load(tickets_dir: &Path) -> Self {
+        fs::read(Self::path(tickets_dir))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Self>(&bytes).ok())
+            .filter(|index| index.version == INDEX_VERSION)
+            .unwrap_or_else(|| Self { version: INDEX_VERSION, entries: BTreeMap::new() })
+    }
+
+    /// Parse `files` (as `(id, path)`), reusing cached entries that are still
+    /// current. Returns each ticket or the error that kept it from loading,
+    /// in the order given, and whether the index changed.
+    pub fn refresh(&mut self, tickets_dir: &Path, files: &[(String, PathBuf)]) -> (Vec<Result<Ticket>>, bool) {
+        let now = SystemTime::now();
+        let mut changed = false;
+        let mut seen = BTreeSet::new();
+        let mut tickets = Vec::with_capacity(files.len());
+
+        for (id, path) in files {
+            let key = path.strip_prefix(tickets_dir).unwrap_or(path).to_string_lossy().replace('\\', "/");
+            let result = self.refresh_entry(&key, id, path, now, &mut changed);
+            seen.insert(key);
+            tickets.push(result);
+        }
+
+        let before = self.entries.len();
+        self.entries.retain(|key, _| seen.contains(key));
+        changed |= self.entries.len() != before;
+
+        (tickets, changed)
+    }
+
+    fn refresh_entry(&mut self, key: &str, id: &str, path: &Path, now: SystemTime, changed: &mut bool) -> Result<Ticket> {
+        let metadata = fs::metadata(path)?;
+        let modified_at = metadata.modified().unwrap_or(UNIX_EPOCH);
+        let modified = modified_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+        let size = metadata.len();
+        let racy = match now.duration_since(modified_at) {
+            Ok(age) => age < RACY_WINDOW,
+            // Modified "in the future": don't trust it
+            Err(_) => true,
+        };
+
+        if let Some(entry) = self.entries.get(key) {
+            if entry.modified == modified && entry.size == size && !racy {
+                return Ok(with_revision(entry));
+            }
+        }
+
+        let content = fs::read_to_string(path)?;
+        let revision = revision_of(&content);
+        if let Some(entry) = self.entries.get_mut(key) {
+            if entry.revision == revision {
+                if entry.modified != modified || entry.size != size {
+                    entry.modified = modified;
+                    entry.size = size;
+                    *changed = true;
+                }
+                return Ok(with_revision(entry));
+            }
+        }
+
+        let mut ticket = TicketDocument::parse(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid ticket {}: {}", id, e))?
+            .ticket;
+        ticket.revision = Some(revision.clone());
+
+        // Only cache tickets that come back from JSON unchanged; anything
+        // else (e.g. exotic YAML values) is simply parsed every time
+        if round_trips(&ticket) {
+            self.entries.insert(key.to_string(), Entry { modified, size, revision, ticket: ticket.clone() });
+            *changed = true;
+        } else if self.entries.remove(key).is_some() {
+            *changed = true;
+        }
+        Ok(ticket)
+    }
+
+    /// Write the index atomically. Failures are harmless (the next run just
+    /// reparses), so callers may ignore them.
+    pub fn save(&self, tickets_dir: &Path) -> Result<()> {
+        let path = Self::path(tickets_dir);
+        let tmp = temp_path(&path);
+        fs::write(&tmp, serde_json::to_vec(self)?)?;
+        if let Err(e) = fs::rename(&tmp, &path) {
+            let _ = fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+fn with_revision(entry: &Entry) -> Ticket {
+    let mut ticket = entry.ticket.clone();
+    ticket.revision = Some(entry.revision.clone());
+    ticket
+}
+
+fn round_trips(ticket: &Ticket) -> bool {
+    serde_json::to_value(ticket)
+        .and_then(serde_json::from_value::<Ticket>)
+        .map(|mut cached| {
+            cached.revision = ticket.revision.clone();
+            cached == *ticket
+        })
+        .unwrap_or(false)
+}
@@ -0,0 +1,1583 @@
+use regex::RegexBuilder;
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A jq-style filter, e.g. `map(select(.priority <= 1)) | group_by(.status)`.
+///
+/// Covers the parts of jq that scripts lean on: paths (`.a.b`, `.[0]`, `.[]`,
+/// `.[1:3]`), `|`, `,`, array and object construction, comparisons and
+/// arithmetic, `and`/`or`, `//`, `if`, `?`, string interpolation, `@csv`-style
+/// formats and the common builtins. Variables, `reduce`, assignment and
+/// user-defined functions are not supported.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    ast: Ast,
+}
+
+impl Filter {
+    pub fn parse(input: &str) -> Result<Self, JqError> {
+        let tokens = tokenize(input, 0, input.len())?;
+        if tokens.is_empty() {
+            return Ok(Self { ast: Ast::Identity });
+        }
+        let mut parser = Parser { input, tokens, pos: 0, end: input.trim_end().len() };
+        let ast = parser.parse_pipe(true)?;
+        parser.expect_end()?;
+        Ok(Self { ast })
+    }
+
+    /// Run the filter, collecting every output.
+    pub fn run(&self, input: &Value) -> Result<Vec<Value>, JqError> {
+        eval(&self.ast, input)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum JqError {
+    /// The filter doesn't parse; displayed with a caret under the column
+    Syntax { message: String, position: usize, input: String },
+    /// The filter failed on the input, e.g. `Cannot index string with "id"`
+    Runtime(String),
+}
+
+impl fmt::Display for JqError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JqError::Syntax { message, position, input } => {
+                let column = input[..*position].chars().count();
+                write!(f, "Invalid filter: {} at column {}\n  {}\n  {}^",
+                    message, column + 1, input, " ".repeat(column))
+            }
+            JqError::Runtime(message) => write!(f, "jq error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for JqError {}
+
+fn runtime<T>(message: String) -> Result<T, JqError> {
+    Err(JqError::Runtime(message))
+}
+
+#[derive(Debug, Clone)]
+enum Ast {
+    Identity,
+    /// `..`
+    Recurse,
+    Literal(Value),
+    /// A string with `\(...)` interpolations
+    Str(Vec<StrAst>),
+    /// `@csv`, `@tsv`, `@json`, `@text`
+    Format(String),
+    /// `target[key]`; the key is evaluated against `.`, not the target
+    Index(Box<Ast>, Box<Ast>),
+    Slice(Box<Ast>, Option<Box<Ast>>, Option<Box<Ast>>),
+    Iterate(Box<Ast>),
+    /// `expr?` - errors produce no output
+    Try(Box<Ast>),
+    Pipe(Box<Ast>, Box<Ast>),
+    Comma(Box<Ast>, Box<Ast>),
+    Neg(Box<Ast>),
+    Binary(BinOp, Box<Ast>, Box<Ast>),
+    And(Box<Ast>, Box<Ast>),
+    Or(Box<Ast>, Box<Ast>),
+    /// `a // b`
+    Alt(Box<Ast>, Box<Ast>),
+    Array(Option<Box<Ast>>),
+    Object(Vec<(Ast, Ast)>),
+    If(Box<Ast>, Box<Ast>, Option<Box<Ast>>),
+    Call(String, Vec<Ast>),
+}
+
+#[derive(Debug, Clone)]
+enum StrAst {
+    Lit(String),
+    Interp(Ast),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+const FORMATS: &[&str] = &["csv", "tsv", "json", "text"];
+
+// ---------------------------------------------------------------------------
+// Lexer
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Dot,
+    Recurse,
+    /// `.name`
+    Field(String),
+    Ident(String),
+    /// `@name`
+    Format(String),
+    /// `$name`, only lexed so `reduce`/`as` get the clearer error
+    Var(String),
+    Num(f64),
+    Str(Vec<StrPart>),
+    Sym(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum StrPart {
+    Lit(String),
+    /// Byte range of an interpolated expression
+    Interp(usize, usize),
+}
+
+const SYMBOLS: &[&str] = &[
+    "==", "!=", "<=", ">=", "//", "(", ")", "[", "]", "{", "}", "|", ",", ":", ";", "?",
+    "<", ">", "+", "-", "*", "/", "%",
+];
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Tokenize `input[start..end]`; positions stay relative to the whole input.
+fn tokenize(input: &str, start: usize, end: usize) -> Result<Vec<(Tok, usize)>, JqError> {
+    let error = |position: usize, message: String| JqError::Syntax { message, position, input: input.to_string() };
+    let read_ident = |from: usize| {
+        let len = input[from..end].find(|c: char| !is_ident_char(c)).unwrap_or(end - from);
+        (input[from..from + len].to_string(), from + len)
+    };
+
+    let mut tokens = Vec::new();
+    let mut i = start;
+    while i < end {
+        let rest = &input[i..end];
+        let c = rest.chars().next().unwrap_or_default();
+        let next = rest[c.len_utf8()..].chars().next();
+
+        if c.is_whitespace() {
+            i += c.len_utf8();
+            continue;
+        }
+        if c == '#' {
+            i += rest.find('\n').unwrap_or(rest.len());
+            continue;
+        }
+
+        let token_start = i;
+        let token = match c {
+            '.' if next == Some('.') => {
+                i += 2;
+                Tok::Recurse
+            }
+            '.' if next.is_some_and(is_ident_start) => {
+                let (name, after) = read_ident(i + 1);
+                i = after;
+                Tok::Field(name)
+            }
+            '.' => {
+                i += 1;
+                Tok::Dot
+            }
+            '"' => {
+                let (parts, after) = lex_string(input, i, end).map_err(|(pos, msg)| error(pos, msg))?;
+                i = after;
+                Tok::Str(parts)
+            }
+            '0'..='9' => {
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E'))
+                    .unwrap_or(rest.len());
+                let number = rest[..len].parse::<f64>()
+                    .map_err(|_| error(i, format!("invalid number '{}'", &rest[..len])))?;
+                i += len;
+                Tok::Num(number)
+            }
+            c if is_ident_start(c) => {
+                let (name, after) = read_ident(i);
+                i = after;
+                Tok::Ident(name)
+            }
+            '@' => {
+                let (name, after) = read_ident(i + 1);
+                if !FORMATS.contains(&name.as_str()) {
+                    return Err(error(i, format!("unknown format '@{}' (expected one of @{})", name, FORMATS.join(", @"))));
+                }
+                i = after;
+                Tok::Format(name)
+            }
+            '$' => {
+                let (name, after) = read_ident(i + 1);
+                i = after;
+                Tok::Var(name)
+            }
+            '=' if next != Some('=') => return Err(error(i, "assignment is not supported (did you mean '=='?)".to_string())),
+            _ => match SYMBOLS.iter().find(|s| rest.starts_with(**s)) {
+                Some(symbol) => {
+                    i += symbol.len();
+                    Tok::Sym(symbol)
+                }
+                None => return Err(error(i, format!("unexpected character '{}'", c))),
+            },
+        };
+        tokens.push((token, token_start));
+    }
+
+    Ok(tokens)
+}
+
+/// Lex a string literal starting at the opening quote; returns its parts and
+/// the offset just past the closing quote.
+fn lex_string(input: &str, start: usize, end: usize) -> Result<(Vec<StrPart>, usize), (usize, String)> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut i = start + 1;
+
+    while i < end {
+        let c = input[i..end].chars().next().unwrap_or_default();
+        match c {
+            '"' => {
+                if !literal.is_empty() || parts.is_empty() {
+                    parts.push(StrPart::Lit(literal));
+                }
+                return Ok((parts, i + 1));
+            }
+            '\\' => {
+                let escaped = input[i + 1..end].chars().next()
+                    .ok_or((i, "unterminated string".to_string()))?;
+                i += 1 + escaped.len_utf8();
+                match escaped {
+                    'n' => literal.push('\n'),
+                    't' => literal.push('\t'),
+                    'r' => literal.push('\r'),
+                    'b' => literal.push('\u{8}'),
+                    'f' => literal.push('\u{c}'),
+                    '"' | '\\' | '/' => literal.push(escaped),
+                    'u' => {
+                        let hex = input.get(i..i + 4).unwrap_or("");
+                        let ch = u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+                            .ok_or((i - 2, "invalid \\u escape".to_string()))?;
+                        literal.push(ch);
+                        i += 4;
+                    }
+                    '(' => {
+                        let close = find_interp_end(input, i, end)?;
+                        if !literal.is_empty() {
+                            parts.push(StrPart::Lit(std::mem::take(&mut literal)));
+                        }
+                        parts.push(StrPart::Interp(i, close));
+                        i = close + 1;
+                    }
+                    _ => return Err((i - 2, format!("invalid escape '\\{}'", escaped))),
+                }
+            }
+            c => {
+                literal.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+
+    Err((start, "unterminated string".to_string()))
+}
+
+/// Find the `)` closing an interpolation whose body starts at `start`.
+fn find_interp_end(input: &str, start: usize, end: usize) -> Result<usize, (usize, String)> {
+    let mut depth = 1;
+    let mut in_string = false;
+    let mut chars = input[start..end].char_indices();
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(start + offset);
+                }
+            }
+            _ => {}
+        }
+    }
+    Err((start - 2, "unclosed '\\('".to_string()))
+}
+
+// ---------------------------------------------------------------------------
+// Parser
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<(Tok, usize)>,
+    pos: usize,
+    /// Where "end of filter" errors point
+    end: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, position: usize, message: String) -> JqError {
+        JqError::Syntax { message, position, input: self.input.to_string() }
+    }
+
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos).map(|(tok, _)| tok)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Tok> {
+        self.tokens.get(self.pos + offset).map(|(tok, _)| tok)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |(_, start)| *start)
+    }
+
+    fn is_sym(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Sym(s)) if *s == symbol)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Ident(name)) if name == keyword)
+    }
+
+    fn unexpected(&self) -> JqError {
+        let message = match self.peek() {
+            None => "unexpected end of filter".to_string(),
+            Some(tok) => format!("unexpected {}", describe(tok)),
+        };
+        self.error(self.position(), message)
+    }
+
+    fn expect_sym(&mut self, symbol: &str) -> Result<(), JqError> {
+        if !self.is_sym(symbol) {
+            let found = self.peek().map_or("end of filter".to_string(), describe);
+            return Err(self.error(self.position(), format!("expected '{}', found {}", symbol, found)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), JqError> {
+        if !self.is_keyword(keyword) {
+            let found = self.peek().map_or("end of filter".to_string(), describe);
+            return Err(self.error(self.position(), format!("expected '{}', found {}", keyword, found)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn expect_end(&self) -> Result<(), JqError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.unexpected()),
+        }
+    }
+
+    // pipe := comma ("|" pipe)?   (objects values use the comma-free form)
+    fn parse_pipe(&mut self, allow_comma: bool) -> Result<Ast, JqError> {
+        let lhs = if allow_comma { self.parse_comma()? } else { self.parse_alt()? };
+        if self.is_sym("|") {
+            self.pos += 1;
+            let rhs = self.parse_pipe(allow_comma)?;
+            return Ok(Ast::Pipe(Box::new(lhs), Box::new(rhs)));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_comma(&mut self) -> Result<Ast, JqError> {
+        let mut ast = self.parse_alt()?;
+        while self.is_sym(",") {
+            self.pos += 1;
+            ast = Ast::Comma(Box::new(ast), Box::new(self.parse_alt()?));
+        }
+        Ok(ast)
+    }
+
+    fn parse_alt(&mut self) -> Result<Ast, JqError> {
+        let lhs = self.parse_or()?;
+        if self.is_sym("//") {
+            self.pos += 1;
+            return Ok(Ast::Alt(Box::new(lhs), Box::new(self.parse_alt()?)));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_or(&mut self) -> Result<Ast, JqError> {
+        let mut ast = self.parse_and()?;
+        while self.is_keyword("or") {
+            self.pos += 1;
+            ast = Ast::Or(Box::new(ast), Box::new(self.parse_and()?));
+        }
+        Ok(ast)
+    }
+
+    fn parse_and(&mut self) -> Result<Ast, JqError> {
+        let mut ast = self.parse_comparison()?;
+        while self.is_keyword("and") {
+            self.pos += 1;
+            ast = Ast::And(Box::new(ast), Box::new(self.parse_comparison()?));
+        }
+        Ok(ast)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Ast, JqError> {
+        let lhs = self.parse_additive()?;
+        let op = match self.peek() {
+            Some(Tok::Sym("==")) => BinOp::Eq,
+            Some(Tok::Sym("!=")) => BinOp::Ne,
+            Some(Tok::Sym("<")) => BinOp::Lt,
+            Some(Tok::Sym("<=")) => BinOp::Le,
+            Some(Tok::Sym(">")) => BinOp::Gt,
+            Some(Tok::Sym(">=")) => BinOp::Ge,
+            _ => return Ok(lhs),
+        };
+        self.pos += 1;
+        let rhs = self.parse_additive()?;
+        Ok(Ast::Binary(op, Box::new(lhs), Box::new(rhs)))
+    }
+
+    fn parse_additive(&mut self) -> Result<Ast, JqError> {
+        let mut ast = self.parse_multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(Tok::Sym("+")) => BinOp::Add,
+                Some(Tok::Sym("-")) => BinOp::Sub,
+                _ => return Ok(ast),
+            };
+            self.pos += 1;
+            ast = Ast::Binary(op, Box::new(ast), Box::new(self.parse_multiplicative()?));
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Ast, JqError> {
+        let mut ast = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Tok::Sym("*")) => BinOp::Mul,
+                Some(Tok::Sym("/")) => BinOp::Div,
+                Some(Tok::Sym("%")) => BinOp::Mod,
+                _ => return Ok(ast),
+            };
+            self.pos += 1;
+            ast = Ast::Binary(op, Box::new(ast), Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Ast, JqError> {
+        if self.is_sym("-") {
+            self.pos += 1;
+            return Ok(Ast::Neg(Box::new(self.parse_postfix()?)));
+        }
+        self.parse_postfix()
+    }
+
+    fn parse_postfix(&mut self) -> Result<Ast, JqError> {
+        let mut ast = self.parse_primary()?;
+        loop {
+            match self.peek() {
+                Some(Tok::Field(name)) => {
+                    let key = Ast::Literal(Value::String(name.clone()));
+                    self.pos += 1;
+                    ast = Ast::Index(Box::new(ast), Box::new(key));
+                }
+                // `.a."b"` and `.a.[0]`
+                Some(Tok::Dot) if matches!(self.peek_at(1), Some(Tok::Str(_)) | Some(Tok::Sym("["))) => {
+                    self.pos += 1;
+                    if let Some(Tok::Str(_)) = self.peek() {
+                        let key = self.parse_primary()?;
+                        ast = Ast::Index(Box::new(ast), Box::new(key));
+                    }
+                }
+                Some(Tok::Sym("[")) => {
+                    self.pos += 1;
+                    ast = self.parse_bracket_suffix(ast)?;
+                }
+                Some(Tok::Sym("?")) => {
+                    self.pos += 1;
+                    ast = Ast::Try(Box::new(ast));
+                }
+                _ => return Ok(ast),
+            }
+        }
+    }
+
+    /// After `target[`: `]`, `expr]`, `expr:expr]`, `:expr]` or `expr:]`.
+    fn parse_bracket_suffix(&mut self, target: Ast) -> Result<Ast, JqError> {
+        let target = Box::new(target);
+        if self.is_sym("]") {
+            self.pos += 1;
+            return Ok(Ast::Iterate(target));
+        }
+
+        let from = if self.is_sym(":") { None } else { Some(Box::new(self.parse_pipe(true)?)) };
+        if self.is_sym(":") {
+            self.pos += 1;
+            let to = if self.is_sym("]") { None } else { Some(Box::new(self.parse_pipe(true)?)) };
+            self.expect_sym("]")?;
+            return Ok(Ast::Slice(target, from, to));
+        }
+
+        self.expect_sym("]")?;
+        match from {
+            Some(key) => Ok(Ast::Index(target, key)),
+            None => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Ast, JqError> {
+        let Some((tok, start)) = self.tokens.get(self.pos).cloned() else {
+            return Err(self.unexpected());
+        };
+
+        let ast = match tok {
+            Tok::Dot => {
+                self.pos += 1;
+                if let Some(Tok::Str(_)) = self.peek() {
+                    let key = self.parse_primary()?;
+                    return Ok(Ast::Index(Box::new(Ast::Identity), Box::new(key)));
+                }
+                return Ok(Ast::Identity);
+            }
+            Tok::Recurse => Ast::Recurse,
+            Tok::Field(name) => Ast::Index(Box::new(Ast::Identity), Box::new(Ast::Literal(Value::String(name)))),
+            Tok::Num(number) => Ast::Literal(num(number)),
+            Tok::Format(name) => Ast::Format(name),
+            Tok::Str(parts) => self.string_ast(&parts)?,
+            Tok::Sym("(") => {
+                self.pos += 1;
+                let inner = self.parse_pipe(true)?;
+                self.expect_sym(")")?;
+                return Ok(inner);
+            }
+            Tok::Sym("[") => {
+                self.pos += 1;
+                if self.is_sym("]") {
+                    self.pos += 1;
+                    return Ok(Ast::Array(None));
+                }
+                let inner = self.parse_pipe(true)?;
+                self.expect_sym("]")?;
+                return Ok(Ast::Array(Some(Box::new(inner))));
+            }
+            Tok::Sym("{") => {
+                self.pos += 1;
+                return self.parse_object();
+            }
+            Tok::Sym(_) => return Err(self.unexpected()),
+            Tok::Var(_) => return Err(self.error(start, "variables are not supported".to_string())),
+            Tok::Ident(name) => {
+                self.pos += 1;
+                return match name.as_str() {
+                    "true" => Ok(Ast::Literal(Value::Bool(true))),
+                    "false" => Ok(Ast::Literal(Value::Bool(false))),
+                    "null" => Ok(Ast::Literal(Value::Null)),
+                    "if" => self.parse_if(),
+                    "try" => Ok(Ast::Try(Box::new(self.parse_postfix()?))),
+                    "reduce" | "foreach" | "def" | "as" | "label" => {
+                        Err(self.error(start, format!("'{}' is not supported", name)))
+                    }
+                    "then" | "elif" | "else" | "end" | "and" | "or" | "catch" => {
+                        Err(self.error(start, format!("unexpected '{}'", name)))
+                    }
+                    _ => self.parse_call(name, start),
+                };
+            }
+        };
+        self.pos += 1;
+        Ok(ast)
+    }
+
+    fn string_ast(&self, parts: &[StrPart]) -> Result<Ast, JqError> {
+        if let [StrPart::Lit(literal)] = parts {
+            return Ok(Ast::Literal(Value::String(literal.clone())));
+        }
+        let parts = parts.iter()
+            .map(|part| match part {
+                StrPart::Lit(literal) => Ok(StrAst::Lit(literal.clone())),
+                StrPart::Interp(start, end) => {
+                    let tokens = tokenize(self.input, *start, *end)?;
+                    let mut parser = Parser { input: self.input, tokens, pos: 0, end: *end };
+                    let ast = parser.parse_pipe(true)?;
+                    parser.expect_end()?;
+                    Ok(StrAst::Interp(ast))
+                }
+            })
+            .collect::<Result<_, JqError>>()?;
+        Ok(Ast::Str(parts))
+    }
+
+    // After `if` (or `elif`): cond then body (elif ...|else body end|end)
+    fn parse_if(&mut self) -> Result<Ast, JqError> {
+        let cond = self.parse_pipe(true)?;
+        self.expect_keyword("then")?;
+        let then = self.parse_pipe(true)?;
+        let otherwise = if self.is_keyword("elif") {
+            self.pos += 1;
+            Some(Box::new(self.parse_if()?))
+        } else if self.is_keyword("else") {
+            self.pos += 1;
+            let otherwise = self.parse_pipe(true)?;
+            self.expect_keyword("end")?;
+            Some(Box::new(otherwise))
+        } else {
+            self.expect_keyword("end")?;
+            None
+        };
+        Ok(Ast::If(Box::new(cond), Box::new(then), otherwise))
+    }
+
+    fn parse_call(&mut self, name: String, start: usize) -> Result<Ast, JqError> {
+        let mut args = Vec::new();
+        if self.is_sym("(") {
+            self.pos += 1;
+            loop {
+                args.push(self.parse_pipe(true)?);
+                if self.is_sym(";") {
+                    self.pos += 1;
+                } else {
+                    self.expect_sym(")")?;
+                    break;
+                }
+            }
+        }
+        if !is_builtin(&name, args.len()) {
+            return Err(self.error(start, format!("unknown function {}/{}", name, args.len())));
+        }
+        Ok(Ast::Call(name, args))
+    }
+
+    // After `{`: (key (":" value)?),* "}"
+    fn parse_object(&mut self) -> Result<Ast, JqError> {
+        let mut entries = Vec::new();
+        while !self.is_sym("}") {
+            let key_start = self.position();
+            let (key, shorthand) = match self.peek().cloned() {
+                Some(Tok::Ident(name)) => {
+                    self.pos += 1;
+                    (Ast::Literal(Value::String(name)), true)
+                }
+                Some(Tok::Str(parts)) => {
+                    self.pos += 1;
+                    (self.string_ast(&parts)?, true)
+                }
+                Some(Tok::Sym("(")) => {
+                    self.pos += 1;
+                    let key = self.parse_pipe(true)?;
+                    self.expect_sym(")")?;
+                    (key, false)
+                }
+                _ => return Err(self.error(key_start, "expected an object key".to_string())),
+            };
+
+            let value = if self.is_sym(":") {
+                self.pos += 1;
+                self.parse_pipe(false)?
+            } else if shorthand {
+                // `{id}` is `{id: .id}`
+                Ast::Index(Box::new(Ast::Identity), Box::new(key.clone()))
+            } else {
+                return Err(self.error(self.position(), "expected ':' after a computed key".to_string()));
+            };
+            entries.push((key, value));
+
+            if self.is_sym(",") {
+                self.pos += 1;
+            } else if !self.is_sym("}") {
+                return Err(self.error(self.position(), "expected ',' or '}'".to_string()));
+            }
+        }
+        self.pos += 1;
+        Ok(Ast::Object(entries))
+    }
+}
+
+fn describe(tok: &Tok) -> String {
+    match tok {
+        Tok::Dot => "'.'".to_string(),
+        Tok::Recurse => "'..'".to_string(),
+        Tok::Field(name) => format!("'.{}'", name),
+        Tok::Ident(name) => format!("'{}'", name),
+        Tok::Format(name) => format!("'@{}'", name),
+        Tok::Var(name) => format!("'${}'", name),
+        Tok::Num(number) => format!("'{}'", number),
+        Tok::Str(_) => "string".to_string(),
+        Tok::Sym(symbol) => format!("'{}'", symbol),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Evaluation
+
+fn eval(ast: &Ast, input: &Value) -> Result<Vec<Value>, JqError> {
+    match ast {
+        Ast::Identity => Ok(vec![input.clone()]),
+        Ast::Recurse => {
+            let mut out = Vec::new();
+            recurse(input, &mut out);
+            Ok(out)
+        }
+        Ast::Literal(value) => Ok(vec![value.clone()]),
+        Ast::Str(parts) => {
+            let mut strings = vec![String::new()];
+            for part in parts {
+                match part {
+                    StrAst::Lit(literal) => strings.iter_mut().for_each(|s| s.push_str(literal)),
+                    StrAst::Interp(inner) => {
+                        let values = eval(inner, input)?;
+                        strings = strings.iter()
+                            .flat_map(|s| values.iter().map(move |v| format!("{}{}", s, to_text(v))))
+                            .collect();
+                    }
+                }
+            }
+            Ok(strings.into_iter().map(Value::String).collect())
+        }
+        Ast::Format(name) => Ok(vec![Value::String(format_value(name, input)?)]),
+        Ast::Index(target, key) => {
+            let keys = eval(key, input)?;
+            let mut out = Vec::new();
+            for value in eval(target, input)? {
+                for key in &keys {
+                    out.push(index(&value, key)?);
+                }
+            }
+            Ok(out)
+        }
+        Ast::Slice(target, from, to) => {
+            let bound = |ast: &Option<Box<Ast>>| -> Result<Option<f64>, JqError> {
+                match ast {
+                    None => Ok(None),
+                    Some(ast) => match eval(ast, input)?.first() {
+                        Some(Value::Number(n)) => Ok(n.as_f64()),
+                        Some(Value::Null) | None => Ok(None),
+                        Some(other) => runtime(format!("Start and end indices of a slice must be numbers, not {}", type_name(other))),
+                    },
+                }
+            };
+            let (from, to) = (bound(from)?, bound(to)?);
+            eval(target, input)?.iter().map(|value| slice(value, from, to)).collect()
+        }
+        Ast::Iterate(target) => {
+            let mut out = Vec::new();
+            for value in eval(target, input)? {
+                out.extend(iterate(&value)?);
+            }
+            Ok(out)
+        }
+        Ast::Try(inner) => match eval(inner, input) {
+            Ok(values) => Ok(values),
+            Err(JqError::Runtime(_)) => Ok(Vec::new()),
+            Err(e) => Err(e),
+        },
+        Ast::Pipe(lhs, rhs) => {
+            let mut out = Vec::new();
+            for value in eval(lhs, input)? {
+                out.extend(eval(rhs, &value)?);
+            }
+            Ok(out)
+        }
+        Ast::Comma(lhs, rhs) => {
+            let mut out = eval(lhs, input)?;
+            out.extend(eval(rhs, input)?);
+            Ok(out)
+        }
+        Ast::Neg(inner) => eval(inner, input)?.iter()
+            .map(|value| match value.as_f64() {
+                Some(n) => Ok(num(-n)),
+                None => runtime(format!("{} ({}) cannot be negated", type_name(value), to_json(value))),
+            })
+            .collect(),
+        Ast::Binary(op, lhs, rhs) => {
+            // Like jq, the right-hand side varies slowest
+            let lefts = eval(lhs, input)?;
+            let mut out = Vec::new();
+            for right in eval(rhs, input)? {
+                for left in &lefts {
+                    out.push(binary(*op, left, &right)?);
+                }
+            }
+            Ok(out)
+        }
+        Ast::And(lhs, rhs) | Ast::Or(lhs, rhs) => {
+            let is_and = matches!(ast, Ast::And(..));
+            let mut out = Vec::new();
+            for left in eval(lhs, input)? {
+                if truthy(&left) != is_and {
+                    // Short-circuit: `false and _` / `true or _`
+                    out.push(Value::Bool(!is_and));
+                    continue;
+                }
+                out.extend(eval(rhs, input)?.iter().map(|right| Value::Bool(truthy(right))));
+            }
+            Ok(out)
+        }
+        Ast::Alt(lhs, rhs) => {
+            let values: Vec<Value> = eval(lhs, input).unwrap_or_default()
+                .into_iter()
+                .filter(truthy)
+                .collect();
+            if values.is_empty() { eval(rhs, input) } else { Ok(values) }
+        }
+        Ast::Array(None) => Ok(vec![Value::Array(Vec::new())]),
+        Ast::Array(Some(inner)) => Ok(vec![Value::Array(eval(inner, input)?)]),
+        Ast::Object(entries) => {
+            let mut objects = vec![Map::new()];
+            for (key, value) in entries {
+                let keys = eval(key, input)?;
+                let values = eval(value, input)?;
+                let mut next = Vec::new();
+                for object in &objects {
+                    for key in &keys {
+                        let Value::String(key) = key else {
+                            return runtime(format!("Object keys must be strings, not {}", type_name(key)));
+                        };
+                        for value in &values {
+                            let mut object = object.clone();
+                            object.insert(key.clone(), value.clone());
+                            next.push(object);
+                        }
+                    }
+                }
+                objects = next;
+            }
+            Ok(objects.into_iter().map(Value::Object).collect())
+        }
+        Ast::If(cond, then, otherwise) => {
+            let mut out = Vec::new();
+            for value in eval(cond, input)? {
+                if truthy(&value) {
+                    out.extend(eval(then, input)?);
+                } else if let Some(otherwise) = otherwise {
+                    out.extend(eval(otherwise, input)?);
+                } else {
+                    out.push(input.clone());
+                }
+            }
+            Ok(out)
+        }
+        Ast::Call(name, args) => call(name, args, input),
+    }
+}
+
+fn recurse(value: &Value, out: &mut Vec<Value>) {
+    out.push(value.clone());
+    match value {
+        Value::Array(items) => items.iter().for_each(|item| recurse(item, out)),
+        Value::Object(map) => map.values().for_each(|item| recurse(item, out)),
+        _ => {}
+    }
+}
+
+fn index(value: &Value, key: &Value) -> Result<Value, JqError> {
+    match (value, key) {
+        (Value::Object(map), Value::String(key)) => Ok(map.get(key).cloned().unwrap_or(Value::Null)),
+        (Value::Array(items), Value::Number(n)) => {
+            let n = n.as_f64().unwrap_or_default().floor() as i64;
+            let i = if n < 0 { n + items.len() as i64 } else { n };
+            Ok(usize::try_from(i).ok().and_then(|i| items.get(i)).cloned().unwrap_or(Value::Null))
+        }
+        (Value::Null, Value::String(_) | Value::Number(_)) => Ok(Value::Null),
+        (_, Value::String(key)) => runtime(format!("Cannot index {} with \"{}\"", type_name(value), key)),
+        _ => runtime(format!("Cannot index {} with {}", type_name(value), type_name(key))),
+    }
+}
+
+fn slice(value: &Value, from: Option<f64>, to: Option<f64>) -> Result<Value, JqError> {
+    let range = |len: usize| {
+        let clamp = |n: f64| {
+            let n = if n < 0.0 { n + len as f64 } else { n };
+            n.clamp(0.0, len as f64) as usize
+        };
+        let start = from.map_or(0, clamp);
+        let end = to.map_or(len, clamp).max(start);
+        start..end
+    };
+    match value {
+        Value::Null => Ok(Value::Null),
+        Value::Array(items) => Ok(Value::Array(items[range(items.len())].to_vec())),
+        Value::String(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            Ok(Value::String(chars[range(chars.len())].iter().collect()))
+        }
+        _ => runtime(format!("Cannot index {} with object", type_name(value))),
+    }
+}
+
+fn iterate(value: &Value) -> Result<Vec<Value>, JqError> {
+    match value {
+        Value::Array(items) => Ok(items.clone()),
+        Value::Object(map) => Ok(map.values().cloned().collect()),
+        _ => runtime(format!("Cannot iterate over {}", describe_value(value))),
+    }
+}
+
+fn binary(op: BinOp, left: &Value, right: &Value) -> Result<Value, JqError> {
+    let ordering = || compare(left, right);
+    let result = match op {
+        BinOp::Eq => Value::Bool(ordering() == Ordering::Equal),
+        BinOp::Ne => Value::Bool(ordering() != Ordering::Equal),
+        BinOp::Lt => Value::Bool(ordering() == Ordering::Less),
+        BinOp::Le => Value::Bool(ordering() != Ordering::Greater),
+        BinOp::Gt => Value::Bool(ordering() == Ordering::Greater),
+        BinOp::Ge => Value::Bool(ordering() != Ordering::Less),
+        BinOp::Add => match (left, right) {
+            (Value::Null, other) | (other, Value::Null) => other.clone(),
+            (Value::Number(a), Value::Number(b)) => num(a.as_f64().unwrap_or_default() + b.as_f64().unwrap_or_default()),
+            (Value::String(a), Value::String(b)) => Value::String(format!("{}{}", a, b)),
+            (Value::Array(a), Value::Array(b)) => Value::Array(a.iter().chain(b).cloned().collect()),
+            (Value::Object(a), Value::Object(b)) => {
+                let mut merged = a.clone();
+                merged.extend(b.iter().map(|(k, v)| (k.clone(), v.clone())));
+                Value::Object(merged)
+            }
+            _ => return operand_error(left, right, "added"),
+        },
+        BinOp::Sub => match (left, right) {
+            (Value::Number(a), Value::Number(b)) => num(a.as_f64().unwrap_or_default() - b.as_f64().unwrap_or_default()),
+            (Value::Array(a), Value::Array(b)) => Value::Array(
+                a.iter().filter(|item| !b.iter().any(|other| compare(item, other) == Ordering::Equal)).cloned().collect(),
+            ),
+            _ => return operand_error(left, right, "subtracted"),
+        },
+        BinOp::Mul => match (left, right) {
+            (Value::Number(a), Value::Number(b)) => num(a.as_f64().unwrap_or_default() * b.as_f64().unwrap_or_default()),
+            (Value::String(s), Value::Number(n)) | (Value::Number(n), Value::String(s)) => {
+                let times = n.as_f64().unwrap_or_default();
+                if times <= 0.0 { Value::Null } else { Value::String(s.repeat(times.ceil() as usize)) }
+            }
+            (Value::Object(_), Value::Object(_)) => deep_merge(left, right),
+            _ => return operand_error(left, right, "multiplied"),
+        },
+        BinOp::Div => match (left, right) {
+            (Value::Number(a), Value::Number(b)) => {
+                let divisor = b.as_f64().unwrap_or_default();
+                if divisor == 0.0 {
+                    return operand_error(left, right, "divided because the divisor is zero");
+                }
+                num(a.as_f64().unwrap_or_default() / divisor)
+            }
+            (Value::String(a), Value::String(b)) => split(a, b),
+            _ => return operand_error(left, right, "divided"),
+        },
+        BinOp::Mod => match (left.as_f64(), right.as_f64()) {
+            (Some(a), Some(b)) => {
+                let divisor = b as i64;
+                if divisor == 0 {
+                    return operand_error(left, right, "divided because the divisor is zero");
+                }
+                Value::from((a as i64) % divisor)
+            }
+            _ => return operand_error(left, right, "divided"),
+        },
+    };
+    Ok(result)
+}
+
+fn operand_error(left: &Value, right: &Value, action: &str) -> Result<Value, JqError> {
+    runtime(format!("{} ({}) and {} ({}) cannot be {}",
+        type_name(left), to_json(left), type_name(right), to_json(right), action))
+}
+
+fn deep_merge(left: &Value, right: &Value) -> Value {
+    match (left, right) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut merged = a.clone();
+            for (key, value) in b {
+                let value = match merged.get(key) {
+                    Some(existing) => deep_merge(existing, value),
+                    None => value.clone(),
+                };
+                merged.insert(key.clone(), value);
+            }
+            Value::Object(merged)
+        }
+        _ => right.clone(),
+    }
+}
+
+fn split(s: &str, separator: &str) -> Value {
+    if s.is_empty() {
+        return Value::Array(Vec::new());
+    }
+    Value::Array(s.split(separator).map(|part| Value::String(part.to_string())).collect())
+}
+
+fn is_builtin(name: &str, arity: usize) -> bool {
+    matches!((name, arity),
+        ("empty" | "not" | "length" | "keys" | "values" | "sort" | "unique" | "min" | "max" | "add"
+            | "first" | "last" | "reverse" | "flatten" | "any" | "all" | "ascii_downcase" | "ascii_upcase"
+            | "tostring" | "tonumber" | "tojson" | "fromjson" | "type" | "to_entries" | "from_entries"
+            | "floor" | "ceil" | "round" | "abs", 0)
+        | ("map" | "map_values" | "select" | "group_by" | "sort_by" | "unique_by" | "min_by" | "max_by"
+            | "has" | "contains" | "inside" | "startswith" | "endswith" | "ltrimstr" | "rtrimstr" | "split"
+            | "join" | "test" | "first" | "last" | "flatten" | "any" | "all" | "with_entries" | "range"
+            | "error" | "index", 1)
+        | ("test" | "limit" | "range", 2))
+}
+
+fn call(name: &str, args: &[Ast], input: &Value) -> Result<Vec<Value>, JqError> {
+    // Builtins taking a plain value run once per output of their argument
+    let with_arg = |f: &dyn Fn(&Value) -> Result<Value, JqError>| -> Result<Vec<Value>, JqError> {
+        eval(&args[0], input)?.iter().map(f).collect()
+    };
+    let one = |value: Value| Ok(vec![value]);
+
+    match (name, args.len()) {
+        ("empty", 0) => Ok(Vec::new()),
+        ("not", 0) => one(Value::Bool(!truthy(input))),
+        ("length", 0) => one(match input {
+            Value::Null => Value::from(0),
+            Value::Bool(_) => return runtime("boolean has no length".to_string()),
+            Value::Number(n) => num(n.as_f64().unwrap_or_default().abs()),
+            Value::String(s) => Value::from(s.chars().count()),
+            Value::Array(items) => Value::from(items.len()),
+            Value::Object(map) => Value::from(map.len()),
+        }),
+        ("keys", 0) => one(match input {
+            Value::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                Value::Array(keys.into_iter().map(|k| Value::String(k.clone())).collect())
+            }
+            Value::Array(items) => Value::Array((0..items.len()).map(Value::from).collect()),
+            _ => return runtime(format!("{} has no keys", describe_value(input))),
+        }),
+        ("values", 0) => Ok(if input.is_null() { Vec::new() } else { vec![input.clone()] }),
+        ("has", 1) => with_arg(&|key| match (input, key) {
+            (Value::Object(map), Value::String(key)) => Ok(Value::Bool(map.contains_key(key))),
+            (Value::Array(items), Value::Number(n)) => {
+                Ok(Value::Bool(n.as_f64().is_some_and(|n| n >= 0.0 && (n as usize) < items.len())))
+            }
+            _ => runtime(format!("Cannot check whether {} has a {} key", type_name(input), type_name(key))),
+        }),
+        ("map", 1) => {
+            let mut out = Vec::new();
+            for item in iterate(input)? {
+                out.extend(eval(&args[0], &item)?);
+            }
+            one(Value::Array(out))
+        }
+        ("map_values", 1) => {
+            let first_output = |item: &Value| -> Result<Option<Value>, JqError> {
+                Ok(eval(&args[0], item)?.into_iter().next())
+            };
+            one(match input {
+                Value::Object(map) => {
+                    let mut out = Map::new();
+                    for (key, item) in map {
+                        if let Some(value) = first_output(item)? {
+                            out.insert(key.clone(), value);
+                        }
+                    }
+                    Value::Object(out)
+                }
+                Value::Array(items) => {
+                    let mut out = Vec::new();
+                    for item in items {
+                        out.extend(first_output(item)?);
+                    }
+                    Value::Array(out)
+                }
+                _ => return runtime(format!("Cannot iterate over {}", describe_value(input))),
+            })
+        }
+        ("select", 1) => Ok(eval(&args[0], input)?
+            .iter()
+            .filter(|value| truthy(value))
+            .map(|_| input.clone())
+            .collect()),
+        ("sort", 0) => one(Value::Array(sorted_by_key(input, None)?.into_iter().map(|(_, v)| v).collect())),
+        ("sort_by", 1) => one(Value::Array(sorted_by_key(input, Some(&args[0]))?.into_iter().map(|(_, v)| v).collect())),
+        ("group_by", 1) => {
+            let mut groups: Vec<(Value, Vec<Value>)> = Vec::new();
+            for (key, value) in sorted_by_key(input, Some(&args[0]))? {
+                match groups.last_mut() {
+                    Some((last, group)) if compare(last, &key) == Ordering::Equal => group.push(value),
+                    _ => groups.push((key, vec![value])),
+                }
+            }
+            one(Value::Array(groups.into_iter().map(|(_, group)| Value::Array(group)).collect()))
+        }
+        ("unique", 0) | ("unique_by", 1) => {
+            let mut out: Vec<(Value, Value)> = Vec::new();
+            for (key, value) in sorted_by_key(input, args.first())? {
+                match out.last() {
+                    Some((last, _)) if compare(last, &key) == Ordering::Equal => {}
+                    _ => out.push((key, value)),
+                }
+            }
+            one(Value::Array(out.into_iter().map(|(_, v)| v).collect()))
+        }
+        ("min", 0) | ("min_by", 1) => one(sorted_by_key(input, args.first())?.into_iter().next().map(|(_, v)| v).unwrap_or(Value::Null)),
+        ("max", 0) | ("max_by", 1) => one(sorted_by_key(input, args.first())?.into_iter().last().map(|(_, v)| v).unwrap_or(Value::Null)),
+        ("add", 0) => {
+            let mut total = Value::Null;
+            for item in iterate(input)? {
+                total = binary(BinOp::Add, &total, &item)?;
+            }
+            one(total)
+        }
+        ("first", 0) => one(index(input, &Value::from(0))?),
+        ("last", 0) => one(index(input, &Value::from(-1))?),
+        ("first", 1) => Ok(eval(&args[0], input)?.into_iter().take(1).collect()),
+        ("last", 1) => Ok(eval(&args[0], input)?.into_iter().last().into_iter().collect()),
+        ("limit", 2) => {
+            let n = eval(&args[0], input)?.first().and_then(Value::as_f64).unwrap_or_default();
+            Ok(eval(&args[1], input)?.into_iter().take(n.max(0.0) as usize).collect())
+        }
+        ("reverse", 0) => one(match input {
+            Value::Array(items) => Value::Array(items.iter().rev().cloned().collect()),
+            Value::String(s) => Value::String(s.chars().rev().collect()),
+            Value::Null => Value::Array(Vec::new()),
+            _ => return runtime(format!("Cannot reverse {}", describe_value(input))),
+        }),
+        ("flatten", _) => {
+            let depth = match args.first() {
+                Some(arg) => eval(arg, input)?.first().and_then(Value::as_f64).unwrap_or_default() as usize,
+                None => usize::MAX,
+            };
+            let Value::Array(items) = input else {
+                return runtime(format!("Cannot flatten {}", describe_value(input)));
+            };
+            let mut out = Vec::new();
+            flatten(items, depth, &mut out);
+            one(Value::Array(out))
+        }
+        ("any", 0) => one(Value::Bool(iterate(input)?.iter().any(truthy))),
+        ("all", 0) => one(Value::Bool(iterate(input)?.iter().all(truthy))),
+        ("any", 1) | ("all", 1) => {
+            let mut results = Vec::new();
+            for item in iterate(input)? {
+                results.push(eval(&args[0], &item)?.iter().any(truthy));
+            }
+            one(Value::Bool(if name == "any" { results.contains(&true) } else { !results.contains(&false) }))
+        }
+        ("contains", 1) => with_arg(&|other| Ok(Value::Bool(contains(input, other)))),
+        ("inside", 1) => with_arg(&|other| Ok(Value::Bool(contains(other, input)))),
+        ("startswith", 1) | ("endswith", 1) | ("ltrimstr", 1) | ("rtrimstr", 1) => with_arg(&|other| {
+            let (Value::String(s), Value::String(affix)) = (input, other) else {
+                return match name {
+                    "ltrimstr" | "rtrimstr" => Ok(input.clone()),
+                    _ => runtime(format!("{}() requires string inputs", name)),
+                };
+            };
+            Ok(match name {
+                "startswith" => Value::Bool(s.starts_with(affix.as_str())),
+                "endswith" => Value::Bool(s.ends_with(affix.as_str())),
+                "ltrimstr" => Value::String(s.strip_prefix(affix.as_str()).unwrap_or(s).to_string()),
+                _ => Value::String(s.strip_suffix(affix.as_str()).unwrap_or(s).to_string()),
+            })
+        }),
+        ("ascii_downcase", 0) | ("ascii_upcase", 0) => match input {
+            Value::String(s) if name == "ascii_downcase" => one(Value::String(s.to_ascii_lowercase())),
+            Value::String(s) => one(Value::String(s.to_ascii_uppercase())),
+            _ => runtime(format!("{} cannot be case-converted", describe_value(input))),
+        },
+        ("split", 1) => with_arg(&|separator| match (input, separator) {
+            (Value::String(s), Value::String(separator)) => Ok(split(s, separator)),
+            _ => runtime("split input and separator must be strings".to_string()),
+        }),
+        ("join", 1) => with_arg(&|separator| {
+            let Value::String(separator) = separator else {
+                return runtime("join separator must be a string".to_string());
+            };
+            let mut parts = Vec::new();
+            for item in iterate(input)? {
+                parts.push(match item {
+                    Value::Null => String::new(),
+                    Value::String(s) => s,
+                    Value::Number(_) | Value::Bool(_) => to_json(&item),
+                    _ => return runtime(format!("Cannot join with {}", type_name(&item))),
+                });
+            }
+            Ok(Value::String(parts.join(separator)))
+        }),
+        ("test", _) => {
+            let flags = match args.get(1) {
+                Some(arg) => eval(arg, input)?.first().and_then(Value::as_str).unwrap_or("").to_string(),
+                None => String::new(),
+            };
+            with_arg(&|pattern| {
+                let (Value::String(s), Value::String(pattern)) = (input, pattern) else {
+                    return runtime(format!("{} cannot be matched, as it is not a string", describe_value(input)));
+                };
+                let regex = RegexBuilder::new(pattern)
+                    .case_insensitive(flags.contains('i'))
+                    .multi_line(flags.contains('s'))
+                    .build()
+                    .or_else(|e| runtime(format!("{} (at offset 0) is not a valid regex: {}", pattern, e)))?;
+                Ok(Value::Bool(regex.is_match(s)))
+            })
+        }
+        ("index", 1) => with_arg(&|needle| match (input, needle) {
+            (Value::String(s), Value::String(needle)) => Ok(s.find(needle.as_str())
+                .map(|byte| Value::from(s[..byte].chars().count()))
+                .unwrap_or(Value::Null)),
+            _ => index(input, needle),
+        }),
+        ("tostring", 0) => one(Value::String(to_text(input))),
+        ("tonumber", 0) => match input {
+            Value::Number(_) => one(input.clone()),
+            Value::String(s) => match s.trim().parse::<f64>() {
+                Ok(n) => one(num(n)),
+                Err(_) => runtime(format!("Cannot parse '{}' as a number", s)),
+            },
+            _ => runtime(format!("{} cannot be parsed as a number", describe_value(input))),
+        },
+        ("tojson", 0) => one(Value::String(to_json(input))),
+        ("fromjson", 0) => match input {
+            Value::String(s) => serde_json::from_str(s)
+                .map(|value| vec![value])
+                .or_else(|e| runtime(format!("{} (while parsing '{}')", e, s))),
+            _ => runtime(format!("{} cannot be parsed as JSON", describe_value(input))),
+        },
+        ("type", 0) => one(Value::String(type_name(input).to_string())),
+        ("to_entries", 0) => one(to_entries(input)?),
+        ("from_entries", 0) => one(from_entries(input)?),
+        ("with_entries", 1) => {
+            let mut entries = Vec::new();
+            for entry in iterate(&to_entries(input)?)? {
+                entries.extend(eval(&args[0], &entry)?);
+            }
+            one(from_entries(&Value::Array(entries))?)
+        }
+        ("range", _) => {
+            let bounds: Vec<f64> = args.iter()
+                .map(|arg| eval(arg, input)?.first().and_then(Value::as_f64)
+                    .ok_or_else(|| JqError::Runtime("Range bounds must be numeric".to_string())))
+                .collect::<Result<_, _>>()?;
+            let (start, end) = match bounds.as_slice() {
+                [end] => (0.0, *end),
+                [start, end] => (*start, *end),
+                _ => unreachable!("range takes one or two arguments"),
+            };
+            let mut out = Vec::new();
+            let mut n = start;
+            while n < end {
+                out.push(num(n));
+                n += 1.0;
+            }
+            Ok(out)
+        }
+        ("floor" | "ceil" | "round" | "abs", 0) => match input.as_f64() {
+            Some(n) => one(num(match name {
+                "floor" => n.floor(),
+                "ceil" => n.ceil(),
+                "round" => n.round(),
+                _ => n.abs(),
+            })),
+            None => runtime(format!("{} number required", describe_value(input))),
+        },
+        ("error", 1) => {
+            let message = eval(&args[0], input)?.first().map(to_text).unwrap_or_default();
+            runtime(message)
+        }
+        _ => runtime(format!("unknown function {}/{}", name, args.len())),
+    }
+}
+
+/// Pair each element of an array with its sort key (`[f]`, or the element
+/// itself) and sort stably by key.
+fn sorted_by_key(input: &Value, key: Option<&Ast>) -> Result<Vec<(Value, Value)>, JqError> {
+    let Value::Array(items) = input else {
+        return runtime(format!("{} cannot be sorted, as it is not an array", describe_value(input)));
+    };
+    let mut keyed = Vec::with_capacity(items.len());
+    for item in items {
+        let key = match key {
+            Some(key) => Value::Array(eval(key, item)?),
+            None => item.clone(),
+        };
+        keyed.push((key, item.clone()));
+    }
+    keyed.sort_by(|(a, _), (b, _)| compare(a, b));
+    Ok(keyed)
+}
+
+fn flatten(items: &[Value], depth: usize, out: &mut Vec<Value>) {
+    for item in items {
+        match item {
+            Value::Array(inner) if depth > 0 => flatten(inner, depth - 1, out),
+            _ => out.push(item.clone()),
+        }
+    }
+}
+
+/// jq's `contains`: substrings for strings, subsets for arrays and objects.
+fn contains(haystack: &Value, needle: &Value) -> bool {
+    match (haystack, needle) {
+        (Value::String(a), Value::String(b)) => a.contains(b.as_str()),
+        (Value::Array(a), Value::Array(b)) => b.iter().all(|n| a.iter().any(|h| contains(h, n))),
+        (Value::Object(a), Value::Object(b)) => {
+            b.iter().all(|(key, n)| a.get(key).is_some_and(|h| contains(h, n)))
+        }
+        _ => compare(haystack, needle) == Ordering::Equal,
+    }
+}
+
+fn to_entries(input: &Value) -> Result<Value, JqError> {
+    let Value::Object(map) = input else {
+        return runtime(format!("{} has no keys", describe_value(input)));
+    };
+    Ok(Value::Array(map.iter()
+        .map(|(key, value)| serde_json::json!({ "key": key, "value": value }))
+        .collect()))
+}
+
+fn from_entries(input: &Value) -> Result<Value, JqError> {
+    let mut map = Map::new();
+    for entry in iterate(input)? {
+        let key = ["key", "k", "name", "Name", "Key", "K"].iter()
+            .find_map(|k| entry.get(k).filter(|v| !v.is_null()));
+        let key = match key {
+            Some(Value::String(s)) => s.clone(),
+            Some(other @ (Value::Number(_) | Value::Bool(_))) => to_json(other),
+            _ => return runtime(format!("Cannot use {} as an object key", describe_value(&entry))),
+        };
+        let value = ["value", "v", "Value", "V"].iter()
+            .find_map(|k| entry.get(k))
+            .cloned()
+            .unwrap_or(Value::Null);
+        map.insert(key, value);
+    }
+    Ok(Value::Object(map))
+}
+
+fn format_value(name: &str, input: &Value) -> Result<String, JqError> {
+    match name {
+        "json" => Ok(to_json(input)),
+        "text" => Ok(to_text(input)),
+        "csv" | "tsv" => {
+            let Value::Array(items) = input else {
+                return runtime(format!("{} cannot be {}-formatted, only an array can be", describe_value(input), name));
+            };
+            let mut fields = Vec::with_capacity(items.len());
+            for item in items {
+                fields.push(match item {
+                    Value::Null => String::new(),
+                    Value::String(s) if name == "csv" => format!("\"{}\"", s.replace('"', "\"\"")),
+                    Value::String(s) => s.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r"),
+                    Value::Number(_) | Value::Bool(_) => to_json(item),
+                    _ => return runtime(format!("{} is not valid in a {} row", describe_value(item), name)),
+                });
+            }
+            Ok(fields.join(if name == "csv" { "," } else { "\t" }))
+        }
+        _ => runtime(format!("unknown format @{}", name)),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Values
+
+fn truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// `type (value)` for error messages, truncated like jq does.
+fn describe_value(value: &Value) -> String {
+    let json = to_json(value);
+    let json = if json.chars().count() > 30 {
+        format!("{}...", json.chars().take(27).collect::<String>())
+    } else {
+        json
+    };
+    format!("{} ({})", type_name(value), json)
+}
+
+fn to_json(value: &Value) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+/// Strings as-is, everything else as JSON; used by interpolation and `tostring`.
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        _ => to_json(value),
+    }
+}
+
+/// Integral results stay integers so `1 + 1` prints `2`, not `2.0`.
+fn num(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < 9e15 {
+        Value::from(n as i64)
+    } else {
+        serde_json::Number::from_f64(n).map(Value::Number).unwrap_or(Value::Null)
+    }
+}
+
+/// jq's total order: null < false < true < numbers < strings < arrays < objects.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::Bool(false) => 1,
+            Value::Bool(true) => 2,
+            Value::Number(_) => 3,
+            Value::String(_) => 4,
+            Value::Array(_) => 5,
+            Value::Object(_) => 6,
+        }
+    }
+
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => {
+            x.as_f64().unwrap_or_default().partial_cmp(&y.as_f64().unwrap_or_default()).unwrap_or(Ordering::Equal)
+        }
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Array(x), Value::Array(y)) => x.iter().zip(y)
+            .map(|(x, y)| compare(x, y))
+            .find(|o| *o != Ordering::Equal)
+            .unwrap_or_else(|| x.len().cmp(&y.len())),
+        (Value::Object(x), Value::Object(y)) => {
+            let mut x_keys: Vec<&String> = x.keys().collect();
+            let mut y_keys: Vec<&String> = y.keys().collect();
+            x_keys.sort();
+            y_keys.sort();
+            x_keys.cmp(&y_keys).then_with(|| {
+                x_keys.iter()
+                    .map(|key| compare(&x[*key], &y[*key]))
+                    .find(|o| *o != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal)
+            })
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn run(filter: &str, input: Value) -> Vec<Value> {
+        Filter::parse(filter).unwrap().run(&input).unwrap()
+    }
+
+    fn syntax_error(filter: &str) -> String {
+        Filter::parse(filter).unwrap_err().to_string()
+    }
+
+    fn runtime_error(filter: &str, input: Value) -> String {
+        Filter::parse(filter).unwrap().run(&input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn arithmetic_binds_tighter_than_comparison_and_logic() {
+        assert_eq!(run("1 + 2 * 3", Value::Null), [json!(7)]);
+        assert_eq!(run("(1 + 2) * 3", Value::Null), [json!(9)]);
+        assert_eq!(run("10 - 4 - 3", Value::Null), [json!(3)]);
+        assert_eq!(run("12 / 2 / 3", Value::Null), [json!(2)]);
+        assert_eq!(run("-1 + 3", Value::Null), [json!(2)]);
+        assert_eq!(run("1 + 1 == 2", Value::Null), [json!(true)]);
+        assert_eq!(run("true or false and false", Value::Null), [json!(true)]);
+        assert_eq!(run("(true or false) and false", Value::Null), [json!(false)]);
+        assert_eq!(run("1 < 2 and 3 > 4 or 5 == 5", Value::Null), [json!(true)]);
+    }
+
+    #[test]
+    fn pipe_binds_loosest_then_comma_then_alternative() {
+        assert_eq!(run(".a, .b | . + 1", json!({"a": 1, "b": 2})), [json!(2), json!(3)]);
+        assert_eq!(run(".a // .b, .c", json!({"b": 2, "c": 3})), [json!(2), json!(3)]);
+        assert_eq!(run(".a // .b | . * 10", json!({"b": 2})), [json!(20)]);
+        assert_eq!(run(".a or .b // \"x\"", json!({})), [json!("x")]);
+    }
+
+    #[test]
+    fn alternative_skips_null_false_and_errors() {
+        assert_eq!(run(".a // 1", json!({"a": null})), [json!(1)]);
+        assert_eq!(run(".a // 1", json!({"a": false})), [json!(1)]);
+        assert_eq!(run(".a // 1", json!({"a": 0})), [json!(0)]);
+        assert_eq!(run(".a.b // \"none\"", json!({"a": "str"})), [json!("none")]);
+        assert_eq!(run(".a // .b // .c", json!({"c": 3})), [json!(3)]);
+        // Every truthy output of the left side is kept
+        assert_eq!(run(".[] // 0", json!([null, 1, false, 2])), [json!(1), json!(2)]);
+        assert_eq!(run(".[] // 0", json!([null, false])), [json!(0)]);
+    }
+
+    #[test]
+    fn optional_and_try_swallow_runtime_errors() {
+        assert_eq!(run(".a?", json!("str")), Vec::<Value>::new());
+        assert_eq!(run(".[]?", json!(3)), Vec::<Value>::new());
+        assert_eq!(run("[.[] | .id?]", json!([{"id": 1}, "x", {"id": 2}])), [json!([1, 2])]);
+        assert_eq!(run("try .a.b", json!({"a": 1})), Vec::<Value>::new());
+        assert_eq!(run("try error(\"boom\")", Value::Null), Vec::<Value>::new());
+        assert_eq!(run("[.[] | try tonumber]", json!(["1", "x", "3"])), [json!([1, 3])]);
+        // Without them the error propagates
+        assert_eq!(runtime_error(".a", json!("str")), "jq error: Cannot index string with \"a\"");
+        assert_eq!(runtime_error("error(\"boom\")", Value::Null), "jq error: boom");
+    }
+
+    #[test]
+    fn paths_index_slice_and_iterate() {
+        let input = json!({
+            "a": {"b": [10, 20, 30, 40]},
+            "with space": 1,
+            "list": [{"id": "x"}, {"id": "y"}],
+        });
+        assert_eq!(run(".a.b[1]", input.clone()), [json!(20)]);
+        assert_eq!(run(".a.b[-1]", input.clone()), [json!(40)]);
+        assert_eq!(run(".a.b[1:3]", input.clone()), [json!([20, 30])]);
+        assert_eq!(run(".a.b[:1]", input.clone()), [json!([10])]);
+        assert_eq!(run(".a.b[-2:]", input.clone()), [json!([30, 40])]);
+        assert_eq!(run(".a[\"b\"][0]", input.clone()), [json!(10)]);
+        assert_eq!(run(".\"with space\"", input.clone()), [json!(1)]);
+        assert_eq!(run(".a.\"b\".[2]", input.clone()), [json!(30)]);
+        assert_eq!(run(".list[].id", input.clone()), [json!("x"), json!("y")]);
+        assert_eq!(run("[.list[] | .id]", input.clone()), [json!(["x", "y"])]);
+        assert_eq!(run(".missing.deeper", input.clone()), [Value::Null]);
+        assert_eq!(run(".a.b[9]", input.clone()), [Value::Null]);
+        assert_eq!(run("[..] | length", json!({"n": 1, "m": [2]})), [json!(4)]);
+    }
+
+    #[test]
+    fn unsupported_reduce_and_path_are_rejected_at_parse_time() {
+        assert_eq!(
+            syntax_error("reduce .[] as $x (0; . + $x)"),
+            "Invalid filter: 'reduce' is not supported at column 1\n  reduce .[] as $x (0; . + $x)\n  ^"
+        );
+        assert!(syntax_error("map(.) | foreach .[] as $x (0; 1)").contains("'foreach' is not supported at column 10"));
+        assert!(syntax_error("path(.a)").contains("unknown function path/1 at column 1"));
+        // `add` covers the common reduce use
+        assert_eq!(run("map(.n) | add", json!([{"n": 1}, {"n": 2}])), [json!(3)]);
+    }
+
+    #[test]
+    fn malformed_filters_point_at_the_problem() {
+        assert_eq!(syntax_error(".a |"), "Invalid filter: unexpected end of filter at column 5\n  .a |\n      ^");
+        assert_eq!(syntax_error("map(.a"), "Invalid filter: expected ')', found end of filter at column 7\n  map(.a\n        ^");
+        assert!(syntax_error(".a ]").contains("unexpected ']' at column 4"));
+        assert!(syntax_error("[1, 2").contains("expected ']', found end of filter at column 6"));
+        assert!(syntax_error("{a: 1").contains("expected ',' or '}' at column 6"));
+        assert!(syntax_error("if . then 1").contains("expected 'end', found end of filter at column 12"));
+        assert!(syntax_error("frobnicate").contains("unknown function frobnicate/0"));
+        assert!(syntax_error("\"unterminated").contains("unterminated string at column 1"));
+        assert!(syntax_error(". else").contains("unexpected 'else' at column 3"));
+        // Columns count characters, not bytes
+        assert!(syntax_error("\"é\" |").contains("at column 6"));
+    }
+}
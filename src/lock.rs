@@ -0,0 +1,82 @@
+use anyhow::Result;
+use fs2::FileExt;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How long to wait for another tkr process before giving up.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+const LOCK_POLL: Duration = Duration::from_millis(25);
+
+thread_local! {
+    /// Locks held by this thread, so nested operations don't deadlock on themselves
+    static HELD: RefCell<BTreeMap<PathBuf, (File, usize)>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+/// An exclusive advisory lock on `.tickets/.lock`, released on drop.
+///
+/// Every tkr process (CLI, TUI, web server) takes this lock around writes, so
+/// multi-file operations like status moves are never interleaved. The lock is
+/// re-entrant within a thread.
+#[derive(Debug)]
+pub struct RepoLock {
+    path: PathBuf,
+}
+
+impl RepoLock {
+    pub fn acquire(tickets_dir: &Path) -> Result<Self> {
+        let path = tickets_dir.join(".lock");
+
+        let reentered = HELD.with(|held| match held.borrow_mut().get_mut(&path) {
+            Some((_, depth)) => {
+                *depth += 1;
+                true
+            }
+            None => false,
+        });
+        if reentered {
+            return Ok(Self { path });
+        }
+
+        fs::create_dir_all(tickets_dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+
+        let started = Instant::now();
+        while file.try_lock_exclusive().is_err() {
+            if started.elapsed() > LOCK_TIMEOUT {
+                anyhow::bail!("Timed out waiting for {} (another tkr process is writing)", path.display());
+            }
+            std::thread::sleep(LOCK_POLL);
+        }
+
+        HELD.with(|held| held.borrow_mut().insert(path.clone(), (file, 1)));
+        Ok(Self { path })
+    }
+}
+
+impl Drop for RepoLock {
+    fn drop(&mut self) {
+        HELD.with(|held| {
+            let mut held = held.borrow_mut();
+            let released = match held.get_mut(&self.path) {
+                Some((_, depth)) => {
+                    *depth -= 1;
+                    *depth == 0
+                }
+                None => false,
+            };
+            if released {
+                // Closing the file releases the lock
+                if let Some((file, _)) = held.remove(&self.path) {
+                    let _ = FileExt::unlock(&file);
+                }
+            }
+        });
+    }
+}
@@ -1,6 +1,21 @@
+mod auth;
 mod cli;
+mod config;
+mod doctor;
+mod document;
+mod edit;
+mod git;
+mod graph;
+mod history;
+mod index;
+mod jq;
+mod lock;
+mod output;
+mod query;
+mod search;
 mod ticket;
 mod utils;
+mod watch;
 mod web;
 mod tui;
 
@@ -10,6 +25,7 @@ use std::path::PathBuf;
 
 use ticket::TicketManager;
 use cli::Cli;
+use config::load_config;
 use utils::find_tickets_dir;
 
 #[tokio::main]
@@ -23,15 +39,21 @@ async fn main() -> Result<()> {
         find_tickets_dir(cli.repo_root.map(|p| p.to_string()))?
     };
 
+    // Load config from the git root or XDG_CONFIG_HOME
+    let config = load_config()?.unwrap_or_default();
+    config.workflow.validate()?;
+
     // Create ticket manager
     let mut manager = TicketManager::new(
         tickets_dir,
         cli.project.clone(),
         cli.category.clone(),
+        config,
     );
+    manager.no_commit = cli.no_commit;
 
     // Execute command
-    cli.command.execute(&mut manager).await?;
+    cli.command.execute(&mut manager, &cli.output).await?;
 
     Ok(())
 }
@@ -0,0 +1,317 @@
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use crossterm::style::Stylize;
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::io::{IsTerminal, Write};
+
+use crate::config::Config;
+use crate::ticket::Ticket;
+
+/// Fields shown when `--fields` isn't given, for the formats that need a
+/// fixed set of columns.
+const DEFAULT_FIELDS: &[&str] = &["id", "status", "priority", "type", "title"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned columns, colored when writing to a terminal
+    Table,
+    /// One JSON array
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    Csv,
+    Tsv,
+    Yaml,
+    /// One line per ticket from `--template`
+    Template,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortKey {
+    Id,
+    Priority,
+    Created,
+    Updated,
+    Closed,
+    /// Workflow order
+    Status,
+    Title,
+}
+
+/// Output options shared by every command that prints tickets. They can
+/// also be given before the command (`tkr --format json ready`), where they
+/// apply unless the command sets its own.
+#[derive(Debug, Clone, Default, Args)]
+pub struct OutputOptions {
+    /// Output format [default: table]
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+    /// Comma-separated fields to show, e.g. id,title,assignee (custom fields too)
+    #[arg(long = "fields", id = "output_fields", value_name = "FIELDS", value_delimiter = ',')]
+    pub fields: Vec<String>,
+    /// Line template for --format template, e.g. '{id}: {title} [{assignee}]'
+    #[arg(long)]
+    pub template: Option<String>,
+    /// Sort tickets by this field
+    #[arg(long, value_enum)]
+    pub sort: Option<SortKey>,
+    /// Reverse the sort order
+    #[arg(long)]
+    pub reverse: bool,
+    /// Show at most this many tickets
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+impl OutputOptions {
+    /// These options, falling back to `global` for anything not set.
+    pub fn or(self, global: &OutputOptions) -> Self {
+        Self {
+            format: self.format.or(global.format),
+            fields: if self.fields.is_empty() { global.fields.clone() } else { self.fields },
+            template: self.template.or_else(|| global.template.clone()),
+            sort: self.sort.or(global.sort),
+            reverse: self.reverse || global.reverse,
+            limit: self.limit.or(global.limit),
+        }
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.format.unwrap_or(OutputFormat::Table)
+    }
+
+    /// Sort, limit and print `tickets`; `empty` is the table-format message
+    /// when there's nothing to show.
+    pub fn print(&self, tickets: Vec<Ticket>, config: &Config, empty: &str) -> Result<()> {
+        self.print_with(tickets, config, empty, DEFAULT_FIELDS, |_| Map::new())
+    }
+
+    /// Like `print`, adding the computed fields `extra` returns for each
+    /// ticket and showing `default_fields` when `--fields` isn't given.
+    pub fn print_with(
+        &self,
+        mut tickets: Vec<Ticket>,
+        config: &Config,
+        empty: &str,
+        default_fields: &[&str],
+        extra: impl Fn(&Ticket) -> Map<String, Value>,
+    ) -> Result<()> {
+        if let Some(key) = self.sort {
+            tickets.sort_by(|a, b| compare(a, b, key, config));
+        }
+        if self.reverse {
+            tickets.reverse();
+        }
+        if let Some(limit) = self.limit {
+            tickets.truncate(limit);
+        }
+
+        let records: Vec<Map<String, Value>> = tickets.iter()
+            .map(|ticket| match serde_json::to_value(ticket) {
+                Ok(Value::Object(mut map)) => {
+                    map.extend(extra(ticket));
+                    Ok(map)
+                }
+                Ok(_) => Ok(extra(ticket)),
+                Err(e) => Err(e),
+            })
+            .collect::<Result<_, _>>()?;
+
+        let mut out = std::io::stdout().lock();
+        self.write(&records, default_fields, empty, &mut out)
+    }
+
+    fn write(&self, records: &[Map<String, Value>], default_fields: &[&str], empty: &str, out: &mut impl Write) -> Result<()> {
+        let fields: Vec<&str> = if self.fields.is_empty() {
+            default_fields.to_vec()
+        } else {
+            self.fields.iter().map(|f| f.trim()).collect()
+        };
+        // JSON and YAML keep every field unless some were asked for
+        let project = |record: &Map<String, Value>| -> Value {
+            if self.fields.is_empty() {
+                return Value::Object(record.clone());
+            }
+            Value::Object(fields.iter()
+                .map(|f| (f.to_string(), record.get(*f).cloned().unwrap_or(Value::Null)))
+                .collect())
+        };
+
+        match self.format() {
+            OutputFormat::Table => {
+                if records.is_empty() {
+                    writeln!(out, "{}", empty)?;
+                } else {
+                    write_table(records, &fields, out)?;
+                }
+            }
+            OutputFormat::Json => {
+                let values: Vec<Value> = records.iter().map(project).collect();
+                writeln!(out, "{}", serde_json::to_string_pretty(&values)?)?;
+            }
+            OutputFormat::Ndjson => {
+                for record in records {
+                    writeln!(out, "{}", serde_json::to_string(&project(record))?)?;
+                }
+            }
+            OutputFormat::Yaml => {
+                let values: Vec<Value> = records.iter().map(project).collect();
+                write!(out, "{}", serde_yaml::to_string(&values)?)?;
+            }
+            OutputFormat::Csv | OutputFormat::Tsv => {
+                let (separator, escape): (&str, fn(&str) -> String) = match self.format() {
+                    OutputFormat::Csv => (",", csv_escape),
+                    _ => ("\t", tsv_escape),
+                };
+                let header: Vec<String> = fields.iter().map(|f| escape(f)).collect();
+                writeln!(out, "{}", header.join(separator))?;
+                for record in records {
+                    let row: Vec<String> = fields.iter().map(|f| escape(&cell(record.get(*f)))).collect();
+                    writeln!(out, "{}", row.join(separator))?;
+                }
+            }
+            OutputFormat::Template => {
+                let Some(template) = self.template.as_deref() else {
+                    anyhow::bail!("--format template needs a --template");
+                };
+                for record in records {
+                    writeln!(out, "{}", render_template(template, record))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn compare(a: &Ticket, b: &Ticket, key: SortKey, config: &Config) -> Ordering {
+    let status_rank = |ticket: &Ticket| {
+        config.workflow.statuses.iter()
+            .position(|s| *s == ticket.status)
+            .unwrap_or(usize::MAX)
+    };
+    // Tickets saved before `updated` was recorded fall back to created
+    let updated = |ticket: &Ticket| ticket.updated.unwrap_or(ticket.created);
+
+    let ordering = match key {
+        SortKey::Id => a.id.cmp(&b.id),
+        SortKey::Priority => a.priority.cmp(&b.priority),
+        SortKey::Created => a.created.cmp(&b.created),
+        SortKey::Updated => updated(a).cmp(&updated(b)),
+        SortKey::Closed => a.closed_or_created().cmp(&b.closed_or_created()),
+        SortKey::Status => status_rank(a).cmp(&status_rank(b)),
+        SortKey::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+    };
+    ordering.then_with(|| a.id.cmp(&b.id))
+}
+
+/// Render a field value as plain text: lists are comma-separated and
+/// missing values are empty.
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(items)) => items.iter()
+            .map(|item| match item {
+                // Typed links render as `type:id`
+                Value::Object(map) => match (map.get("type"), map.get("id")) {
+                    (Some(Value::String(kind)), Some(Value::String(id))) => format!("{}:{}", kind, id),
+                    _ => item.to_string(),
+                },
+                _ => cell(Some(item)),
+            })
+            .collect::<Vec<_>>()
+            .join(","),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn tsv_escape(field: &str) -> String {
+    field.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+/// Replace `{field}` placeholders; `{{` and `}}` are literal braces.
+fn render_template(template: &str, record: &Map<String, Value>) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        let tail = &rest[i..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            out.push_str(&tail[..1]);
+            rest = &tail[2..];
+        } else if let (true, Some(end)) = (tail.starts_with('{'), tail.find('}')) {
+            out.push_str(&cell(record.get(tail[1..end].trim())));
+            rest = &tail[end + 1..];
+        } else {
+            out.push_str(&tail[..1]);
+            rest = &tail[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn write_table(records: &[Map<String, Value>], fields: &[&str], out: &mut impl Write) -> Result<()> {
+    let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    let rows: Vec<Vec<String>> = records.iter()
+        .map(|record| fields.iter().map(|f| cell(record.get(*f)).replace('\n', " ")).collect())
+        .collect();
+    let widths: Vec<usize> = fields.iter().enumerate()
+        .map(|(i, field)| {
+            rows.iter().map(|row| row[i].chars().count()).chain([field.len()]).max().unwrap_or(0)
+        })
+        .collect();
+
+    // The last column isn't padded so long titles don't leave trailing spaces
+    let pad = |text: &str, i: usize| {
+        if i + 1 == fields.len() {
+            text.to_string()
+        } else {
+            format!("{:<width$}", text, width = widths[i])
+        }
+    };
+
+    let header: Vec<String> = fields.iter().enumerate()
+        .map(|(i, field)| {
+            let text = pad(&field.to_uppercase(), i);
+            if color { text.bold().to_string() } else { text }
+        })
+        .collect();
+    writeln!(out, "{}", header.join("  "))?;
+
+    for row in &rows {
+        let line: Vec<String> = row.iter().enumerate()
+            .map(|(i, value)| {
+                let text = pad(value, i);
+                if !color {
+                    return text;
+                }
+                match fields[i] {
+                    "id" => text.cyan().to_string(),
+                    "status" => match value.as_str() {
+                        "open" => text.green().to_string(),
+                        "in_progress" => text.yellow().to_string(),
+                        "closed" => text.blue().to_string(),
+                        "blocked" => text.red().to_string(),
+                        "ready" => text.cyan().to_string(),
+                        "icebox" | "archive" => text.dark_grey().to_string(),
+                        _ => text,
+                    },
+                    "priority" if matches!(value.as_str(), "0" | "1") => text.red().to_string(),
+                    _ => text,
+                }
+            })
+            .collect();
+        writeln!(out, "{}", line.join("  "))?;
+    }
+    Ok(())
+}
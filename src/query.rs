@@ -0,0 +1,491 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde_yaml::Value;
+use std::fmt;
+
+use crate::config::{value_to_string, Config};
+use crate::ticket::Ticket;
+
+/// A parsed ticket query, e.g.
+/// `status:open priority<=1 assignee:me created>-14d text~"auth"`.
+///
+/// Terms are ANDed; `OR`, `NOT` (or a leading `-`) and parentheses combine
+/// them. A bare word searches the ticket text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Compare { field: String, op: Op, value: String },
+    /// `has:field` - the field is set and not empty
+    Has(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// `:` or `=`
+    Eq,
+    /// `!=`
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// `~` - case-insensitive substring
+    Contains,
+}
+
+impl Op {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Op::Eq => ":",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Contains => "~",
+        }
+    }
+}
+
+/// A syntax error, displayed with a caret under the offending column.
+#[derive(Debug, Clone)]
+pub struct QueryError {
+    pub message: String,
+    /// Byte offset into `input`
+    pub position: usize,
+    pub input: String,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let column = self.input[..self.position].chars().count();
+        write!(f, "Invalid query: {} at column {}\n  {}\n  {}^",
+            self.message, column + 1, self.input, " ".repeat(column))
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// Values the query needs from outside the ticket.
+pub struct QueryContext<'a> {
+    pub config: &'a Config,
+    /// What `me` means in `assignee:me`
+    pub me: Option<String>,
+    pub now: DateTime<Utc>,
+}
+
+impl<'a> QueryContext<'a> {
+    pub fn new(config: &'a Config, me: Option<String>) -> Self {
+        Self { config, me, now: Utc::now() }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub expr: Expr,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self, QueryError> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { input, tokens, pos: 0 };
+        let expr = if parser.tokens.is_empty() {
+            Expr::And(Vec::new())
+        } else {
+            parser.parse_or()?
+        };
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(parser.error_at(token.start, "unexpected ')'"));
+        }
+        Ok(Self { expr })
+    }
+
+    pub fn matches(&self, ticket: &Ticket, ctx: &QueryContext) -> bool {
+        eval(&self.expr, ticket, ctx)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    Or,
+    Not,
+    Term(Expr),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, QueryError> {
+    let error = |position: usize, message: String| QueryError { message, position, input: input.to_string() };
+    let bytes = input.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        match c {
+            b'(' => {
+                tokens.push(Token { kind: TokenKind::LParen, start });
+                i += 1;
+            }
+            b')' => {
+                tokens.push(Token { kind: TokenKind::RParen, start });
+                i += 1;
+            }
+            b'-' if i + 1 < bytes.len() && !bytes[i + 1].is_ascii_whitespace() => {
+                tokens.push(Token { kind: TokenKind::Not, start });
+                i += 1;
+            }
+            _ => {
+                // A field name, an operator and a value, or just a bare word
+                let name_end = i + input[i..]
+                    .find(|ch: char| !(ch.is_alphanumeric() || ch == '_' || ch == '-' || ch == '.'))
+                    .unwrap_or(input.len() - i);
+                let rest = &input[name_end..];
+                let op = [("<=", Op::Le), (">=", Op::Ge), ("!=", Op::Ne), (":", Op::Eq), ("=", Op::Eq),
+                          ("<", Op::Lt), (">", Op::Gt), ("~", Op::Contains)]
+                    .into_iter()
+                    .find(|(symbol, _)| rest.starts_with(symbol));
+
+                match op {
+                    Some((symbol, op)) if name_end > i => {
+                        let field = input[i..name_end].to_lowercase();
+                        let value_start = name_end + symbol.len();
+                        let (value, end) = read_value(input, value_start).map_err(|(pos, msg)| error(pos, msg))?;
+                        if value.is_empty() {
+                            return Err(error(value_start, format!("expected a value after '{}{}'", field, op.as_str())));
+                        }
+                        let kind = if field == "has" {
+                            if op != Op::Eq {
+                                return Err(error(name_end, "'has' only supports ':'".to_string()));
+                            }
+                            Expr::Has(value.to_lowercase())
+                        } else {
+                            check_field(&field, op, &value).map_err(|msg| error(start, msg))?;
+                            Expr::Compare { field, op, value }
+                        };
+                        tokens.push(Token { kind: TokenKind::Term(kind), start });
+                        i = end;
+                    }
+                    Some((symbol, _)) => {
+                        return Err(error(start, format!("expected a field name before '{}'", symbol)));
+                    }
+                    None => {
+                        let (word, end) = read_value(input, i).map_err(|(pos, msg)| error(pos, msg))?;
+                        let kind = match word.as_str() {
+                            "OR" => TokenKind::Or,
+                            "AND" => {
+                                i = end;
+                                continue;
+                            }
+                            "NOT" => TokenKind::Not,
+                            _ => TokenKind::Term(Expr::Compare { field: "text".to_string(), op: Op::Contains, value: word }),
+                        };
+                        tokens.push(Token { kind, start });
+                        i = end;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Read a quoted or bare value starting at `start`; returns it and the end offset.
+fn read_value(input: &str, start: usize) -> Result<(String, usize), (usize, String)> {
+    let rest = &input[start..];
+    if let Some(quoted) = rest.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = quoted.char_indices();
+        while let Some((i, ch)) = chars.next() {
+            match ch {
+                '"' => return Ok((value, start + 1 + i + 1)),
+                '\\' => {
+                    if let Some((_, escaped)) = chars.next() {
+                        value.push(escaped);
+                    }
+                }
+                ch => value.push(ch),
+            }
+        }
+        return Err((start, "unterminated quoted value".to_string()));
+    }
+
+    let len = rest.find(|ch: char| ch.is_whitespace() || ch == '(' || ch == ')').unwrap_or(rest.len());
+    Ok((rest[..len].to_string(), start + len))
+}
+
+/// Reject comparisons that can never make sense, at parse time.
+fn check_field(field: &str, op: Op, value: &str) -> Result<(), String> {
+    let ordered = matches!(op, Op::Lt | Op::Le | Op::Gt | Op::Ge);
+    match field {
+        "created" | "updated" | "started" | "closed" if op != Op::Contains && parse_date(value, Utc::now()).is_none() => {
+            Err(format!("expected a date like 2024-01-31, today or -14d, got '{}'", value))
+        }
+        "text" if op != Op::Contains && op != Op::Eq => {
+            Err(format!("'text' only supports ':' and '~', not '{}'", op.as_str()))
+        }
+        "deps" | "links" | "status" | "type" | "assignee" if ordered => {
+            Err(format!("'{}' cannot be compared with '{}'", field, op.as_str()))
+        }
+        _ => Ok(()),
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error_at(&self, position: usize, message: &str) -> QueryError {
+        QueryError { message: message.to_string(), position, input: self.input.to_string() }
+    }
+
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+
+    fn end_position(&self) -> usize {
+        self.input.trim_end().len()
+    }
+
+    // or := and ("OR" and)*
+    fn parse_or(&mut self) -> Result<Expr, QueryError> {
+        let mut terms = vec![self.parse_and()?];
+        while self.peek() == Some(&TokenKind::Or) {
+            self.pos += 1;
+            terms.push(self.parse_and()?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Expr::Or(terms) })
+    }
+
+    // and := unary+
+    fn parse_and(&mut self) -> Result<Expr, QueryError> {
+        let mut terms = Vec::new();
+        while let Some(kind) = self.peek() {
+            if matches!(kind, TokenKind::Or | TokenKind::RParen) {
+                break;
+            }
+            terms.push(self.parse_unary()?);
+        }
+        if terms.is_empty() {
+            let position = self.tokens.get(self.pos).map_or(self.end_position(), |t| t.start);
+            return Err(self.error_at(position, "expected a search term"));
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Expr::And(terms) })
+    }
+
+    // unary := "NOT" unary | "(" or ")" | term
+    fn parse_unary(&mut self) -> Result<Expr, QueryError> {
+        let Some(token) = self.tokens.get(self.pos).cloned() else {
+            return Err(self.error_at(self.end_position(), "expected a search term"));
+        };
+        self.pos += 1;
+
+        match token.kind {
+            TokenKind::Not => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            TokenKind::LParen => {
+                let expr = self.parse_or()?;
+                if self.peek() != Some(&TokenKind::RParen) {
+                    return Err(self.error_at(token.start, "unclosed '('"));
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            TokenKind::Term(expr) => Ok(expr),
+            TokenKind::Or | TokenKind::RParen => Err(self.error_at(token.start, "expected a search term")),
+        }
+    }
+}
+
+fn eval(expr: &Expr, ticket: &Ticket, ctx: &QueryContext) -> bool {
+    match expr {
+        Expr::And(terms) => terms.iter().all(|t| eval(t, ticket, ctx)),
+        Expr::Or(terms) => terms.iter().any(|t| eval(t, ticket, ctx)),
+        Expr::Not(inner) => !eval(inner, ticket, ctx),
+        Expr::Has(field) => field_values(ticket, field).iter().any(|v| !v.is_empty()),
+        Expr::Compare { field, op, value } => compare(ticket, field, *op, value, ctx),
+    }
+}
+
+fn compare(ticket: &Ticket, field: &str, op: Op, wanted: &str, ctx: &QueryContext) -> bool {
+    let wanted = match (field, wanted) {
+        ("assignee", "me") => match &ctx.me {
+            Some(me) => me.clone(),
+            None => return false,
+        },
+        _ => wanted.to_string(),
+    };
+
+    if let Some(at) = timestamp(ticket, field) {
+        // Tickets that were never started or closed don't match any date
+        let Some(at) = at else { return op == Op::Ne };
+        if op == Op::Contains {
+            return at.to_rfc3339().contains(&wanted);
+        }
+        let Some(bound) = parse_date(&wanted, ctx.now) else { return false };
+        let date = at.date_naive();
+        return match op {
+            Op::Eq => date == bound,
+            Op::Ne => date != bound,
+            Op::Lt => date < bound,
+            Op::Le => date <= bound,
+            Op::Gt => date > bound,
+            _ => date >= bound,
+        };
+    }
+
+    // Custom fields compare the way `--field KEY=VALUE` does
+    if !BUILTIN_FIELDS.contains(&field) && matches!(op, Op::Eq | Op::Ne) {
+        let value = ticket.extra.get(field);
+        let found = wanted.split(',').any(|w| ctx.config.field_matches(field, value, w.trim()));
+        return found == (op == Op::Eq);
+    }
+
+    // `text:` behaves like `text~`
+    let op = if field == "text" && op == Op::Eq { Op::Contains } else { op };
+    let values = field_values(ticket, field);
+    let any_of = |actual: &String| wanted.split(',').any(|w| actual.eq_ignore_ascii_case(w.trim()));
+
+    match op {
+        Op::Eq => values.iter().any(any_of),
+        Op::Ne => !values.iter().any(any_of),
+        Op::Contains => {
+            let wanted = wanted.to_lowercase();
+            values.iter().any(|v| v.to_lowercase().contains(&wanted))
+        }
+        Op::Lt | Op::Le | Op::Gt | Op::Ge => values.iter().any(|actual| {
+            // Numeric when both sides are numbers, otherwise lexical (which
+            // also orders YYYY-MM-DD dates correctly)
+            let ordering = match (actual.parse::<f64>(), wanted.parse::<f64>()) {
+                (Ok(a), Ok(b)) => a.partial_cmp(&b),
+                _ => Some(actual.as_str().cmp(wanted.as_str())),
+            };
+            match ordering {
+                Some(o) => match op {
+                    Op::Lt => o.is_lt(),
+                    Op::Le => o.is_le(),
+                    Op::Gt => o.is_gt(),
+                    _ => o.is_ge(),
+                },
+                None => false,
+            }
+        }),
+    }
+}
+
+const BUILTIN_FIELDS: &[&str] = &[
+    "id", "title", "status", "type", "priority", "assignee", "project", "category", "parent",
+    "description", "design", "acceptance", "external_ref", "external-ref", "deps", "dep",
+    "links", "link", "alias", "aliases", "created", "updated", "started", "closed",
+    "created_by", "closed_by", "notes", "text",
+];
+
+/// The string values a field has on a ticket; lists yield one per item.
+fn field_values(ticket: &Ticket, field: &str) -> Vec<String> {
+    let opt = |v: &Option<String>| v.iter().cloned().collect::<Vec<_>>();
+    match field {
+        "id" => vec![ticket.id.clone()],
+        "title" => vec![ticket.title.clone()],
+        "status" => vec![ticket.status.clone()],
+        "type" => vec![ticket.issue_type.clone()],
+        "priority" => vec![ticket.priority.to_string()],
+        "assignee" => opt(&ticket.assignee),
+        "project" => opt(&ticket.project),
+        "category" => opt(&ticket.category),
+        "parent" => opt(&ticket.parent),
+        "description" => opt(&ticket.description),
+        "design" => opt(&ticket.design),
+        "acceptance" => opt(&ticket.acceptance),
+        "external_ref" | "external-ref" => opt(&ticket.external_ref),
+        "deps" | "dep" => ticket.deps.clone(),
+        "links" | "link" => ticket.links.iter().map(|l| l.id.clone()).collect(),
+        "alias" | "aliases" => ticket.aliases.clone(),
+        "created" => vec![ticket.created.to_rfc3339()],
+        "updated" | "started" | "closed" => timestamp(ticket, field).flatten().iter().map(|at| at.to_rfc3339()).collect(),
+        "created_by" => opt(&ticket.created_by),
+        "closed_by" => opt(&ticket.closed_by),
+        "notes" => ticket.notes.iter().flatten().map(|n| n.content.clone()).collect(),
+        "text" => {
+            let mut text = vec![ticket.id.clone(), ticket.title.clone()];
+            for field in [&ticket.description, &ticket.design, &ticket.acceptance] {
+                text.extend(field.iter().cloned());
+            }
+            text.extend(ticket.notes.iter().flatten().map(|n| n.content.clone()));
+            text
+        }
+        // Anything else is a custom field
+        name => match ticket.extra.get(name) {
+            Some(Value::Sequence(items)) => items.iter().map(value_to_string).collect(),
+            Some(value) => vec![value_to_string(value)],
+            None => Vec::new(),
+        },
+    }
+}
+
+/// The date fields: `Some` for a date field, holding the ticket's value if it has one.
+fn timestamp(ticket: &Ticket, field: &str) -> Option<Option<DateTime<Utc>>> {
+    match field {
+        "created" => Some(Some(ticket.created)),
+        // Saved before `updated` was recorded
+        "updated" => Some(Some(ticket.updated.unwrap_or(ticket.created))),
+        "started" => Some(ticket.started_at),
+        "closed" => Some(ticket.closed_at),
+        _ => None,
+    }
+}
+
+/// Parse a `--since` bound: a date as in queries, where an unsigned offset
+/// like `7d` also means seven days ago.
+pub fn parse_since(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    let relative = value.strip_suffix(|c: char| c.is_ascii_alphabetic())
+        .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()));
+    if relative {
+        parse_date(&format!("-{}", value), Utc::now())
+    } else {
+        parse_date(value, Utc::now())
+    }
+}
+
+/// Parse `YYYY-MM-DD`, `today`, `yesterday` or a relative offset like
+/// `-14d`, `-2w`, `-3m`, `-1y`.
+fn parse_date(value: &str, now: DateTime<Utc>) -> Option<NaiveDate> {
+    let today = now.date_naive();
+    match value {
+        "today" => return Some(today),
+        "yesterday" => return Some(today - Duration::days(1)),
+        _ => {}
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some(date);
+    }
+
+    let (split, unit) = value.char_indices().next_back()?;
+    let amount: i64 = value[..split].parse().ok()?;
+    let days_per_unit = match unit {
+        'd' => 1,
+        'w' => 7,
+        'm' => 30,
+        'y' => 365,
+        _ => return None,
+    };
+    // Offsets past the calendar's range are invalid, not a panic
+    let offset = Duration::try_days(amount.checked_mul(days_per_unit)?)?;
+    today.checked_add_signed(offset)
+}
@@ -0,0 +1,716 @@
+use crossterm::style::Stylize;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::ticket::Ticket;
+
+/// BM25 parameters
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+/// How much a match is worth relative to an exact one
+const PREFIX_WEIGHT: f64 = 0.7;
+const FUZZY_WEIGHT: f64 = 0.5;
+
+/// Query words too common to be worth matching on their own
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "in", "is", "it", "of", "on",
+    "or", "the", "to", "with",
+];
+
+/// Snippet context around the first highlight, in characters
+const SNIPPET_BEFORE: usize = 40;
+const SNIPPET_AFTER: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Field {
+    Id,
+    Title,
+    Alias,
+    Description,
+    Design,
+    Acceptance,
+    Notes,
+}
+
+impl Field {
+    const ALL: [Field; 7] = [
+        Field::Id, Field::Title, Field::Alias, Field::Description, Field::Design, Field::Acceptance, Field::Notes,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Id => "id",
+            Field::Title => "title",
+            Field::Alias => "alias",
+            Field::Description => "description",
+            Field::Design => "design",
+            Field::Acceptance => "acceptance",
+            Field::Notes => "notes",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "id" => Some(Field::Id),
+            "title" => Some(Field::Title),
+            "alias" | "aliases" => Some(Field::Alias),
+            "description" | "desc" => Some(Field::Description),
+            "design" => Some(Field::Design),
+            "acceptance" => Some(Field::Acceptance),
+            "notes" | "note" => Some(Field::Notes),
+            _ => None,
+        }
+    }
+
+    /// Matches in short, descriptive fields count for more
+    fn weight(self) -> f64 {
+        match self {
+            Field::Id | Field::Title => 3.0,
+            Field::Alias => 2.0,
+            Field::Description | Field::Design | Field::Acceptance => 1.0,
+            Field::Notes => 0.8,
+        }
+    }
+
+    fn text(self, ticket: &Ticket) -> String {
+        let opt = |v: &Option<String>| v.clone().unwrap_or_default();
+        match self {
+            Field::Id => ticket.id.clone(),
+            Field::Title => ticket.title.clone(),
+            Field::Alias => ticket.aliases.join(" "),
+            Field::Description => opt(&ticket.description),
+            Field::Design => opt(&ticket.design),
+            Field::Acceptance => opt(&ticket.acceptance),
+            Field::Notes => ticket.notes.iter().flatten()
+                .map(|note| note.content.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+/// A ranked search result.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub id: String,
+    pub title: String,
+    pub status: String,
+    pub score: f64,
+    pub snippets: Vec<Snippet>,
+}
+
+/// An excerpt of a matching field; `highlights` are character ranges into `text`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Snippet {
+    pub field: &'static str,
+    pub text: String,
+    pub highlights: Vec<(usize, usize)>,
+}
+
+impl SearchHit {
+    /// Print the hit with its snippets; matches are bold when `color` is set
+    /// and wrapped in `**` otherwise.
+    pub fn print(&self, color: bool) {
+        let header = format!("{}  {} ({})", self.id, self.title, self.status);
+        println!("{}  {:.2}", if color { header.bold().to_string() } else { header }, self.score);
+        for snippet in &self.snippets {
+            println!("    {}: {}", snippet.field, snippet.render(color));
+        }
+    }
+}
+
+impl Snippet {
+    fn render(&self, color: bool) -> String {
+        let mut out = String::new();
+        let mut highlights = self.highlights.iter().peekable();
+        let mut current = String::new();
+        let mut in_match = false;
+
+        let flush = |out: &mut String, current: &mut String, in_match: bool| {
+            if in_match {
+                let marked = if color { current.as_str().yellow().bold().to_string() } else { format!("**{}**", current) };
+                out.push_str(&marked);
+            } else {
+                out.push_str(current);
+            }
+            current.clear();
+        };
+
+        for (i, c) in self.text.chars().enumerate() {
+            while highlights.peek().is_some_and(|(_, end)| *end <= i) {
+                highlights.next();
+            }
+            let matched = highlights.peek().is_some_and(|(start, end)| *start <= i && i < *end);
+            if matched != in_match {
+                flush(&mut out, &mut current, in_match);
+                in_match = matched;
+            }
+            current.push(c);
+        }
+        flush(&mut out, &mut current, in_match);
+        out
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    term: String,
+    /// Character range in the field text
+    start: usize,
+    end: usize,
+}
+
+struct Document {
+    ticket: Ticket,
+    fields: Vec<(Field, String, Vec<Token>)>,
+    len: usize,
+}
+
+/// A matched token: the field and its position within it
+type Spot = (Field, usize);
+
+/// Score and matched tokens per document, for one query term
+type Matches = HashMap<usize, (f64, Vec<Spot>)>;
+
+#[derive(Debug, Clone)]
+struct Posting {
+    doc: usize,
+    field: Field,
+    /// Token positions within the field
+    positions: Vec<usize>,
+}
+
+/// An inverted index over ticket text, built once per search.
+pub struct SearchIndex {
+    docs: Vec<Document>,
+    postings: HashMap<String, Vec<Posting>>,
+    avg_len: f64,
+}
+
+impl SearchIndex {
+    pub fn new(tickets: Vec<Ticket>) -> Self {
+        let mut postings: HashMap<String, Vec<Posting>> = HashMap::new();
+        let mut docs = Vec::with_capacity(tickets.len());
+
+        for (doc, ticket) in tickets.into_iter().enumerate() {
+            let mut fields = Vec::new();
+            let mut len = 0;
+            for field in Field::ALL {
+                let text = field.text(&ticket);
+                let tokens = tokenize(&text);
+                if tokens.is_empty() {
+                    continue;
+                }
+                let mut positions: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+                for (position, token) in tokens.iter().enumerate() {
+                    positions.entry(token.term.as_str()).or_default().push(position);
+                }
+                for (term, positions) in positions {
+                    postings.entry(term.to_string()).or_default().push(Posting { doc, field, positions });
+                }
+                len += tokens.len();
+                fields.push((field, text, tokens));
+            }
+            docs.push(Document { ticket, fields, len });
+        }
+
+        let avg_len = if docs.is_empty() {
+            1.0
+        } else {
+            (docs.iter().map(|d| d.len).sum::<usize>() as f64 / docs.len() as f64).max(1.0)
+        };
+        Self { docs, postings, avg_len }
+    }
+
+    /// Search with a query like `login "sso redirect" notes:timeout`.
+    ///
+    /// Every term must match, exactly, as a prefix or with a typo or two;
+    /// `field:` limits a term to one field and quotes match a phrase. Results
+    /// are ranked by BM25, weighting title and ID matches highest.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let terms = parse_query(query);
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        // Per term: doc -> (score, matched (field, token index) pairs)
+        let mut matches: Vec<Matches> = Vec::new();
+        for term in &terms {
+            matches.push(if term.words.len() == 1 {
+                self.match_word(&term.words[0], term.field)
+            } else {
+                self.match_phrase(&term.words, term.field)
+            });
+        }
+
+        // Every term has to match
+        let (first, rest) = matches.split_first().expect("terms is not empty");
+        let mut hits: Vec<(usize, f64, Vec<Spot>)> = first.iter()
+            .filter_map(|(doc, (score, spots))| {
+                let mut score = *score;
+                let mut spots = spots.clone();
+                for other in rest {
+                    let (s, more) = other.get(doc)?;
+                    score += s;
+                    spots.extend(more.iter().copied());
+                }
+                Some((*doc, score, spots))
+            })
+            .collect();
+
+        hits.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| self.docs[b.0].ticket.created.cmp(&self.docs[a.0].ticket.created))
+                .then_with(|| self.docs[a.0].ticket.id.cmp(&self.docs[b.0].ticket.id))
+        });
+        hits.truncate(limit);
+
+        hits.into_iter()
+            .map(|(doc, score, spots)| {
+                let ticket = &self.docs[doc].ticket;
+                SearchHit {
+                    id: ticket.id.clone(),
+                    title: ticket.title.clone(),
+                    status: ticket.status.clone(),
+                    score,
+                    snippets: self.snippets(doc, &spots),
+                }
+            })
+            .collect()
+    }
+
+    fn idf(&self, term: &str) -> f64 {
+        let n = self.docs.len() as f64;
+        let df = self.postings.get(term)
+            .map(|postings| {
+                let mut docs: Vec<usize> = postings.iter().map(|p| p.doc).collect();
+                docs.dedup();
+                docs.len()
+            })
+            .unwrap_or(0) as f64;
+        (1.0 + (n - df + 0.5) / (df + 0.5)).ln()
+    }
+
+    fn bm25(&self, doc: usize, weighted_tf: f64) -> f64 {
+        let len = self.docs[doc].len as f64;
+        weighted_tf * (K1 + 1.0) / (weighted_tf + K1 * (1.0 - B + B * len / self.avg_len))
+    }
+
+    /// Vocabulary terms a query word matches, with how much each is worth.
+    fn expand(&self, word: &str) -> Vec<(&str, f64)> {
+        let max_edits = match word.chars().count() {
+            0..=3 => 0,
+            4..=7 => 1,
+            _ => 2,
+        };
+        let mut expansions = Vec::new();
+        for term in self.postings.keys() {
+            if term == word {
+                expansions.push((term.as_str(), 1.0));
+            } else if word.chars().count() >= 3 && term.starts_with(word) {
+                expansions.push((term.as_str(), PREFIX_WEIGHT));
+            } else if max_edits > 0 {
+                if let Some(distance) = edit_distance(word, term, max_edits) {
+                    expansions.push((term.as_str(), FUZZY_WEIGHT / distance as f64));
+                }
+            }
+        }
+        expansions
+    }
+
+    fn match_word(&self, word: &str, field: Option<Field>) -> Matches {
+        let mut results: Matches = HashMap::new();
+        for (term, weight) in self.expand(word) {
+            let idf = self.idf(term);
+            let mut per_doc: Matches = HashMap::new();
+            for posting in &self.postings[term] {
+                if field.is_some_and(|f| f != posting.field) {
+                    continue;
+                }
+                let entry = per_doc.entry(posting.doc).or_default();
+                entry.0 += posting.field.weight() * posting.positions.len() as f64;
+                entry.1.extend(posting.positions.iter().map(|p| (posting.field, *p)));
+            }
+            for (doc, (tf, spots)) in per_doc {
+                let score = weight * idf * self.bm25(doc, tf);
+                let best = results.entry(doc).or_insert((0.0, Vec::new()));
+                // The best expansion scores; all of them are highlighted
+                best.0 = best.0.max(score);
+                best.1.extend(spots);
+            }
+        }
+        results
+    }
+
+    fn match_phrase(&self, words: &[String], field: Option<Field>) -> Matches {
+        let mut results = HashMap::new();
+        let Some(first) = self.postings.get(&words[0]) else { return results };
+        let idf: f64 = words.iter().map(|w| self.idf(w)).sum();
+
+        for posting in first {
+            if field.is_some_and(|f| f != posting.field) {
+                continue;
+            }
+            let Some((_, _, tokens)) = self.docs[posting.doc].fields.iter().find(|(f, _, _)| *f == posting.field) else {
+                continue;
+            };
+            let starts: Vec<usize> = posting.positions.iter()
+                .copied()
+                .filter(|start| {
+                    words.iter().enumerate().all(|(offset, word)| {
+                        tokens.get(start + offset).is_some_and(|token| token.term == *word)
+                    })
+                })
+                .collect();
+            if starts.is_empty() {
+                continue;
+            }
+
+            let entry = results.entry(posting.doc).or_insert((0.0, Vec::new()));
+            let tf = posting.field.weight() * starts.len() as f64;
+            entry.0 += idf * self.bm25(posting.doc, tf);
+            for start in starts {
+                entry.1.extend((start..start + words.len()).map(|p| (posting.field, p)));
+            }
+        }
+        results
+    }
+
+    /// Up to two excerpts of the fields that matched.
+    fn snippets(&self, doc: usize, spots: &[(Field, usize)]) -> Vec<Snippet> {
+        let document = &self.docs[doc];
+        let mut by_field: BTreeMap<Field, Vec<usize>> = BTreeMap::new();
+        for (field, position) in spots {
+            by_field.entry(*field).or_default().push(*position);
+        }
+
+        let order = [Field::Description, Field::Notes, Field::Design, Field::Acceptance, Field::Alias, Field::Title];
+        let mut snippets = order.iter()
+            .filter_map(|field| {
+                let positions = by_field.get(field)?;
+                let (_, text, tokens) = document.fields.iter().find(|(f, _, _)| f == field)?;
+                let mut ranges: Vec<(usize, usize)> = positions.iter()
+                    .filter_map(|p| tokens.get(*p))
+                    .map(|token| (token.start, token.end))
+                    .collect();
+                ranges.sort();
+                ranges.dedup();
+                Some(excerpt(*field, text, &ranges))
+            })
+            .collect::<Vec<_>>();
+
+        // The title is in the result header; only show it when nothing else matched
+        if snippets.len() > 1 {
+            snippets.retain(|snippet| snippet.field != Field::Title.name());
+        }
+        snippets.truncate(2);
+        snippets
+    }
+}
+
+/// Cut a window of `text` around the first highlight and shift highlights into it.
+fn excerpt(field: Field, text: &str, ranges: &[(usize, usize)]) -> Snippet {
+    let chars: Vec<char> = text.chars().collect();
+    let first = ranges.first().map_or(0, |r| r.0);
+
+    let mut start = first.saturating_sub(SNIPPET_BEFORE);
+    let mut end = (first + SNIPPET_AFTER).min(chars.len());
+    // Don't cut words in half
+    while start > 0 && !chars[start - 1].is_whitespace() {
+        start -= 1;
+    }
+    while end < chars.len() && !chars[end].is_whitespace() {
+        end += 1;
+    }
+
+    let mut snippet: String = chars[start..end].iter()
+        .map(|c| if c.is_whitespace() { ' ' } else { *c })
+        .collect();
+    let mut offset = 0;
+    if start > 0 {
+        snippet.insert(0, '…');
+        offset = 1;
+    }
+    if end < chars.len() {
+        snippet.push('…');
+    }
+
+    // Words of a phrase are highlighted as one
+    let mut highlights: Vec<(usize, usize)> = Vec::new();
+    for (s, e) in ranges.iter().filter(|(s, e)| *s >= start && *e <= end) {
+        let (s, e) = (s - start + offset, e - start + offset);
+        match highlights.last_mut() {
+            Some(last) if chars[last.1 + start - offset..s + start - offset].iter().all(|c| c.is_whitespace()) => last.1 = e,
+            _ => highlights.push((s, e)),
+        }
+    }
+    Snippet { field: field.name(), text: snippet, highlights }
+}
+
+struct QueryTerm {
+    field: Option<Field>,
+    /// Stemmed words; more than one means a phrase
+    words: Vec<String>,
+}
+
+/// Split a query into terms: words, `"quoted phrases"` and `field:` scoped
+/// versions of either. Unknown `field:` prefixes are searched as text.
+fn parse_query(query: &str) -> Vec<QueryTerm> {
+    let mut terms = Vec::new();
+    let mut stopwords = Vec::new();
+    let mut rest = query.trim_start();
+
+    while !rest.is_empty() {
+        let mut field = None;
+        if let Some((name, value)) = rest.split_once(':') {
+            if let (false, Some(f)) = (name.contains(char::is_whitespace), Field::from_name(name)) {
+                field = Some(f);
+                rest = value;
+            }
+        }
+
+        let (chunk, quoted, remainder) = match rest.strip_prefix('"') {
+            Some(inner) => {
+                // An unterminated quote runs to the end of the query
+                let end = inner.find('"').unwrap_or(inner.len());
+                (&inner[..end], true, inner.get(end + 1..).unwrap_or(""))
+            }
+            None => {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                (&rest[..end], false, &rest[end..])
+            }
+        };
+        rest = remainder.trim_start();
+
+        let words: Vec<String> = tokenize(chunk).into_iter().map(|t| t.term).collect();
+        if words.is_empty() {
+            continue;
+        }
+        if !quoted && field.is_none() && words.len() == 1 && STOPWORDS.contains(&words[0].as_str()) {
+            stopwords.push(QueryTerm { field, words });
+            continue;
+        }
+        terms.push(QueryTerm { field, words });
+    }
+
+    // A query of nothing but stopwords is still a query
+    if terms.is_empty() { stopwords } else { terms }
+}
+
+/// Lowercased, stemmed alphanumeric words with their character ranges.
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut start = 0;
+
+    for (i, c) in text.chars().chain([' ']).enumerate() {
+        if c.is_alphanumeric() {
+            if word.is_empty() {
+                start = i;
+            }
+            word.extend(c.to_lowercase());
+        } else if !word.is_empty() {
+            tokens.push(Token { term: stem(&word), start, end: i });
+            word.clear();
+        }
+    }
+
+    tokens
+}
+
+/// A light English stemmer: enough that "caching", "cached" and "caches"
+/// all meet at "cach", without a full Porter implementation.
+fn stem(word: &str) -> String {
+    if word.chars().count() <= 3 || !word.chars().all(|c| c.is_ascii_alphabetic()) {
+        return word.to_string();
+    }
+
+    let mut stem = word.to_string();
+    let strip = |stem: &mut String, suffix: &str, replacement: &str, min_len: usize| {
+        if stem.ends_with(suffix) && stem.len() - suffix.len() + replacement.len() >= min_len {
+            stem.truncate(stem.len() - suffix.len());
+            stem.push_str(replacement);
+            true
+        } else {
+            false
+        }
+    };
+
+    // Plurals
+    if !strip(&mut stem, "sses", "ss", 3) && !strip(&mut stem, "ies", "y", 3)
+        && !stem.ends_with("ss") && !stem.ends_with("us") && !stem.ends_with("is")
+    {
+        strip(&mut stem, "s", "", 3);
+    }
+
+    // Verb and adverb endings
+    let stripped = strip(&mut stem, "ing", "", 3) || strip(&mut stem, "ed", "", 3);
+    if !stripped {
+        strip(&mut stem, "ly", "", 3);
+    }
+    strip(&mut stem, "ation", "ate", 3);
+    strip(&mut stem, "e", "", 4);
+
+    // "running" -> "runn" -> "run"
+    let bytes = stem.as_bytes();
+    if stripped && bytes.len() >= 4 {
+        let (a, b) = (bytes[bytes.len() - 2], bytes[bytes.len() - 1]);
+        if a == b && !matches!(b, b'l' | b's' | b'z') && !b"aeiou".contains(&b) {
+            stem.pop();
+        }
+    }
+
+    stem
+}
+
+/// Optimal string alignment distance, or `None` if it exceeds `max`.
+fn edit_distance(a: &str, b: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+
+    let mut previous2: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1).min(current[j - 1] + 1).min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(previous2[j - 2] + 1);
+            }
+        }
+        if current.iter().min().is_some_and(|m| *m > max) {
+            return None;
+        }
+        previous2 = std::mem::replace(&mut previous, current);
+    }
+
+    let distance = previous[b.len()];
+    (distance <= max).then_some(distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticket(id: &str, title: &str, description: &str, created: &str) -> Ticket {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "title": title,
+            "status": "open",
+            "deps": [],
+            "links": [],
+            "created": created,
+            "type": "task",
+            "priority": 2,
+            "description": description,
+        }))
+        .unwrap()
+    }
+
+    fn ids(index: &SearchIndex, query: &str) -> Vec<String> {
+        index.search(query, 10).into_iter().map(|hit| hit.id).collect()
+    }
+
+    fn highlighted(snippet: &Snippet) -> Vec<String> {
+        let chars: Vec<char> = snippet.text.chars().collect();
+        snippet.highlights.iter().map(|(s, e)| chars[*s..*e].iter().collect()).collect()
+    }
+
+    #[test]
+    fn exact_matches_outrank_prefix_and_fuzzy_ones() {
+        let index = SearchIndex::new(vec![
+            ticket("t-1", "Fix grph", "", "2024-01-01T00:00:00Z"),
+            ticket("t-2", "Fix graphql", "", "2024-01-01T00:00:00Z"),
+            ticket("t-3", "Fix graph", "", "2024-01-01T00:00:00Z"),
+        ]);
+        assert_eq!(ids(&index, "graph"), ["t-3", "t-2", "t-1"]);
+    }
+
+    #[test]
+    fn title_matches_outrank_description_matches() {
+        let index = SearchIndex::new(vec![
+            ticket("t-1", "Other thing", "The login page", "2024-01-01T00:00:00Z"),
+            ticket("t-2", "Login page", "", "2024-01-01T00:00:00Z"),
+            ticket("t-3", "Unrelated", "", "2024-01-01T00:00:00Z"),
+        ]);
+        let hits = index.search("login", 10);
+        assert_eq!(hits.iter().map(|hit| hit.id.as_str()).collect::<Vec<_>>(), ["t-2", "t-1"]);
+        assert!(hits[0].score > hits[1].score);
+    }
+
+    #[test]
+    fn every_term_must_match_and_ties_go_to_the_newest() {
+        let index = SearchIndex::new(vec![
+            ticket("t-1", "Crash on save", "", "2024-01-01T00:00:00Z"),
+            ticket("t-3", "Crash on save", "", "2024-03-01T00:00:00Z"),
+            ticket("t-2", "Crash on save", "", "2024-03-01T00:00:00Z"),
+            ticket("t-4", "Crash on load", "", "2024-05-01T00:00:00Z"),
+        ]);
+        assert_eq!(ids(&index, "crash save"), ["t-2", "t-3", "t-1"]);
+        assert_eq!(ids(&index, "crash"), ["t-4", "t-2", "t-3", "t-1"]);
+        assert_eq!(index.search("crash", 2).len(), 2);
+        assert!(ids(&index, "the on").is_empty());
+    }
+
+    #[test]
+    fn typo_tolerance_grows_with_word_length() {
+        let index = SearchIndex::new(vec![
+            ticket("t-1", "Keyboard layout", "", "2024-01-01T00:00:00Z"),
+            ticket("t-2", "Fix bag", "", "2024-01-01T00:00:00Z"),
+        ]);
+        // Up to three characters: exact or prefix only
+        assert!(ids(&index, "bug").is_empty());
+        assert_eq!(ids(&index, "key"), ["t-1"]);
+        // Four to seven: one edit
+        assert_eq!(ids(&index, "layuot"), ["t-1"]);
+        assert_eq!(ids(&index, "kyboard"), ["t-1"]);
+        assert!(ids(&index, "kybaord").is_empty());
+        // Eight or more: two edits, transpositions counting as one
+        assert_eq!(ids(&index, "kyebaord"), ["t-1"]);
+        assert!(ids(&index, "kyebaodr").is_empty());
+    }
+
+    #[test]
+    fn edit_distance_counts_transpositions_once_and_gives_up_past_max() {
+        assert_eq!(edit_distance("keyboard", "keyboard", 2), Some(0));
+        assert_eq!(edit_distance("keyboard", "keybaord", 2), Some(1));
+        assert_eq!(edit_distance("keyboard", "kyebaord", 2), Some(2));
+        assert_eq!(edit_distance("keyboard", "kyebaord", 1), None);
+        assert_eq!(edit_distance("naïve", "naive", 1), Some(1));
+        assert_eq!(edit_distance("abc", "abcdef", 2), None);
+    }
+
+    #[test]
+    fn snippets_cut_non_ascii_text_on_word_boundaries() {
+        let filler = "größe ".repeat(20);
+        let description = format!("{}Überweisung fehlgeschlagen, 请重试 {}", filler, "zurück ".repeat(30));
+        let index = SearchIndex::new(vec![ticket("t-1", "Zahlungen", &description, "2024-01-01T00:00:00Z")]);
+
+        let hits = index.search("überweisung", 10);
+        let snippet = &hits[0].snippets[0];
+        assert_eq!(snippet.field, "description");
+        assert_eq!(highlighted(snippet), ["Überweisung"]);
+        // Whole words on both sides, marked as cut
+        assert!(snippet.text.starts_with("…größe "));
+        assert!(snippet.text.ends_with(" zurück…"));
+        assert!(snippet.text.chars().count() <= SNIPPET_BEFORE + SNIPPET_AFTER + "…zurück…".chars().count());
+
+        let hits = index.search("请重试", 10);
+        assert_eq!(highlighted(&hits[0].snippets[0]), ["请重试"]);
+    }
+
+    #[test]
+    fn phrase_words_are_highlighted_as_one() {
+        let index = SearchIndex::new(vec![ticket("t-1", "Bug", "Ça plante: écran  blanc au démarrage", "2024-01-01T00:00:00Z")]);
+        let hits = index.search("\"écran blanc\"", 10);
+        let snippet = &hits[0].snippets[0];
+        assert_eq!(snippet.text, "Ça plante: écran  blanc au démarrage");
+        assert_eq!(highlighted(snippet), ["écran  blanc"]);
+    }
+}
@@ -1,17 +1,43 @@
 use anyhow::Result;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
-use std::path::PathBuf;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, Guard, WorkflowConfig};
+use crate::document::TicketDocument;
+use crate::git::CommitMode;
+use crate::graph::find_path;
+use crate::history::{self, ChangeKind, FileChange, Operation};
+use crate::index::TicketIndex;
+use crate::lock::RepoLock;
+use crate::query::{Query, QueryContext};
+use crate::search::{SearchHit, SearchIndex};
+use crate::utils::temp_path;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Ticket {
     pub id: String,
     pub title: String,
     pub status: String,
     pub deps: Vec<String>,
-    pub links: Vec<String>,
+    pub links: Vec<Link>,
     pub created: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+    /// Last time the ticket was saved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<DateTime<Utc>>,
+    /// When work first started (the first move to `in_progress`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+    /// When the ticket last entered a closed status; cleared on reopen
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closed_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closed_by: Option<String>,
     #[serde(rename = "type")]
     pub issue_type: String,
     pub priority: i32,
@@ -27,15 +53,124 @@ pub struct Ticket {
     pub external_ref: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parent: Option<String>,
+    /// Alternative names that resolve to this ticket
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub project: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub category: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub notes: Option<Vec<Note>>,
+    /// Every status the ticket entered, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub status_log: Vec<StatusChange>,
+    /// Custom fields and any other frontmatter keys, kept as-is
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+    /// Hash of the file this ticket was loaded from, checked before saving
+    #[serde(skip)]
+    pub revision: Option<String>,
+}
+
+impl Ticket {
+    /// When the ticket was closed; tickets closed before `closed_at` was
+    /// recorded fall back to when they were created.
+    pub fn closed_or_created(&self) -> DateTime<Utc> {
+        self.closed_at.unwrap_or(self.created)
+    }
+}
+
+/// The kind of a link, read from the linking ticket's side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkKind {
+    RelatesTo,
+    Duplicates,
+    DuplicatedBy,
+    Supersedes,
+    SupersededBy,
+    CausedBy,
+    Causes,
+}
+
+impl LinkKind {
+    /// The kind recorded on the other ticket.
+    pub fn inverse(self) -> Self {
+        match self {
+            LinkKind::RelatesTo => LinkKind::RelatesTo,
+            LinkKind::Duplicates => LinkKind::DuplicatedBy,
+            LinkKind::DuplicatedBy => LinkKind::Duplicates,
+            LinkKind::Supersedes => LinkKind::SupersededBy,
+            LinkKind::SupersededBy => LinkKind::Supersedes,
+            LinkKind::CausedBy => LinkKind::Causes,
+            LinkKind::Causes => LinkKind::CausedBy,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LinkKind::RelatesTo => "relates-to",
+            LinkKind::Duplicates => "duplicates",
+            LinkKind::DuplicatedBy => "duplicated-by",
+            LinkKind::Supersedes => "supersedes",
+            LinkKind::SupersededBy => "superseded-by",
+            LinkKind::CausedBy => "caused-by",
+            LinkKind::Causes => "causes",
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A typed link to another ticket.
+///
+/// Stored in frontmatter as a plain ID for `relates-to` (the format older
+/// files use) and as `{id, type}` otherwise.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "LinkRepr", into = "LinkRepr")]
+pub struct Link {
+    pub id: String,
+    pub kind: LinkKind,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum LinkRepr {
+    Plain(String),
+    Typed {
+        id: String,
+        #[serde(rename = "type")]
+        kind: LinkKind,
+    },
+}
+
+impl From<LinkRepr> for Link {
+    fn from(repr: LinkRepr) -> Self {
+        match repr {
+            LinkRepr::Plain(id) => Link { id, kind: LinkKind::RelatesTo },
+            LinkRepr::Typed { id, kind } => Link { id, kind },
+        }
+    }
+}
+
+impl From<Link> for LinkRepr {
+    fn from(link: Link) -> Self {
+        match link.kind {
+            LinkKind::RelatesTo => LinkRepr::Plain(link.id),
+            kind => LinkRepr::Typed { id: link.id, kind },
+        }
+    }
+}
+
+/// One entry in a ticket's status log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusChange {
+    pub status: String,
+    pub at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Note {
     pub timestamp: DateTime<Utc>,
     pub content: String,
@@ -46,20 +181,24 @@ pub struct TicketManager {
     pub tickets_dir: PathBuf,
     pub project: Option<String>,
     pub category: Option<String>,
+    pub config: Config,
+    /// Leave changes for `tkr commit` even when auto-commit is enabled
+    pub no_commit: bool,
 }
 
 impl TicketManager {
-    pub fn new(tickets_dir: PathBuf, project: Option<String>, category: Option<String>) -> Self {
+    pub fn new(tickets_dir: PathBuf, project: Option<String>, category: Option<String>, config: Config) -> Self {
         Self {
             tickets_dir,
             project,
             category,
+            config,
+            no_commit: false,
         }
     }
 
     pub fn ensure_status_directories(&self) -> Result<()> {
-        let statuses = ["open", "in_progress", "closed", "blocked", "ready", "icebox", "archive"];
-        for status in &statuses {
+        for status in self.statuses() {
             let status_dir = self.tickets_dir.join(status);
             if !status_dir.exists() {
                 fs::create_dir_all(&status_dir)?;
@@ -68,39 +207,12 @@ impl TicketManager {
         Ok(())
     }
 
-    fn get_status_dir(&self, status: &str) -> PathBuf {
-        self.tickets_dir.join(status)
+    pub fn statuses(&self) -> &[String] {
+        &self.config.workflow.statuses
     }
 
-    fn ticket_path_by_status(&self, id: &str, status: &str) -> Result<PathBuf> {
-        let status_dir = self.get_status_dir(status);
-        let exact_path = status_dir.join(format!("{}.md", id));
-
-        if exact_path.exists() {
-            return Ok(exact_path);
-        }
-
-        // Try partial ID matching in the specific status directory
-        if let Ok(entries) = fs::read_dir(&status_dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-                    if filename.starts_with(id) && filename.ends_with(".md") {
-                        return Ok(path);
-                    }
-                }
-            }
-        }
-
-        // Fallback: search all status directories
-        self.ticket_path(id)
-    }
-
-    pub fn ensure_tickets_dir(&self) -> Result<()> {
-        if !self.tickets_dir.exists() {
-            fs::create_dir_all(&self.tickets_dir)?;
-        }
-        Ok(())
+    pub fn get_status_dir(&self, status: &str) -> PathBuf {
+        self.tickets_dir.join(status)
     }
 
     pub fn generate_id(&self) -> Result<String> {
@@ -137,145 +249,318 @@ impl TicketManager {
         Ok(format!("{}-{}", prefix, hash))
     }
 
-    pub fn ticket_path(&self, id: &str) -> Result<PathBuf> {
-        // Search in all status directories
-        let statuses = ["open", "in_progress", "closed", "blocked", "ready", "icebox", "archive"];
+    /// Resolve a ticket reference to the file backing it.
+    ///
+    /// A reference is a full ID, an alias, the slug of a ticket's title, or an
+    /// ID prefix, tried in that order. Anything that matches more than one
+    /// ticket is rejected with the list of candidates.
+    pub fn ticket_path(&self, query: &str) -> Result<PathBuf, ResolveError> {
+        self.resolve(query).map(|(_, path)| path)
+    }
+
+    /// Resolve a ticket reference to its full ID.
+    pub fn resolve_id(&self, query: &str) -> Result<String, ResolveError> {
+        self.resolve(query).map(|(id, _)| id)
+    }
+
+    fn resolve(&self, query: &str) -> Result<(String, PathBuf), ResolveError> {
+        // Exact IDs never need the frontmatter
+        if let Some(path) = self.find_ticket_file(query) {
+            return Ok((query.to_string(), path));
+        }
 
-        for status in &statuses {
-            let status_dir = self.get_status_dir(status);
-            let exact_path = status_dir.join(format!("{}.md", id));
+        let query_slug = slugify(query);
+        let mut named = Vec::new();
+        let mut prefixed = Vec::new();
 
-            if exact_path.exists() {
-                return Ok(exact_path);
+        for (id, path, ticket) in self.indexed_tickets() {
+            let ticket = ticket.ok();
+
+            let candidate = Candidate {
+                id: id.clone(),
+                title: ticket.as_ref().map(|t| t.title.clone()).unwrap_or_default(),
+                status: ticket.as_ref().map(|t| t.status.clone()).unwrap_or_default(),
+            };
+
+            if let Some(ticket) = &ticket {
+                let alias_match = ticket.aliases.iter().any(|a| a.eq_ignore_ascii_case(query));
+                let slug_match = !query_slug.is_empty() && slugify(&ticket.title) == query_slug;
+                if alias_match || slug_match {
+                    named.push((candidate.clone(), path.clone()));
+                }
             }
+            if id.starts_with(query) {
+                prefixed.push((candidate, path));
+            }
+        }
 
-            // Try partial ID matching in this status directory
-            if let Ok(entries) = fs::read_dir(&status_dir) {
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-                        if filename.starts_with(id) && filename.ends_with(".md") {
-                            return Ok(path);
-                        }
-                    }
+        for mut matches in [named, prefixed] {
+            match matches.len() {
+                0 => continue,
+                1 => {
+                    let (candidate, path) = matches.remove(0);
+                    return Ok((candidate.id, path));
+                }
+                _ => {
+                    return Err(ResolveError::Ambiguous {
+                        query: query.to_string(),
+                        candidates: matches.into_iter().map(|(c, _)| c).collect(),
+                    });
+                }
+            }
+        }
+
+        Err(ResolveError::NotFound(query.to_string()))
+    }
+
+    /// The file for an exact ID, using status order when there are duplicates.
+    pub fn find_ticket_file(&self, id: &str) -> Option<PathBuf> {
+        if id.is_empty() || id.contains(['/', '\\']) {
+            return None;
+        }
+        self.statuses().iter()
+            .map(|status| self.get_status_dir(status).join(format!("{}.md", id)))
+            .find(|path| path.exists())
+    }
+
+    /// Every ticket file as (ID, path), one per ID in status order.
+    fn ticket_files(&self) -> Vec<(String, PathBuf)> {
+        let mut files: Vec<(String, PathBuf)> = Vec::new();
+        let mut seen = BTreeSet::new();
+
+        for status in self.statuses() {
+            let Ok(entries) = fs::read_dir(self.get_status_dir(status)) else { continue };
+            let mut paths: Vec<PathBuf> = entries.flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().map(|ext| ext == "md").unwrap_or(false))
+                .collect();
+            paths.sort();
+
+            for path in paths {
+                let Some(id) = path.file_stem().and_then(|s| s.to_str()).map(str::to_string) else { continue };
+                if !seen.insert(id.clone()) {
+                    continue;
                 }
+                files.push((id, path));
             }
         }
 
-        // If not found, return the path in the open directory as default
-        let default_path = self.get_status_dir("open").join(format!("{}.md", id));
-        Ok(default_path)
+        files
+    }
+
+    /// Every ticket as (ID, path, parsed ticket or load error), read through
+    /// the on-disk index so unchanged files aren't parsed again.
+    fn indexed_tickets(&self) -> Vec<(String, PathBuf, Result<Ticket>)> {
+        let files = self.ticket_files();
+        let mut index = TicketIndex::load(&self.tickets_dir);
+        let (tickets, changed) = index.refresh(&self.tickets_dir, &files);
+        if changed {
+            // Only a cache; a failed write just means reparsing next time
+            let _ = index.save(&self.tickets_dir);
+        }
+
+        files.into_iter()
+            .zip(tickets)
+            .map(|((id, path), ticket)| (id, path, ticket))
+            .collect()
     }
 
     pub fn load_ticket(&self, id: &str) -> Result<Ticket> {
+        Ok(self.load_document(id)?.ticket)
+    }
+
+    pub fn load_document(&self, id: &str) -> Result<TicketDocument> {
         let path = self.ticket_path(id)?;
         let content = fs::read_to_string(&path)?;
 
-        // Split YAML frontmatter and content - be more robust with malformed separators
-        let parts: Vec<&str> = content.splitn(3, "---").collect();
-        if parts.len() < 3 {
-            anyhow::bail!("Invalid ticket format - expected frontmatter with --- separators");
-        }
+        let mut document = TicketDocument::parse(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid ticket {}: {}", id, e))?;
+        document.ticket.revision = Some(revision_of(&content));
+        Ok(document)
+    }
 
-        let yaml_content = parts[1].trim();
+    /// Take the repository write lock; see `RepoLock`.
+    pub fn lock(&self) -> Result<RepoLock> {
+        RepoLock::acquire(&self.tickets_dir)
+    }
 
-        // Try to parse YAML, providing better error context
-        let ticket: Ticket = serde_yaml::from_str(yaml_content)
-            .map_err(|e| {
-                // Show the problematic YAML content for debugging
-                anyhow::anyhow!(
-                    "Failed to parse YAML for ticket {}: {}\nYAML content:\n{}",
-                    id,
-                    e,
-                    yaml_content
-                )
-            })?;
+    /// Group the changes made until the guard is dropped into one history
+    /// operation; see `history::Operation`.
+    pub fn begin_operation(&self, command: &str) -> Operation {
+        Operation::begin(command, || self.current_user(), self.commit_mode())
+    }
 
-        Ok(ticket)
+    /// How operations are committed to git, if auto-commit is enabled.
+    pub fn commit_mode(&self) -> Option<CommitMode> {
+        self.config.git.auto_commit.then(|| CommitMode {
+            tickets_dir: self.tickets_dir.clone(),
+            defer: self.no_commit,
+        })
+    }
+
+    /// Find the file currently backing a ticket.
+    ///
+    /// Uses the same directory precedence as `ticket_path`, so a save always
+    /// replaces the copy that was loaded.
+    fn existing_ticket_file(&self, ticket: &Ticket) -> Option<PathBuf> {
+        self.find_ticket_file(&ticket.id)
+    }
+
+    /// Save a ticket into the directory for its status, returning the new
+    /// revision.
+    ///
+    /// When the status changed, the existing file is first rewritten in place
+    /// and then renamed into the new directory. Both steps are atomic, so an
+    /// interrupted save leaves exactly one copy of the ticket behind.
+    ///
+    /// A ticket that was loaded from disk is only saved if the file still has
+    /// the revision it was loaded at; otherwise this fails with `ConflictError`.
+    /// Every save sets `updated` and is recorded in the history.
+    pub fn save_ticket(&self, ticket: &Ticket) -> Result<String> {
+        self.save_ticket_onto(ticket, None)
     }
 
-    pub fn save_ticket(&self, ticket: &Ticket) -> Result<()> {
+    /// Save a ticket, rendering it on top of `base` instead of the file on
+    /// disk when given, so an edited document's markdown is kept.
+    fn save_ticket_onto(&self, ticket: &Ticket, base: Option<&str>) -> Result<String> {
+        let _lock = self.lock()?;
+        let _op = self.begin_operation("save");
         self.ensure_status_directories()?;
+        let ticket = &Ticket { updated: Some(Utc::now()), ..ticket.clone() };
 
         let path = self.get_status_dir(&ticket.status).join(format!("{}.md", ticket.id));
+        let existing = self.existing_ticket_file(ticket);
+        let current = match &existing {
+            Some(existing) => Some(fs::read_to_string(existing)?),
+            None => None,
+        };
 
-        // Serialize YAML frontmatter
-        let yaml_content = serde_yaml::to_string(ticket)?;
+        if let Some(expected) = &ticket.revision {
+            let actual = current.as_deref().map(revision_of);
+            if actual.as_ref() != Some(expected) {
+                return Err(ConflictError { id: ticket.id.clone(), deleted: actual.is_none() }.into());
+            }
+        }
 
-        // Format as markdown with frontmatter
-        let mut content = format!("---\n{}\n---\n\n# {}\n",
-            yaml_content.trim(),
-            ticket.title
-        );
+        // Re-render on top of the existing document so hand-written markdown is kept
+        let document = current.as_deref()
+            .and_then(|content| TicketDocument::parse(content).ok());
+        let edited = base.and_then(|content| TicketDocument::parse(content).ok());
 
-        // Add description if present
-        if let Some(desc) = &ticket.description {
-            content.push_str(&format!("\n\n{}", desc));
-        }
+        let content = match edited.as_ref().or(document.as_ref()) {
+            Some(base) => base.render(ticket)?,
+            None => TicketDocument::new(ticket)?.render(ticket)?,
+        };
 
-        // Add notes if present
-        if let Some(notes) = &ticket.notes {
-            content.push_str("\n\n## Notes\n");
-            for note in notes {
-                content.push_str(&format!("\n**{}**: {}",
-                    note.timestamp.format("%Y-%m-%d %H:%M:%S"),
-                    note.content
-                ));
+        let kind = if existing.is_some() { ChangeKind::Update } else { ChangeKind::Create };
+        match existing {
+            Some(existing) if existing != path => {
+                write_atomic(&existing, &content)?;
+                fs::rename(&existing, &path)?;
+                history::touch(&existing);
             }
+            _ => write_atomic(&path, &content)?,
         }
+        history::touch(&path);
 
-        fs::write(&path, content)?;
+        let before = document.map(|document| document.ticket);
+        // The ticket is already saved; a missing history entry only costs an undo
+        if let Err(e) = history::record(&self.tickets_dir, kind, &ticket.id, before.as_ref(), Some(ticket), || self.current_user()) {
+            eprintln!("Warning: Failed to record the change to {} in the history: {}", ticket.id, e);
+        }
+        Ok(revision_of(&content))
+    }
 
-        Ok(())
+    /// Move a ticket to a new status.
+    ///
+    /// This is the one status-change path used by the CLI, TUI and web server:
+    /// it checks the workflow, moves the file between status directories and
+    /// unblocks dependents when the ticket is closed.
+    pub fn move_ticket_to_status(&self, ticket_id: &str, new_status: &str) -> Result<Ticket> {
+        let _lock = self.lock()?;
+        let _op = self.begin_operation(&format!("status {}", new_status));
+        let ticket = self.load_ticket(ticket_id)?;
+        self.transition_ticket(ticket, new_status)
     }
 
-    pub fn move_ticket_to_status(&self, ticket_id: &str, new_status: &str) -> Result<()> {
-        let mut ticket = self.load_ticket(ticket_id)?;
+    /// Apply a status change to an already loaded (and possibly edited) ticket.
+    pub fn transition_ticket(&self, ticket: Ticket, new_status: &str) -> Result<Ticket> {
+        self.transition_ticket_onto(ticket, new_status, None)
+    }
+
+    fn transition_ticket_onto(&self, mut ticket: Ticket, new_status: &str, base: Option<&str>) -> Result<Ticket> {
+        // Held until dependents have been unblocked
+        let _lock = self.lock()?;
+        let _op = self.begin_operation(&format!("status {}", new_status));
         let old_status = ticket.status.clone();
 
         if old_status == new_status {
-            return Ok(()); // Already in the right status
+            // Already in the right status, but keep any other edits
+            ticket.revision = Some(self.save_ticket_onto(&ticket, base)?);
+            return Ok(ticket);
         }
 
-        // Handle special closing logic
-        if new_status == "closed" {
+        self.check_transition(&ticket, new_status)?;
+
+        let workflow = &self.config.workflow;
+        let closing = workflow.is_closed(new_status) && !workflow.is_closed(&old_status);
+
+        // Update status; save_ticket moves the file to the new directory
+        self.record_status(&mut ticket, new_status);
+        ticket.revision = Some(self.save_ticket_onto(&ticket, base)?);
+
+        // Handle special closing logic once the ticket itself is closed
+        if closing {
             self.handle_ticket_closure(&ticket)?;
         }
 
-        // Update status and save to new location
-        ticket.status = new_status.to_string();
-        self.save_ticket(&ticket)?;
+        Ok(ticket)
+    }
 
-        // Remove from old location if it exists
-        let old_path = self.ticket_path_by_status(ticket_id, &old_status)?;
-        if old_path.exists() && old_path != self.get_status_dir(new_status).join(format!("{}.md", ticket_id)) {
-            fs::remove_file(old_path)?;
+    /// Set a ticket's status in memory, keeping the lifecycle fields in step:
+    /// the status log, `started_at` on the first start and `closed_at`/`closed_by`,
+    /// which are cleared again when the ticket is reopened.
+    fn record_status(&self, ticket: &mut Ticket, status: &str) {
+        let workflow = &self.config.workflow;
+        let now = Utc::now();
+        let actor = self.current_user();
+
+        if status == workflow.start_status && ticket.started_at.is_none() {
+            ticket.started_at = Some(now);
+        }
+        if !workflow.is_closed(status) {
+            ticket.closed_at = None;
+            ticket.closed_by = None;
+        } else if !workflow.is_closed(&ticket.status) {
+            ticket.closed_at = Some(now);
+            ticket.closed_by = actor.clone();
         }
 
-        Ok(())
+        ticket.status = status.to_string();
+        ticket.status_log.push(StatusChange { status: status.to_string(), at: now, by: actor });
     }
 
     fn handle_ticket_closure(&self, closing_ticket: &Ticket) -> Result<()> {
         // Find tickets that depend on this closing ticket
         let all_tickets = self.list_tickets()?;
 
+        let workflow = &self.config.workflow;
+        let Some(blocked_status) = workflow.blocked_status() else {
+            return Ok(());
+        };
+
         for ticket in &all_tickets {
-            if ticket.deps.contains(&closing_ticket.id) && ticket.status == "blocked" {
+            if ticket.deps.contains(&closing_ticket.id) && ticket.status == blocked_status {
                 // Unblock this ticket by removing the dependency and setting to ready
                 let mut updated_ticket = ticket.clone();
                 updated_ticket.deps.retain(|dep| dep != &closing_ticket.id);
-                updated_ticket.status = "ready".to_string();
+                self.record_status(&mut updated_ticket, workflow.unblocked_status());
 
                 println!("Unblocking ticket {} (was blocked by {})",
                     updated_ticket.id, closing_ticket.id);
 
+                // Moves the file out of the blocked directory
                 self.save_ticket(&updated_ticket)?;
-
-                // Remove from blocked directory
-                let blocked_path = self.ticket_path_by_status(&updated_ticket.id, "blocked")?;
-                if blocked_path.exists() {
-                    fs::remove_file(blocked_path)?;
-                }
             }
         }
 
@@ -286,93 +571,103 @@ impl TicketManager {
         self.ensure_status_directories()?;
 
         let mut tickets = Vec::new();
-
-        if !self.tickets_dir.exists() {
-            return Ok(tickets);
-        }
-
-        // Use traditional directory scan with status directories
-        let statuses = ["open", "in_progress", "closed", "blocked", "ready", "icebox", "archive"];
-        for status in &statuses {
-            let status_dir = self.get_status_dir(status);
-            if status_dir.exists() {
-                if let Ok(entries) = fs::read_dir(&status_dir) {
-                    for entry in entries.flatten() {
-                        let path = entry.path();
-                        if path.extension().map(|ext| ext == "md").unwrap_or(false) {
-                            if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-                                let ticket_id = filename.trim_end_matches(".md");
-                                match self.load_ticket(ticket_id) {
-                                    Ok(ticket) => tickets.push(ticket),
-                                    Err(e) => {
-                                        // Log the error but continue processing other tickets
-                                        eprintln!("Warning: Failed to load ticket {}: {}", ticket_id, e);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+        for (id, _, ticket) in self.indexed_tickets() {
+            match ticket {
+                Ok(ticket) => tickets.push(ticket),
+                // Log the error but continue processing other tickets
+                Err(e) => eprintln!("Warning: Failed to load ticket {}: {}", id, e),
             }
         }
 
         // Sort by creation date (newest first)
-        tickets.sort_by(|a, b| b.created.cmp(&a.created));
+        tickets.sort_by_key(|t| std::cmp::Reverse(t.created));
 
         Ok(tickets)
     }
 
-    pub fn search_tickets(&self, query: &str) -> Result<Vec<Ticket>> {
-        let all_tickets = self.list_tickets()?;
-        let query_lower = query.to_lowercase();
+    pub fn filter_tickets(&self, filter: &TicketFilter) -> Result<Vec<Ticket>> {
+        let tickets = self.list_tickets()?;
 
-        let filtered_tickets: Vec<Ticket> = all_tickets.into_iter()
-            .filter(|ticket| {
-                ticket.title.to_lowercase().contains(&query_lower) ||
-                ticket.description.as_ref().map(|d| d.to_lowercase().contains(&query_lower)).unwrap_or(false) ||
-                ticket.id.to_lowercase().contains(&query_lower)
-            })
-            .collect();
+        Ok(tickets.into_iter()
+            .filter(|ticket| filter.matches(ticket, &self.config))
+            .collect())
+    }
 
-        Ok(filtered_tickets)
+    pub fn query_context(&self) -> QueryContext<'_> {
+        QueryContext::new(&self.config, self.current_user())
     }
 
-    pub fn list_ready_tickets(&self) -> Result<Vec<Ticket>> {
-        let all_tickets = self.list_tickets()?;
-        let mut ready_tickets = Vec::new();
+    pub fn query_tickets(&self, query: &Query) -> Result<Vec<Ticket>> {
+        let ctx = self.query_context();
+        Ok(self.list_tickets()?
+            .into_iter()
+            .filter(|ticket| query.matches(ticket, &ctx))
+            .collect())
+    }
 
-        for ticket in all_tickets {
-            // Only consider tickets that are open or in_progress
-            if ticket.status != "open" && ticket.status != "in_progress" {
-                continue;
-            }
+    /// Full-text search, optionally limited to tickets matching `filter`.
+    pub fn search_tickets(&self, query: &str, filter: Option<&Query>, limit: usize) -> Result<Vec<SearchHit>> {
+        let tickets = match filter {
+            Some(filter) => self.query_tickets(filter)?,
+            None => self.list_tickets()?,
+        };
+        Ok(SearchIndex::new(tickets).search(query, limit))
+    }
 
-            // Check if all dependencies are resolved (not open or in_progress)
-            let mut all_deps_resolved = true;
-            for dep_id in &ticket.deps {
-                if let Ok(dep_ticket) = self.load_ticket(dep_id) {
-                    if dep_ticket.status == "open" || dep_ticket.status == "in_progress" {
-                        all_deps_resolved = false;
-                        break;
-                    }
-                } else {
-                    // If dependency ticket doesn't exist, consider it unresolved
-                    all_deps_resolved = false;
-                    break;
-                }
-            }
+    pub fn list_ready_tickets(&self) -> Result<Vec<Ticket>> {
+        let all_tickets = self.list_tickets()?;
+        let statuses = status_map(&all_tickets);
+        let workflow = &self.config.workflow;
 
-            if all_deps_resolved {
-                ready_tickets.push(ticket);
-            }
-        }
+        let mut ready_tickets: Vec<Ticket> = all_tickets.iter()
+            .filter(|ticket| workflow.is_active(&ticket.status) && unresolved_deps(ticket, &statuses, workflow).is_empty())
+            .cloned()
+            .collect();
 
         // Sort by creation date (newest first)
-        ready_tickets.sort_by(|a, b| b.created.cmp(&a.created));
+        ready_tickets.sort_by_key(|t| std::cmp::Reverse(t.created));
 
         Ok(ready_tickets)
     }
 
+    /// Tickets that can't proceed, with the dependencies holding each one up:
+    /// those marked blocked, and active tickets that `list_ready_tickets`
+    /// leaves out because of their dependencies.
+    pub fn list_blocked_tickets(&self) -> Result<Vec<(Ticket, Vec<String>)>> {
+        let all_tickets = self.list_tickets()?;
+        let statuses = status_map(&all_tickets);
+        let workflow = &self.config.workflow;
+
+        let mut blocked: Vec<(Ticket, Vec<String>)> = all_tickets.iter()
+            .filter_map(|ticket| {
+                let blockers = unresolved_deps(ticket, &statuses, workflow);
+                let marked = workflow.blocked_status() == Some(ticket.status.as_str());
+                let stuck = marked || (workflow.is_active(&ticket.status) && !blockers.is_empty());
+                stuck.then(|| (ticket.clone(), blockers))
+            })
+            .collect();
+
+        blocked.sort_by_key(|(t, _)| std::cmp::Reverse(t.created));
+        Ok(blocked)
+    }
+
+    /// Closed tickets, most recently closed first, optionally only those
+    /// closed on or after `since`. Tickets closed before `closed_at` was
+    /// recorded count as closed when they were created.
+    pub fn list_closed_tickets(&self, since: Option<NaiveDate>) -> Result<Vec<Ticket>> {
+        let workflow = &self.config.workflow;
+        let mut closed: Vec<Ticket> = self.list_tickets()?
+            .into_iter()
+            .filter(|ticket| workflow.is_closed(&ticket.status))
+            .collect();
+        if let Some(since) = since {
+            closed.retain(|ticket| ticket.closed_or_created().date_naive() >= since);
+        }
+
+        closed.sort_by_key(|t| std::cmp::Reverse(t.closed_or_created()));
+        Ok(closed)
+    }
+
     pub fn migrate_tickets(&self, source: &str) -> Result<()> {
         self.ensure_status_directories()?;
 
@@ -419,6 +714,7 @@ impl TicketManager {
 
     fn migrate_from_bash_tk(&self) -> Result<()> {
         println!("Migrating from bash tk format...");
+        let _lock = self.lock()?;
 
         if !self.tickets_dir.exists() {
             anyhow::bail!("No .tickets directory found");
@@ -439,15 +735,7 @@ impl TicketManager {
                     continue;
                 }
 
-                // Parse bash tk format
-                let ticket = self.parse_bash_tk_ticket(&content, &path)?;
-
-                // Save in new format with status directory
-                self.save_ticket(&ticket)?;
-
-                // Remove old file
-                fs::remove_file(&path)?;
-
+                self.migrate_bash_tk_file(&path)?;
                 migrated_count += 1;
             }
         }
@@ -456,6 +744,29 @@ impl TicketManager {
         Ok(())
     }
 
+    /// Convert a single top-level bash tk file into a ticket in its status directory.
+    pub fn migrate_bash_tk_file(&self, path: &Path) -> Result<Ticket> {
+        let _op = self.begin_operation("migrate");
+        let content = fs::read_to_string(path)?;
+
+        // Parse bash tk format
+        let ticket = self.parse_bash_tk_ticket(&content, path)?;
+
+        // Save in new format with status directory
+        self.save_ticket(&ticket)?;
+
+        // Remove old file, keeping it in the history for undo
+        fs::remove_file(path)?;
+        history::touch(path);
+        let removed = FileChange::Removed {
+            path: path.strip_prefix(&self.tickets_dir).unwrap_or(path).to_path_buf(),
+            content,
+        };
+        history::record_file(&self.tickets_dir, &ticket.id, removed, || self.current_user())?;
+
+        Ok(ticket)
+    }
+
     fn parse_bash_tk_ticket(&self, content: &str, path: &std::path::Path) -> Result<Ticket> {
         let lines: Vec<&str> = content.lines().collect();
 
@@ -472,7 +783,7 @@ impl TicketManager {
             .unwrap_or("unknown");
 
         // Parse metadata from bash tk format
-        let mut status = "open".to_string();
+        let mut status = self.config.workflow.initial_status.clone();
         let mut description = None;
         let mut notes = Vec::new();
         let mut current_section = "description";
@@ -510,7 +821,7 @@ impl TicketManager {
                         if description.is_none() {
                             description = Some(line.to_string());
                         } else if let Some(ref mut desc) = description {
-                            desc.push_str("\n");
+                            desc.push('\n');
                             desc.push_str(line);
                         }
                     }
@@ -526,6 +837,11 @@ impl TicketManager {
             deps: Vec::new(),
             links: Vec::new(),
             created: Utc::now(), // Would need to extract from file metadata if available
+            created_by: None,
+            updated: None,
+            started_at: None,
+            closed_at: None,
+            closed_by: None,
             issue_type: "task".to_string(),
             priority: 2,
             description,
@@ -534,9 +850,13 @@ impl TicketManager {
             assignee: None,
             external_ref: None,
             parent: None,
+            aliases: Vec::new(),
             project: self.project.clone(),
             category: self.category.clone(),
             notes: if notes.is_empty() { None } else { Some(notes) },
+            status_log: Vec::new(),
+            extra: BTreeMap::new(),
+            revision: None,
         })
     }
 
@@ -548,25 +868,131 @@ impl TicketManager {
     }
 
     pub fn validate_status(&self, status: &str) -> Result<()> {
-        let valid_statuses = ["open", "in_progress", "closed", "blocked", "ready", "icebox", "archive"];
-        if !valid_statuses.contains(&status) {
+        if !self.config.workflow.is_valid(status) {
             anyhow::bail!("Invalid status: {}. Valid statuses: {}",
-                status, valid_statuses.join(", "));
+                status, self.statuses().join(", "));
         }
         Ok(())
     }
 
-    pub fn create_ticket(&mut self, title: String, options: CreateOptions) -> Result<String> {
+    /// Check that the workflow allows moving `ticket` to `new_status`.
+    pub fn check_transition(&self, ticket: &Ticket, new_status: &str) -> Result<()> {
+        self.validate_status(new_status)?;
+
+        let workflow = &self.config.workflow;
+        if ticket.status == new_status {
+            return Ok(());
+        }
+
+        if !workflow.allows(&ticket.status, new_status) {
+            let allowed = workflow.transitions.get(&ticket.status)
+                .map(|targets| targets.join(", "))
+                .unwrap_or_default();
+            anyhow::bail!("Cannot move {} from {} to {}. Allowed: {}",
+                ticket.id, ticket.status, new_status,
+                if allowed.is_empty() { "(none)".to_string() } else { allowed });
+        }
+
+        for guard in workflow.guards_for(new_status) {
+            match guard {
+                Guard::DepsClosed => {
+                    let open_deps: Vec<&String> = ticket.deps.iter()
+                        .filter(|dep_id| {
+                            self.load_ticket(dep_id)
+                                .map(|dep| !workflow.is_closed(&dep.status))
+                                .unwrap_or(true)
+                        })
+                        .collect();
+                    if !open_deps.is_empty() {
+                        anyhow::bail!("Cannot move {} to {} while dependencies are open: {}",
+                            ticket.id, new_status,
+                            open_deps.iter().map(|d| d.as_str()).collect::<Vec<_>>().join(", "));
+                    }
+                }
+                Guard::Assignee => {
+                    if ticket.assignee.as_deref().map(str::trim).unwrap_or("").is_empty() {
+                        anyhow::bail!("Cannot move {} to {} without an assignee", ticket.id, new_status);
+                    }
+                }
+                Guard::Field(name) => {
+                    if !ticket.extra.contains_key(name) {
+                        anyhow::bail!("Cannot move {} to {} without field '{}'", ticket.id, new_status, name);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check hand-edited file content against the ticket it was opened as,
+    /// returning the edited ticket.
+    pub fn validate_edit(&self, before: &Ticket, content: &str) -> Result<Ticket> {
+        let mut edited = TicketDocument::parse(content)?.ticket;
+        if edited.id != before.id {
+            anyhow::bail!("The ticket ID can't be changed (it is {})", before.id);
+        }
+
+        let from = Ticket { status: before.status.clone(), ..edited.clone() };
+        self.check_transition(&from, &edited.status)?;
+        self.config.check_fields(&edited.extra)?;
+        let added: Vec<String> = edited.aliases.iter()
+            .filter(|alias| !before.aliases.contains(alias))
+            .cloned()
+            .collect();
+        self.check_aliases(&added)?;
+
+        edited.revision = before.revision.clone();
+        Ok(edited)
+    }
+
+    /// Replace a ticket's file with validated, hand-edited content, moving it
+    /// when the status changed.
+    ///
+    /// `edited.revision` is the revision the edit started from; if the file
+    /// changed since, this fails with `ConflictError` and nothing is written.
+    pub fn apply_edit(&self, edited: Ticket, content: &str) -> Result<Ticket> {
+        let _lock = self.lock()?;
+        let _op = self.begin_operation("edit");
+        let conflict = |deleted| ConflictError { id: edited.id.clone(), deleted };
+
+        let path = self.find_ticket_file(&edited.id).ok_or_else(|| conflict(true))?;
+        let current = fs::read_to_string(&path)?;
+        if Some(revision_of(&current)) != edited.revision {
+            return Err(conflict(false).into());
+        }
+        let before = TicketDocument::parse(&current)?.ticket;
+        let new_status = edited.status.clone();
+        let ticket = Ticket { status: before.status, ..edited };
+        // Checked again under the lock, before anything is written
+        self.check_transition(&ticket, &new_status)?;
+
+        // Saved on top of the edited document in one write, so the edit is
+        // recorded once; this also moves the file and unblocks dependents.
+        self.transition_ticket_onto(ticket, &new_status, Some(content))
+    }
+
+    pub fn create_ticket(&self, title: String, options: CreateOptions) -> Result<String> {
+        let _lock = self.lock()?;
+        let _op = self.begin_operation("create");
+        let extra = self.config.build_fields(&options.fields)?;
+        self.check_aliases(&options.aliases)?;
         let id = self.generate_id()?;
         let now = Utc::now();
+        let actor = self.current_user();
 
         let ticket = Ticket {
             id: id.clone(),
             title: title.to_string(),
-            status: "open".to_string(),
+            status: self.config.workflow.initial_status.clone(),
             deps: Vec::new(),
             links: Vec::new(),
             created: now,
+            created_by: actor.clone(),
+            updated: None,
+            started_at: None,
+            closed_at: None,
+            closed_by: None,
             issue_type: options.issue_type.to_string(),
             priority: options.priority,
             description: options.description,
@@ -575,17 +1001,63 @@ impl TicketManager {
             assignee: options.assignee,
             external_ref: options.external_ref,
             parent: options.parent,
+            aliases: options.aliases,
             project: self.project.clone(),
             category: self.category.clone(),
             notes: None,
+            status_log: vec![StatusChange { status: self.config.workflow.initial_status.clone(), at: now, by: actor }],
+            extra,
+            revision: None,
         };
 
         self.save_ticket(&ticket)?;
-        println!("{}", id);
         Ok(id)
     }
 
-    #[allow(dead_code)]
+    /// Delete a ticket and drop it from other tickets' deps, links and
+    /// `parent`, so nothing is left pointing at it. Returns the deleted ticket.
+    pub fn delete_ticket(&self, id: &str) -> Result<Ticket> {
+        let _lock = self.lock()?;
+        let _op = self.begin_operation("delete");
+        let path = self.ticket_path(id)?;
+        let ticket = self.load_ticket(id)?;
+
+        fs::remove_file(&path)?;
+        history::touch(&path);
+        history::record(&self.tickets_dir, ChangeKind::Delete, &ticket.id, Some(&ticket), None, || self.current_user())?;
+
+        for other in self.list_tickets()? {
+            let mut updated = other.clone();
+            updated.deps.retain(|dep| dep != &ticket.id);
+            updated.links.retain(|link| link.id != ticket.id);
+            if updated.parent.as_deref() == Some(ticket.id.as_str()) {
+                updated.parent = None;
+            }
+            if updated != other {
+                self.save_ticket(&updated)?;
+            }
+        }
+
+        Ok(ticket)
+    }
+
+    /// Aliases must not collide with an existing ID or alias.
+    fn check_aliases(&self, aliases: &[String]) -> Result<()> {
+        if aliases.is_empty() {
+            return Ok(());
+        }
+
+        let tickets = self.list_tickets()?;
+        for alias in aliases {
+            if let Some(other) = tickets.iter().find(|t| {
+                t.id.eq_ignore_ascii_case(alias) || t.aliases.iter().any(|a| a.eq_ignore_ascii_case(alias))
+            }) {
+                anyhow::bail!("Alias '{}' is already used by {}", alias, other.id);
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_git_user(&self) -> Option<String> {
         std::process::Command::new("git")
             .args(["config", "user.name"])
@@ -593,19 +1065,52 @@ impl TicketManager {
             .ok()
             .and_then(|output| String::from_utf8(output.stdout).ok())
             .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
     }
 
-    pub fn update_status(&self, id: &str, status: &str) -> Result<()> {
-        let mut ticket = self.load_ticket(id)?;
-        self.validate_status(status)?;
-        ticket.status = status.to_string();
-        self.save_ticket(&ticket)?;
-        println!("Updated {} -> {}", id, status);
-        Ok(())
+    /// Who `me` refers to: `$TKR_USER`, falling back to git's `user.name`.
+    pub fn current_user(&self) -> Option<String> {
+        std::env::var("TKR_USER")
+            .ok()
+            .filter(|user| !user.is_empty())
+            .or_else(|| self.get_git_user())
     }
 
-    pub fn add_dependency(&self, id: &str, dep_id: &str) -> Result<()> {
+    pub fn update_status(&self, id: &str, status: &str) -> Result<Ticket> {
+        self.move_ticket_to_status(id, status)
+    }
+
+    /// Add `dep_id` to the dependencies of `id`.
+    ///
+    /// Self-dependencies, unknown tickets and edges that would close a cycle
+    /// are rejected unless `force` is set.
+    pub fn add_dependency(&self, id: &str, dep_id: &str, force: bool) -> Result<()> {
+        let _lock = self.lock()?;
+        let _op = self.begin_operation("dep");
         let mut ticket = self.load_ticket(id)?;
+        let id = ticket.id.clone();
+        let dep_id = match self.resolve_id(dep_id) {
+            Ok(dep_id) => dep_id,
+            Err(ResolveError::NotFound(_)) if force => dep_id.to_string(),
+            Err(e @ ResolveError::NotFound(_)) => anyhow::bail!("{} (use --force to add it anyway)", e),
+            Err(e) => return Err(e.into()),
+        };
+        let dep_id = dep_id.as_str();
+
+        if !force {
+            if dep_id == id {
+                anyhow::bail!("{} cannot depend on itself", id);
+            }
+            let graph: BTreeMap<String, Vec<String>> = self.list_tickets()?
+                .into_iter()
+                .map(|t| (t.id, t.deps))
+                .collect();
+            if let Some(path) = find_path(&graph, dep_id, &id) {
+                anyhow::bail!("Adding {} -> {} would create a dependency cycle: {} -> {} (use --force to add it anyway)",
+                    id, dep_id, id, path.join(" -> "));
+            }
+        }
+
         if !ticket.deps.contains(&dep_id.to_string()) {
             ticket.deps.push(dep_id.to_string());
             self.save_ticket(&ticket)?;
@@ -617,7 +1122,17 @@ impl TicketManager {
     }
 
     pub fn remove_dependency(&self, id: &str, dep_id: &str) -> Result<()> {
+        let _lock = self.lock()?;
+        let _op = self.begin_operation("undep");
         let mut ticket = self.load_ticket(id)?;
+        let id = ticket.id.clone();
+        // Dependencies on deleted tickets can still be removed by their stored ID
+        let dep_id = if ticket.deps.iter().any(|d| d == dep_id) {
+            dep_id.to_string()
+        } else {
+            self.resolve_id(dep_id)?
+        };
+        let dep_id = dep_id.as_str();
         if let Some(pos) = ticket.deps.iter().position(|d| d == dep_id) {
             ticket.deps.remove(pos);
             self.save_ticket(&ticket)?;
@@ -628,7 +1143,66 @@ impl TicketManager {
         Ok(())
     }
 
+    /// Link two tickets, recording the inverse link on the target.
+    ///
+    /// Any existing link between the two tickets is replaced. Returns the
+    /// resolved IDs.
+    pub fn link_tickets(&self, id: &str, target_id: &str, kind: LinkKind) -> Result<(String, String)> {
+        let _lock = self.lock()?;
+        let _op = self.begin_operation("link");
+        let mut ticket = self.load_ticket(id)?;
+        let mut target = self.load_ticket(target_id)?;
+        if ticket.id == target.id {
+            anyhow::bail!("{} cannot be linked to itself", ticket.id);
+        }
+
+        ticket.links.retain(|link| link.id != target.id);
+        ticket.links.push(Link { id: target.id.clone(), kind });
+        target.links.retain(|link| link.id != ticket.id);
+        target.links.push(Link { id: ticket.id.clone(), kind: kind.inverse() });
+
+        self.save_ticket(&ticket)?;
+        self.save_ticket(&target)?;
+        Ok((ticket.id, target.id))
+    }
+
+    /// Remove every link between two tickets, on both sides.
+    pub fn unlink_tickets(&self, id: &str, target_id: &str) -> Result<(String, String)> {
+        let _lock = self.lock()?;
+        let _op = self.begin_operation("unlink");
+        let mut ticket = self.load_ticket(id)?;
+        // Links to deleted tickets can still be removed by their stored ID
+        let target_id = if ticket.links.iter().any(|link| link.id == target_id) {
+            target_id.to_string()
+        } else {
+            self.resolve_id(target_id)?
+        };
+
+        let before = ticket.links.len();
+        ticket.links.retain(|link| link.id != target_id);
+        let mut changed = ticket.links.len() != before;
+        if changed {
+            self.save_ticket(&ticket)?;
+        }
+
+        if let Ok(mut target) = self.load_ticket(&target_id) {
+            let before = target.links.len();
+            target.links.retain(|link| link.id != ticket.id);
+            if target.links.len() != before {
+                self.save_ticket(&target)?;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            anyhow::bail!("{} and {} are not linked", ticket.id, target_id);
+        }
+        Ok((ticket.id, target_id))
+    }
+
     pub fn add_note(&self, id: &str, note_content: &str) -> Result<()> {
+        let _lock = self.lock()?;
+        let _op = self.begin_operation("note");
         let mut ticket = self.load_ticket(id)?;
 
         let note = Note {
@@ -642,16 +1216,26 @@ impl TicketManager {
         }
 
         self.save_ticket(&ticket)?;
-        println!("Note added to {}", id);
+        println!("Note added to {}", ticket.id);
         Ok(())
     }
 
     pub fn show_ticket(&self, id: &str) -> Result<()> {
-        let _ticket = self.load_ticket(id)?;
         let path = self.ticket_path(id)?;
         let content = fs::read_to_string(&path)?;
 
         println!("{}", content);
+
+        let ticket = TicketDocument::parse(&content)?.ticket;
+        if !ticket.links.is_empty() {
+            println!("Links:");
+            for link in &ticket.links {
+                match self.load_ticket(&link.id) {
+                    Ok(other) => println!("  {:<14} {} - {} ({})", link.kind.as_str(), other.id, other.title, other.status),
+                    Err(_) => println!("  {:<14} {} (missing)", link.kind.as_str(), link.id),
+                }
+            }
+        }
         Ok(())
     }
 }
@@ -666,4 +1250,123 @@ pub struct CreateOptions {
     pub assignee: Option<String>,
     pub external_ref: Option<String>,
     pub parent: Option<String>,
+    pub aliases: Vec<String>,
+    /// Raw custom field values, validated against the configured schema
+    pub fields: BTreeMap<String, String>,
+}
+
+/// A ticket that matched an ambiguous reference.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub id: String,
+    pub title: String,
+    pub status: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ResolveError {
+    #[error("Ticket '{0}' not found")]
+    NotFound(String),
+    #[error("'{query}' matches {} tickets:\n{}", candidates.len(), format_candidates(candidates))]
+    Ambiguous { query: String, candidates: Vec<Candidate> },
+}
+
+fn format_candidates(candidates: &[Candidate]) -> String {
+    candidates.iter()
+        .map(|c| format!("  {} - {} ({})", c.id, c.title, c.status))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Lowercase a title and collapse everything but letters and digits into
+/// single dashes, e.g. "Fix login bug!" -> "fix-login-bug".
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Returned by `save_ticket` when the file changed since the ticket was loaded.
+#[derive(Debug, thiserror::Error)]
+#[error("Ticket {id} was {} since it was loaded; reload and try again",
+    if *.deleted { "deleted" } else { "changed by someone else" })]
+pub struct ConflictError {
+    pub id: String,
+    pub deleted: bool,
+}
+
+/// Content hash identifying one version of a ticket file (64-bit FNV-1a).
+pub fn revision_of(content: &str) -> String {
+    let hash = content.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+fn status_map(tickets: &[Ticket]) -> BTreeMap<String, String> {
+    tickets.iter().map(|t| (t.id.clone(), t.status.clone())).collect()
+}
+
+/// Dependencies that aren't resolved yet: those still in an active status,
+/// and those that don't exist.
+fn unresolved_deps(ticket: &Ticket, statuses: &BTreeMap<String, String>, workflow: &WorkflowConfig) -> Vec<String> {
+    ticket.deps.iter()
+        .filter(|dep_id| statuses.get(*dep_id).is_none_or(|status| workflow.is_active(status)))
+        .cloned()
+        .collect()
+}
+
+/// Write a file by renaming a fully written and synced temp file over it.
+fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    use std::io::Write;
+
+    let tmp_path = temp_path(path);
+
+    let written = fs::File::create(&tmp_path).and_then(|mut file| {
+        file.write_all(content.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e.into());
+    }
+    if let Err(e) = fs::rename(&tmp_path, path) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e.into());
+    }
+
+    Ok(())
+}
+
+/// Filters accepted by `list`/`ls`; unset fields match everything.
+#[derive(Debug, Default)]
+pub struct TicketFilter {
+    pub status: Option<String>,
+    pub issue_type: Option<String>,
+    pub project: Option<String>,
+    pub category: Option<String>,
+    pub fields: BTreeMap<String, String>,
+}
+
+impl TicketFilter {
+    pub fn matches(&self, ticket: &Ticket, config: &Config) -> bool {
+        let matches_opt = |wanted: &Option<String>, actual: Option<&String>| match wanted {
+            Some(wanted) => actual == Some(wanted),
+            None => true,
+        };
+
+        matches_opt(&self.status, Some(&ticket.status))
+            && matches_opt(&self.issue_type, Some(&ticket.issue_type))
+            && matches_opt(&self.project, ticket.project.as_ref())
+            && matches_opt(&self.category, ticket.category.as_ref())
+            && self.fields.iter().all(|(name, wanted)| {
+                config.field_matches(name, ticket.extra.get(name), wanted)
+            })
+    }
 }
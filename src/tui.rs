@@ -13,30 +13,35 @@ use ratatui::{
     Frame, Terminal,
 };
 use tokio::sync::mpsc;
+use crate::config::{value_to_string, Config};
+use crate::query::Query;
 use crate::ticket::{Ticket, TicketManager};
 
 #[derive(Clone, Debug)]
 pub enum AppEvent {
     Refresh,
-    Quit,
-    SelectTicket(usize),
-    ChangeStatus(String),
-    ShowHelp,
 }
 
 #[derive(Clone, Debug)]
 pub enum AppState {
     Normal,
     Help,
-    CreatingTicket,
-    EditingTicket(usize),
+    /// Typing a filter query after '/'
+    Filtering,
+    EditingTicket,
 }
 
 pub struct App {
     pub tickets: Vec<Ticket>,
     pub selected_ticket: usize,
     pub state: AppState,
-    pub status_filter: Option<String>,
+    /// Query the ticket list is filtered by, and the text it was parsed from
+    pub filter: Option<Query>,
+    pub filter_text: String,
+    /// What's been typed into the filter bar so far
+    pub filter_input: String,
+    /// Last error or notice, shown in the footer until the next key press
+    pub message: Option<String>,
     list_state: ListState,
 }
 
@@ -46,7 +51,10 @@ impl App {
             tickets: Vec::new(),
             selected_ticket: 0,
             state: AppState::Normal,
-            status_filter: None,
+            filter: None,
+            filter_text: String::new(),
+            filter_input: String::new(),
+            message: None,
             list_state: ListState::default(),
         }
     }
@@ -67,6 +75,32 @@ impl App {
         }
     }
 
+    /// Reload the ticket list, applying the current filter.
+    pub fn refresh(&mut self, manager: &TicketManager) {
+        let tickets = match &self.filter {
+            Some(query) => manager.query_tickets(query),
+            None => manager.list_tickets(),
+        };
+        if let Ok(tickets) = tickets {
+            self.update_tickets(tickets);
+        }
+    }
+
+    /// Parse the filter bar and apply it; an empty query clears the filter.
+    pub fn apply_filter(&mut self, manager: &TicketManager) {
+        match Query::parse(&self.filter_input) {
+            Ok(query) => {
+                self.filter_text = self.filter_input.trim().to_string();
+                self.filter = (!self.filter_text.is_empty()).then_some(query);
+                self.selected_ticket = 0;
+                self.refresh(manager);
+                self.state = AppState::Normal;
+            }
+            // The footer has room for one line, so drop the caret display
+            Err(e) => self.message = Some(format!("Invalid query: {} at column {}", e.message, e.position + 1)),
+        }
+    }
+
     pub fn update_tickets(&mut self, tickets: Vec<Ticket>) {
         self.tickets = tickets;
         if self.selected_ticket >= self.tickets.len() && !self.tickets.is_empty() {
@@ -87,26 +121,20 @@ pub async fn run_tui(manager: &mut TicketManager) -> Result<()> {
     let mut app = App::new();
 
     // Load initial tickets
-    let tickets = manager.list_tickets()?;
-    app.update_tickets(tickets);
+    app.update_tickets(manager.list_tickets()?);
 
     // Setup event handling
     let (tx, mut rx) = mpsc::channel::<AppEvent>(100);
 
-    // Clone manager for async operations
-    let manager_clone = manager.clone();
     let tx_clone = tx.clone();
 
     // Spawn background task for ticket updates
     tokio::spawn(async move {
-        let mut manager = manager_clone;
-        let mut last_update = std::time::Instant::now();
-
         loop {
             tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
 
-            if let Ok(tickets) = manager.list_tickets() {
-                let _ = tx_clone.send(AppEvent::Refresh);
+            if tx_clone.send(AppEvent::Refresh).await.is_err() {
+                break;
             }
         }
     });
@@ -114,11 +142,12 @@ pub async fn run_tui(manager: &mut TicketManager) -> Result<()> {
     // Main UI loop
     loop {
         // Draw UI
-        terminal.draw(|f| ui(f, &app))?;
+        terminal.draw(|f| ui(f, &app, &manager.config))?;
 
         // Handle events
         if event::poll(std::time::Duration::from_millis(100))? {
             if let Event::Key(key) = event::read()? {
+                app.message = None;
                 match app.state {
                     AppState::Normal => match key.code {
                         KeyCode::Char('q') | KeyCode::Esc => break,
@@ -127,39 +156,24 @@ pub async fn run_tui(manager: &mut TicketManager) -> Result<()> {
                         KeyCode::Char('h') | KeyCode::Left => {
                             app.state = AppState::Help;
                         }
-                        KeyCode::Char('r') => {
-                            if let Ok(tickets) = manager.list_tickets() {
-                                app.update_tickets(tickets);
-                            }
+                        KeyCode::Char('r') => app.refresh(manager),
+                        KeyCode::Char('/') => {
+                            app.filter_input = app.filter_text.clone();
+                            app.state = AppState::Filtering;
                         }
-                        KeyCode::Char('1') => {
-                            if let Some(ticket) = app.tickets.get(app.selected_ticket) {
-                                let _ = manager.update_status(&ticket.id, "open");
-                                if let Ok(tickets) = manager.list_tickets() {
-                                    app.update_tickets(tickets);
+                        KeyCode::Char(c @ '1'..='9') => {
+                            // Number keys follow the configured workflow order
+                            let index = c as usize - '1' as usize;
+                            let status = manager.config.workflow.statuses.get(index).cloned();
+                            if let (Some(ticket), Some(status)) = (app.tickets.get(app.selected_ticket), status) {
+                                if let Err(e) = manager.update_status(&ticket.id, &status) {
+                                    app.message = Some(e.to_string());
                                 }
+                                app.refresh(manager);
                             }
                         }
-                        KeyCode::Char('2') => {
-                            if let Some(ticket) = app.tickets.get(app.selected_ticket) {
-                                let _ = manager.update_status(&ticket.id, "in_progress");
-                                if let Ok(tickets) = manager.list_tickets() {
-                                    app.update_tickets(tickets);
-                                }
-                            }
-                        }
-                        KeyCode::Char('3') => {
-                            if let Some(ticket) = app.tickets.get(app.selected_ticket) {
-                                let _ = manager.update_status(&ticket.id, "closed");
-                                if let Ok(tickets) = manager.list_tickets() {
-                                    app.update_tickets(tickets);
-                                }
-                            }
-                        }
-                        KeyCode::Enter => {
-                            if let Some(ticket) = app.tickets.get(app.selected_ticket) {
-                                app.state = AppState::EditingTicket(app.selected_ticket);
-                            }
+                        KeyCode::Enter if app.tickets.get(app.selected_ticket).is_some() => {
+                            app.state = AppState::EditingTicket;
                         }
                         _ => {}
                     },
@@ -169,13 +183,16 @@ pub async fn run_tui(manager: &mut TicketManager) -> Result<()> {
                         }
                         _ => {}
                     },
-                    AppState::EditingTicket(_) => match key.code {
-                        KeyCode::Char('q') | KeyCode::Esc => {
-                            app.state = AppState::Normal;
+                    AppState::Filtering => match key.code {
+                        KeyCode::Esc => app.state = AppState::Normal,
+                        KeyCode::Enter => app.apply_filter(manager),
+                        KeyCode::Backspace => {
+                            app.filter_input.pop();
                         }
+                        KeyCode::Char(c) => app.filter_input.push(c),
                         _ => {}
                     },
-                    AppState::CreatingTicket => match key.code {
+                    AppState::EditingTicket => match key.code {
                         KeyCode::Char('q') | KeyCode::Esc => {
                             app.state = AppState::Normal;
                         }
@@ -188,13 +205,7 @@ pub async fn run_tui(manager: &mut TicketManager) -> Result<()> {
         // Handle async events
         while let Ok(event) = rx.try_recv() {
             match event {
-                AppEvent::Refresh => {
-                    if let Ok(tickets) = manager.list_tickets() {
-                        app.update_tickets(tickets);
-                    }
-                }
-                AppEvent::Quit => break,
-                _ => {}
+                AppEvent::Refresh => app.refresh(manager),
             }
         }
     }
@@ -211,7 +222,7 @@ pub async fn run_tui(manager: &mut TicketManager) -> Result<()> {
     Ok(())
 }
 
-fn ui(f: &mut Frame, app: &App) {
+fn ui(f: &mut Frame, app: &App, config: &Config) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -222,14 +233,19 @@ fn ui(f: &mut Frame, app: &App) {
         .split(f.size());
 
     // Header
-    let header = Paragraph::new("tkr - TUI Mode")
+    let header_text = if app.filter.is_some() {
+        format!("tkr - TUI Mode | filter: {}", app.filter_text)
+    } else {
+        "tkr - TUI Mode".to_string()
+    };
+    let header = Paragraph::new(header_text)
         .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(header, chunks[0]);
 
     // Main content
     match app.state {
-        AppState::Normal => {
+        AppState::Normal | AppState::Filtering => {
             let main_chunks = Layout::default()
                 .direction(Direction::Horizontal)
                 .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
@@ -299,6 +315,45 @@ fn ui(f: &mut Frame, app: &App) {
                 ];
 
                 let mut details_text = details;
+
+                // Declared custom fields first, then any other frontmatter keys
+                let field_names = config.custom_fields.keys()
+                    .chain(ticket.extra.keys().filter(|k| !config.custom_fields.contains_key(*k)));
+                for name in field_names {
+                    let value = ticket.extra.get(name)
+                        .map(value_to_string)
+                        .unwrap_or_else(|| "-".to_string());
+                    details_text.push(Line::from(vec![
+                        Span::styled(format!("{}: ", name), Style::default().fg(Color::Cyan)),
+                        Span::styled(value, Style::default()),
+                    ]));
+                }
+
+                if !ticket.deps.is_empty() {
+                    details_text.push(Line::from(vec![
+                        Span::styled("Deps: ", Style::default().fg(Color::Cyan)),
+                        Span::styled(ticket.deps.join(", "), Style::default()),
+                    ]));
+                }
+
+                if !ticket.links.is_empty() {
+                    details_text.push(Line::from(""));
+                    details_text.push(Line::from(vec![
+                        Span::styled("Links:", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+                    ]));
+                    for link in &ticket.links {
+                        // Show the title when the linked ticket is loaded
+                        let title = app.tickets.iter()
+                            .find(|t| t.id == link.id)
+                            .map(|t| format!(" - {}", t.title))
+                            .unwrap_or_default();
+                        details_text.push(Line::from(vec![
+                            Span::styled(format!("  {} ", link.kind.as_str()), Style::default().fg(Color::Yellow)),
+                            Span::styled(format!("{}{}", link.id, title), Style::default()),
+                        ]));
+                    }
+                }
+
                 if let Some(ref description) = ticket.description {
                     details_text.push(Line::from(""));
                     details_text.push(Line::from(vec![
@@ -319,7 +374,7 @@ fn ui(f: &mut Frame, app: &App) {
             }
         }
         AppState::Help => {
-            let help_text = vec![
+            let mut help_text = vec![
                 Line::from("Help - Keyboard Shortcuts:"),
                 Line::from(""),
                 Line::from("Navigation:"),
@@ -331,19 +386,20 @@ fn ui(f: &mut Frame, app: &App) {
                 Line::from("Actions:"),
                 Line::from("  Enter     - View ticket details"),
                 Line::from("  r         - Refresh tickets"),
-                Line::from("  1         - Set status to 'open'"),
-                Line::from("  2         - Set status to 'in_progress'"),
-                Line::from("  3         - Set status to 'closed'"),
-                Line::from(""),
-                Line::from("Press 'h', 'q', or 'Esc' to return"),
+                Line::from("  /         - Filter, e.g. 'status:open priority<=1' (Enter applies, empty clears)"),
             ];
+            for (i, status) in config.workflow.statuses.iter().take(9).enumerate() {
+                help_text.push(Line::from(format!("  {}         - Set status to '{}'", i + 1, status)));
+            }
+            help_text.push(Line::from(""));
+            help_text.push(Line::from("Press 'h', 'q', or 'Esc' to return"));
 
             let help_para = Paragraph::new(help_text)
                 .block(Block::default().borders(Borders::ALL).title("Help"))
                 .wrap(Wrap { trim: true });
             f.render_widget(help_para, chunks[1]);
         }
-        AppState::EditingTicket(_) => {
+        AppState::EditingTicket => {
             let edit_text = vec![
                 Line::from("Ticket Details View"),
                 Line::from(""),
@@ -355,22 +411,11 @@ fn ui(f: &mut Frame, app: &App) {
                 .wrap(Wrap { trim: true });
             f.render_widget(edit_para, chunks[1]);
         }
-        AppState::CreatingTicket => {
-            let create_text = vec![
-                Line::from("Create New Ticket"),
-                Line::from(""),
-                Line::from("Press 'q' or 'Esc' to cancel"),
-            ];
-
-            let create_para = Paragraph::new(create_text)
-                .block(Block::default().borders(Borders::ALL).title("Create Ticket"))
-                .wrap(Wrap { trim: true });
-            f.render_widget(create_para, chunks[1]);
-        }
     }
 
     // Footer
     let footer_text = match app.state {
+        AppState::Normal if app.message.is_some() => app.message.clone().unwrap_or_default(),
         AppState::Normal => {
             if let Some(ticket) = app.tickets.get(app.selected_ticket) {
                 format!("{} | {} | Press 'h' for help", ticket.id, ticket.status)
@@ -378,9 +423,10 @@ fn ui(f: &mut Frame, app: &App) {
                 "No tickets | Press 'h' for help".to_string()
             }
         }
+        AppState::Filtering if app.message.is_some() => app.message.clone().unwrap_or_default(),
+        AppState::Filtering => format!("/{} | Enter to apply, Esc to cancel", app.filter_input),
         AppState::Help => "Help Mode | Press 'h', 'q', or 'Esc' to return".to_string(),
-        AppState::EditingTicket(_) => "Ticket Details | Press 'q' or 'Esc' to return".to_string(),
-        AppState::CreatingTicket => "Create Ticket | Press 'q' or 'Esc' to cancel".to_string(),
+        AppState::EditingTicket => "Ticket Details | Press 'q' or 'Esc' to return".to_string(),
     };
 
     let footer = Paragraph::new(footer_text)
@@ -1,4 +1,5 @@
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use anyhow::Result;
 
 pub fn find_tickets_dir(repo_root: Option<String>) -> Result<PathBuf> {
@@ -41,6 +42,16 @@ fn check_tickets_locations(base: &Path) -> Option<PathBuf> {
     None
 }
 
+/// A hidden temp file next to `path` to write through before renaming it
+/// into place. The process ID and a per-process counter keep concurrent
+/// writers, threads included, from ever sharing one.
+pub fn temp_path(path: &Path) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let n = NEXT.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{}.{}.{}.tmp", name.trim_start_matches('.'), std::process::id(), n))
+}
+
 #[allow(dead_code)]
 pub fn get_repo_root() -> Result<PathBuf> {
     let mut current = std::env::current_dir()?;
@@ -55,3 +66,25 @@ pub fn get_repo_root() -> Result<PathBuf> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn temp_paths_are_unique_across_threads() {
+        let target = Path::new("/tickets/.index");
+        let paths: HashSet<PathBuf> = (0..8)
+            .map(|_| std::thread::spawn(move || (0..50).map(|_| temp_path(target)).collect::<Vec<_>>()))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flat_map(|thread| thread.join().unwrap())
+            .collect();
+        assert_eq!(paths.len(), 400);
+        assert!(paths.iter().all(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            path.parent() == target.parent() && name.starts_with(".index.") && name.ends_with(".tmp")
+        }));
+    }
+}
@@ -0,0 +1,134 @@
+use anyhow::Result;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, RwLock, RwLockReadGuard};
+
+use crate::ticket::{Ticket, TicketManager};
+
+/// How long to wait for a burst of file changes (a `git pull`, a status move)
+/// to settle before reloading.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Events kept for slow subscribers; one that falls further behind is told
+/// to reload instead.
+const EVENT_BUFFER: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl ChangeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChangeKind::Created => "created",
+            ChangeKind::Updated => "updated",
+            ChangeKind::Deleted => "deleted",
+        }
+    }
+}
+
+/// A change to one ticket, from whichever process made it.
+#[derive(Debug, Clone)]
+pub struct TicketChange {
+    pub kind: ChangeKind,
+    pub id: String,
+    /// The ticket as it is now; `None` once deleted
+    pub ticket: Option<Ticket>,
+}
+
+/// The web server's view of the tickets, kept in sync with the tickets
+/// directory. Every change is broadcast to subscribers.
+#[derive(Debug)]
+pub struct LiveTickets {
+    tickets: RwLock<Vec<Ticket>>,
+    changes: broadcast::Sender<TicketChange>,
+}
+
+impl LiveTickets {
+    pub fn new(tickets: Vec<Ticket>) -> Self {
+        let (changes, _) = broadcast::channel(EVENT_BUFFER);
+        Self { tickets: RwLock::new(tickets), changes }
+    }
+
+    pub async fn read(&self) -> RwLockReadGuard<'_, Vec<Ticket>> {
+        self.tickets.read().await
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TicketChange> {
+        self.changes.subscribe()
+    }
+
+    /// Reload the tickets from disk and broadcast what changed since the
+    /// last reload. Reading them blocks, so it happens on a blocking thread.
+    pub async fn reload(&self, manager: &TicketManager) -> Result<()> {
+        let manager = manager.clone();
+        let fresh = tokio::task::spawn_blocking(move || manager.list_tickets()).await??;
+        let mut tickets = self.tickets.write().await;
+
+        let old: BTreeMap<&str, &Ticket> = tickets.iter().map(|t| (t.id.as_str(), t)).collect();
+        let new: BTreeMap<&str, &Ticket> = fresh.iter().map(|t| (t.id.as_str(), t)).collect();
+        let mut changes = Vec::new();
+        for (id, ticket) in &new {
+            let kind = match old.get(id) {
+                None => ChangeKind::Created,
+                Some(before) if before != ticket => ChangeKind::Updated,
+                Some(_) => continue,
+            };
+            changes.push(TicketChange { kind, id: id.to_string(), ticket: Some((*ticket).clone()) });
+        }
+        for id in old.keys().filter(|id| !new.contains_key(*id)) {
+            changes.push(TicketChange { kind: ChangeKind::Deleted, id: id.to_string(), ticket: None });
+        }
+
+        *tickets = fresh;
+        for change in changes {
+            // No subscribers is fine
+            let _ = self.changes.send(change);
+        }
+        Ok(())
+    }
+}
+
+/// Reload `live` whenever a ticket file under the tickets directory changes,
+/// whether through the server, the CLI or git. Changes stop being watched
+/// when the returned watcher is dropped.
+pub fn watch(live: Arc<LiveTickets>, manager: Arc<RwLock<TicketManager>>, tickets_dir: &Path) -> Result<RecommendedWatcher> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let relevant = match event {
+            Ok(event) => event.paths.is_empty() || event.paths.iter().any(|path| is_ticket_file(path)),
+            // e.g. the event queue overflowed; only a reload can catch up
+            Err(_) => true,
+        };
+        if relevant {
+            let _ = tx.send(());
+        }
+    })?;
+    watcher.watch(tickets_dir, RecursiveMode::Recursive)?;
+
+    tokio::spawn(async move {
+        while rx.recv().await.is_some() {
+            tokio::time::sleep(DEBOUNCE).await;
+            while rx.try_recv().is_ok() {}
+
+            let manager = manager.read().await;
+            if let Err(e) = live.reload(&manager).await {
+                eprintln!("Failed to reload tickets: {}", e);
+            }
+        }
+    });
+
+    Ok(watcher)
+}
+
+/// Ticket files, as opposed to the index, lock, history and the `.tmp`
+/// files saves are written through.
+fn is_ticket_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "md")
+}
@@ -1,16 +1,35 @@
 use anyhow::Result;
+use rust_embed::RustEmbed;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
 use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::RwLock;
-use warp::{Filter, Reply};
-use crate::ticket::{TicketManager, Ticket};
+use warp::http::{header, HeaderValue, Method, StatusCode};
+use warp::reply::Response;
+use warp::filters::BoxedFilter;
+use warp::{Filter, Rejection, Reply};
+use crate::auth::AuthConfig;
+use crate::query::Query;
+use crate::search::SearchIndex;
+use crate::ticket::{ConflictError, CreateOptions, LinkKind, Note, ResolveError, TicketManager, Ticket};
+use crate::watch::{self, LiveTickets, TicketChange};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WebConfig {
     pub host: String,
     pub port: u16,
     pub default_assignee: Option<String>,
+    /// Credentials required for every request; none by default
+    pub auth: AuthConfig,
+    /// Reject changes, serving the board and API for reading only
+    pub read_only: bool,
+    /// Other origins whose pages may call the API, e.g.
+    /// `https://tickets.example.com`, or `*` for any
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cors_origins: Vec<String>,
 }
 
 impl Default for WebConfig {
@@ -19,6 +38,9 @@ impl Default for WebConfig {
             host: "127.0.0.1".to_string(),
             port: 8080,
             default_assignee: None,
+            auth: AuthConfig::default(),
+            read_only: false,
+            cors_origins: Vec::new(),
         }
     }
 }
@@ -27,50 +49,213 @@ pub async fn start_web_server(
     manager: &mut TicketManager,
     cli_host: String,
     cli_port: u16,
+    cli_read_only: bool,
+    web_root: Option<String>,
 ) -> Result<()> {
-    // Load config from XDG_CONFIG_HOME or HOME
-    let _config = load_config().unwrap_or_default();
+    // Config is loaded from XDG_CONFIG_HOME or HOME by the manager
+    let config = &manager.config.web;
+    let auth = Arc::new(config.auth.clone().resolve()?);
+    let read_only = cli_read_only || config.read_only;
+    let cors_origins = cors_origins(&config.cors_origins)?;
+    if let Some(root) = &web_root {
+        if !std::path::Path::new(root).join("index.html").is_file() {
+            anyhow::bail!("--web-root {} has no index.html", root);
+        }
+    }
 
     // CLI args override config file
     let host = cli_host;
     let port = cli_port;
 
-    println!("Starting web server on http://{}:{}", host, port);
+    println!("Starting web server on http://{}:{}{}", host, port, if read_only { " (read-only)" } else { "" });
+    let local = host == "localhost" || host.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback());
+    if !local && !auth.is_enabled() && !read_only {
+        eprintln!("Warning: anyone who can reach {} can change tickets; configure auth or use --read-only", host);
+    }
 
-    // Create shared state
-    let tickets = Arc::new(RwLock::new(manager.list_tickets()?));
+    // Create shared state, kept in sync with changes made by anyone
+    manager.ensure_status_directories()?;
+    let tickets = Arc::new(LiveTickets::new(manager.list_tickets()?));
+    let tickets_dir = manager.tickets_dir.clone();
     let manager = Arc::new(RwLock::new(manager.clone()));
+    let _watcher = watch::watch(tickets.clone(), manager.clone(), &tickets_dir)?;
 
-    // CORS headers
+    // CORS headers, for pages served elsewhere
     let cors = warp::cors()
-        .allow_any_origin()
-        .allow_headers(vec!["content-type"])
-        .allow_methods(vec!["GET", "POST", "PUT", "DELETE", "OPTIONS"]);
+        .allow_headers(vec!["content-type", "if-match", "authorization"])
+        .expose_headers(vec!["etag"])
+        .allow_methods(vec!["GET", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"]);
+    let cors = match cors_origins {
+        None => cors.allow_any_origin(),
+        Some(origins) => cors.allow_origins(origins.iter().map(String::as_str)).allow_credentials(true),
+    };
 
     // API routes
     let api_tickets = warp::path("api")
         .and(warp::path("tickets"))
+        .and(warp::path::end())
         .and(warp::get())
+        .and(warp::query::<TicketsQuery>())
         .and(with_tickets(tickets.clone()))
+        .and(with_manager(manager.clone()))
         .and_then(get_tickets);
 
+    let api_search = warp::path("api")
+        .and(warp::path("search"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query::<SearchParams>())
+        .and(with_tickets(tickets.clone()))
+        .and(with_manager(manager.clone()))
+        .and_then(search_tickets);
+
+    let api_ticket_create = warp::path("api")
+        .and(warp::path("tickets"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_tickets(tickets.clone()))
+        .and(with_manager(manager.clone()))
+        .and_then(create_ticket);
+
+    let api_ticket_get = warp::path("api")
+        .and(warp::path("tickets"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_manager(manager.clone()))
+        .and_then(get_ticket);
+
     let api_ticket_update = warp::path("api")
         .and(warp::path("tickets"))
         .and(warp::path::param::<String>())
-        .and(warp::put())
+        .and(warp::path::end())
+        .and(warp::put().or(warp::patch()).unify())
+        .and(warp::header::optional::<String>("if-match"))
         .and(warp::body::json())
+        .and(with_tickets(tickets.clone()))
         .and(with_manager(manager.clone()))
         .and_then(update_ticket);
 
-    // Serve static files
-    let static_files = warp::get()
-        .and(warp::fs::dir("web"))
-        .or(warp::get().and(warp::path("index.html")).and(warp::fs::file("web/index.html")));
+    let api_ticket_delete = warp::path("api")
+        .and(warp::path("tickets"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(with_tickets(tickets.clone()))
+        .and(with_manager(manager.clone()))
+        .and_then(delete_ticket);
+
+    let api_note_add = warp::path("api")
+        .and(warp::path("tickets"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("notes"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_tickets(tickets.clone()))
+        .and(with_manager(manager.clone()))
+        .and_then(add_note);
+
+    let api_dep_add = warp::path("api")
+        .and(warp::path("tickets"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("deps"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_tickets(tickets.clone()))
+        .and(with_manager(manager.clone()))
+        .and_then(add_dependency);
+
+    let api_dep_remove = warp::path("api")
+        .and(warp::path("tickets"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("deps"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(with_tickets(tickets.clone()))
+        .and(with_manager(manager.clone()))
+        .and_then(remove_dependency);
+
+    let api_link_add = warp::path("api")
+        .and(warp::path("tickets"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("links"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_tickets(tickets.clone()))
+        .and(with_manager(manager.clone()))
+        .and_then(add_link);
+
+    let api_link_remove = warp::path("api")
+        .and(warp::path("tickets"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("links"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(with_tickets(tickets.clone()))
+        .and(with_manager(manager.clone()))
+        .and_then(remove_link);
+
+    let api_ready = warp::path("api")
+        .and(warp::path("ready"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_manager(manager.clone()))
+        .and_then(get_ready);
+
+    let api_events = warp::path("api")
+        .and(warp::path("events"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_tickets(tickets.clone()))
+        .map(ticket_events);
+
+    let api_workflow = warp::path("api")
+        .and(warp::path("workflow"))
+        .and(warp::get())
+        .and(with_manager(manager.clone()))
+        .and_then(get_workflow);
+
+    // Serve the board, built in unless developing it
+    let static_files: BoxedFilter<(Response,)> = match web_root {
+        // Always re-read, so edits show up on reload
+        Some(root) => warp::get()
+            .and(warp::fs::dir(root))
+            .map(|file: warp::fs::File| warp::reply::with_header(file, header::CACHE_CONTROL, "no-store").into_response())
+            .boxed(),
+        None => warp::get()
+            .and(warp::path::tail())
+            .and(warp::header::optional::<String>("if-none-match"))
+            .and_then(embedded_asset)
+            .boxed(),
+    };
 
     let routes = static_files
         .or(api_tickets)
+        .or(api_search)
+        .or(api_ticket_create)
+        .or(api_ticket_get)
         .or(api_ticket_update)
-        .with(cors)
+        .or(api_ticket_delete)
+        .or(api_note_add)
+        .or(api_dep_add)
+        .or(api_dep_remove)
+        .or(api_link_add)
+        .or(api_link_remove)
+        .or(api_ready)
+        .or(api_events)
+        .or(api_workflow);
+    let routes = guard(auth, read_only)
+        .and(routes)
+        .recover(handle_rejection);
+    let routes = same_origin(host.clone(), port)
+        .and(routes.clone())
+        .or(routes.with(cors))
         .with(warp::log("web"));
 
     let addr: std::net::SocketAddr = format!("{}:{}", host, port).parse()?;
@@ -79,58 +264,87 @@ pub async fn start_web_server(
     Ok(())
 }
 
-fn load_config() -> Option<WebConfig> {
-    // First try git root config as override
-    if let Some(git_root_config) = load_git_root_config() {
-        return Some(git_root_config);
+/// The allowed CORS origins in the form browsers send them, or `None` for any.
+fn cors_origins(configured: &[String]) -> Result<Option<Vec<String>>> {
+    if configured.iter().any(|origin| origin == "*") {
+        return Ok(None);
     }
+    configured.iter()
+        .map(|origin| {
+            let url = url::Url::parse(origin).ok().filter(|url| url.has_host() && url.path() == "/");
+            match url {
+                Some(url) => Ok(url.origin().ascii_serialization()),
+                None => anyhow::bail!("Invalid CORS origin '{}': expected e.g. https://tickets.example.com", origin),
+            }
+        })
+        .collect::<Result<_>>()
+        .map(Some)
+}
 
-    // Fallback to XDG_CONFIG_HOME or ~/.config
-    let config_dir = std::env::var("XDG_CONFIG_HOME")
-        .ok()
-        .or_else(|| std::env::var("HOME").ok().map(|h| format!("{}/.config", h)))?;
-
-    let config_path = PathBuf::from(config_dir).join("tkr").join("config.yml");
+/// Requests from the board itself, which skip CORS: browsers send `Origin`
+/// on same-origin writes too, and only other origins are in the allowlist.
+/// The origin is checked against the address the server is bound to, not the
+/// request's `Host` header, which whoever sends the request controls.
+fn same_origin(host: String, port: u16) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("origin")
+        .and_then(move |origin: Option<String>| {
+            let same = origin.is_none_or(|origin| is_bind_origin(&origin, &host, port));
+            async move { if same { Ok(()) } else { Err(warp::reject()) } }
+        })
+        .untuple_one()
+}
 
-    if config_path.exists() {
-        let content = std::fs::read_to_string(config_path).ok()?;
-        serde_yaml::from_str(&content).ok()
-    } else {
-        None
+/// Whether `origin` is the server's own address. A server bound to loopback
+/// or to every interface can also be reached as `localhost`; other names it
+/// is reached by (behind a proxy, say) belong in `cors_origins`.
+fn is_bind_origin(origin: &str, host: &str, port: u16) -> bool {
+    let Some(url) = url::Url::parse(origin).ok().filter(|url| url.scheme() == "http") else {
+        return false;
+    };
+    if url.port_or_known_default() != Some(port) {
+        return false;
     }
+    let Some(origin_host) = url.host_str().map(|h| h.trim_start_matches('[').trim_end_matches(']')) else {
+        return false;
+    };
+    let is_local = |name: &str| {
+        name.eq_ignore_ascii_case("localhost") || name.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback())
+    };
+    let bound_to_local = is_local(host) || host.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_unspecified());
+    origin_host.eq_ignore_ascii_case(host) || (bound_to_local && is_local(origin_host))
 }
 
-fn load_git_root_config() -> Option<WebConfig> {
-    // Try to find git root and check for .config/tkr/config.yml
-    let mut current = std::env::current_dir().ok()?;
-
-    loop {
-        let config_path = current.join(".config").join("tkr").join("config.yml");
+/// Why a request was turned away before reaching a route.
+#[derive(Debug)]
+enum Denied {
+    Unauthorized,
+    ReadOnly,
+}
 
-        if config_path.exists() {
-            let content = std::fs::read_to_string(config_path).ok()?;
-            if let Some(config) = serde_yaml::from_str(&content).ok() {
-                return Some(config);
+impl warp::reject::Reject for Denied {}
+
+/// Check credentials, then refuse changes on a read-only server.
+fn guard(auth: Arc<AuthConfig>, read_only: bool) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::method()
+        .and(warp::header::optional::<String>("authorization"))
+        .and_then(move |method: Method, authorization: Option<String>| {
+            let auth = auth.clone();
+            async move {
+                if !auth.allows(authorization.as_deref()) {
+                    return Err(warp::reject::custom(Denied::Unauthorized));
+                }
+                if read_only && !method.is_safe() {
+                    return Err(warp::reject::custom(Denied::ReadOnly));
+                }
+                Ok(())
             }
-        }
-
-        if current.join(".git").exists() {
-            // Found git root, break the loop
-            break;
-        }
-
-        if !current.pop() {
-            // Reached filesystem root
-            break;
-        }
-    }
-
-    None
+        })
+        .untuple_one()
 }
 
 fn with_tickets(
-    tickets: Arc<RwLock<Vec<Ticket>>>,
-) -> impl Filter<Extract = (Arc<RwLock<Vec<Ticket>>>,), Error = std::convert::Infallible> + Clone {
+    tickets: Arc<LiveTickets>,
+) -> impl Filter<Extract = (Arc<LiveTickets>,), Error = std::convert::Infallible> + Clone {
     warp::any().map(move || tickets.clone())
 }
 
@@ -140,49 +354,429 @@ fn with_manager(
     warp::any().map(move || manager.clone())
 }
 
-async fn get_tickets(tickets: Arc<RwLock<Vec<Ticket>>>) -> Result<impl Reply, warp::Rejection> {
+/// `GET /api/tickets?q=status:open priority<=1` filters with the query language.
+async fn get_tickets(
+    params: TicketsQuery,
+    tickets: Arc<LiveTickets>,
+    manager: Arc<RwLock<TicketManager>>,
+) -> Result<Response, Rejection> {
+    let query = match Query::parse(params.q.as_deref().unwrap_or("")) {
+        Ok(query) => query,
+        Err(e) => return Ok(ApiError::new(StatusCode::BAD_REQUEST, e).into_response()),
+    };
+
+    let manager = manager.read().await;
+    let ctx = manager.query_context();
     let tickets = tickets.read().await;
-    let response: Vec<TicketApiResponse> = tickets.iter().map(|t| TicketApiResponse::from(t.clone())).collect();
-    Ok(warp::reply::json(&response))
+    let response: Vec<TicketApiResponse> = tickets.iter()
+        .filter(|t| query.matches(t, &ctx))
+        .map(|t| TicketApiResponse::from(t.clone()))
+        .collect();
+    Ok(warp::reply::json(&response).into_response())
+}
+
+/// `GET /api/search?q=login&where=status:open&limit=10` ranks tickets by
+/// relevance, with highlighted snippets.
+async fn search_tickets(
+    params: SearchParams,
+    tickets: Arc<LiveTickets>,
+    manager: Arc<RwLock<TicketManager>>,
+) -> Result<Response, Rejection> {
+    let filter = match Query::parse(params.where_query.as_deref().unwrap_or("")) {
+        Ok(filter) => filter,
+        Err(e) => return Ok(ApiError::new(StatusCode::BAD_REQUEST, e).into_response()),
+    };
+
+    let manager = manager.read().await;
+    let ctx = manager.query_context();
+    let tickets: Vec<Ticket> = tickets.read().await.iter()
+        .filter(|t| filter.matches(t, &ctx))
+        .cloned()
+        .collect();
+    let hits = SearchIndex::new(tickets).search(&params.q, params.limit.unwrap_or(20));
+    Ok(warp::reply::json(&hits).into_response())
 }
 
+/// `GET /api/tickets/:id` reads the ticket from disk; the ID can be partial
+/// or an alias, as on the command line.
+async fn get_ticket(id: String, manager: Arc<RwLock<TicketManager>>) -> Result<Response, Rejection> {
+    let manager = manager.read().await;
+    Ok(match manager.load_ticket(&id) {
+        Ok(ticket) => ticket_reply(StatusCode::OK, ticket),
+        Err(e) => ApiError::from(e).into_response(),
+    })
+}
+
+/// `GET /api/ready`: open or in-progress tickets whose deps are all closed.
+async fn get_ready(manager: Arc<RwLock<TicketManager>>) -> Result<Response, Rejection> {
+    let manager = manager.read().await;
+    Ok(match manager.list_ready_tickets() {
+        Ok(tickets) => {
+            let response: Vec<TicketApiResponse> = tickets.into_iter().map(TicketApiResponse::from).collect();
+            warp::reply::json(&response).into_response()
+        }
+        Err(e) => ApiError::from(e).into_response(),
+    })
+}
+
+/// `POST /api/tickets` creates a ticket, assigned to `default_assignee`
+/// unless the request names someone.
+async fn create_ticket(
+    new: NewTicket,
+    tickets: Arc<LiveTickets>,
+    manager: Arc<RwLock<TicketManager>>,
+) -> Result<Response, Rejection> {
+    change(tickets, manager, StatusCode::CREATED, move |manager| {
+        if new.title.trim().is_empty() {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "Title must not be empty"));
+        }
+        let options = CreateOptions {
+            issue_type: new.issue_type,
+            priority: new.priority,
+            description: new.description,
+            design: new.design,
+            acceptance: new.acceptance,
+            assignee: new.assignee.or_else(|| manager.config.web.default_assignee.clone()),
+            external_ref: new.external_ref,
+            parent: new.parent,
+            aliases: new.aliases,
+            fields: new.fields,
+        };
+        let id = manager.create_ticket(new.title, options)?;
+        Ok(manager.load_ticket(&id)?)
+    }).await
+}
+
+/// `PUT` or `PATCH /api/tickets/:id` changes the fields present in the body.
+///
+/// `If-Match` must carry the ticket's ETag (its `revision`), or `*`. If the
+/// ticket has changed since, nothing is saved and the response is 412 with
+/// the current ticket, so the client can merge and retry.
 async fn update_ticket(
     id: String,
+    if_match: Option<String>,
     update: TicketUpdate,
+    tickets: Arc<LiveTickets>,
     manager: Arc<RwLock<TicketManager>>,
-) -> Result<impl Reply, warp::Rejection> {
-    let manager = manager.write().await;
+) -> Result<Response, Rejection> {
+    change(tickets, manager, StatusCode::OK, move |manager| {
+        let _op = manager.begin_operation("web update");
+        let mut ticket = manager.load_ticket(&id)?;
+        let Some(if_match) = if_match else {
+            let message = "Updates need an If-Match header with the ticket's ETag (or *)";
+            return Err(ApiError::new(StatusCode::PRECONDITION_REQUIRED, message));
+        };
+        if !etag_matches(&if_match, ticket.revision.as_deref()) {
+            return Err(ApiError::stale(ticket));
+        }
+        // Changed by someone else between here and the save (e.g. the CLI)
+        let stale = |e: anyhow::Error| match e.downcast_ref::<ConflictError>() {
+            Some(conflict) => match manager.load_ticket(&conflict.id) {
+                Ok(current) => ApiError::stale(current),
+                Err(e) => ApiError::from(e),
+            },
+            None => ApiError::from(e),
+        };
+
+        // Apply updates
+        if let Some(title) = update.title {
+            ticket.title = title;
+        }
+        if let Some(description) = update.description {
+            ticket.description = Some(description);
+        }
+        if let Some(assignee) = update.assignee {
+            ticket.assignee = Some(assignee);
+        }
+        if let Some(priority) = update.priority {
+            ticket.priority = priority;
+        }
+
+        // Status last, so guards see the other changes from this update
+        if let Some(status) = update.status {
+            if let Err(e) = manager.check_transition(&ticket, &status) {
+                return Err(ApiError::new(StatusCode::CONFLICT, e));
+            }
+            // Same path as the CLI: moves the file and handles closure
+            manager.transition_ticket(ticket.clone(), &status).map_err(stale)?;
+        } else {
+            manager.save_ticket(&ticket).map_err(stale)?;
+        }
+        Ok(manager.load_ticket(&ticket.id)?)
+    }).await
+}
+
+/// `DELETE /api/tickets/:id` responds with the deleted ticket.
+async fn delete_ticket(
+    id: String,
+    tickets: Arc<LiveTickets>,
+    manager: Arc<RwLock<TicketManager>>,
+) -> Result<Response, Rejection> {
+    change(tickets, manager, StatusCode::OK, move |manager| Ok(manager.delete_ticket(&id)?)).await
+}
+
+async fn add_note(
+    id: String,
+    request: NoteRequest,
+    tickets: Arc<LiveTickets>,
+    manager: Arc<RwLock<TicketManager>>,
+) -> Result<Response, Rejection> {
+    change(tickets, manager, StatusCode::CREATED, move |manager| {
+        if request.note.trim().is_empty() {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "Note must not be empty"));
+        }
+        let id = manager.resolve_id(&id).map_err(anyhow::Error::from)?;
+        manager.add_note(&id, &request.note)?;
+        Ok(manager.load_ticket(&id)?)
+    }).await
+}
+
+async fn add_dependency(
+    id: String,
+    request: DepRequest,
+    tickets: Arc<LiveTickets>,
+    manager: Arc<RwLock<TicketManager>>,
+) -> Result<Response, Rejection> {
+    change(tickets, manager, StatusCode::OK, move |manager| {
+        let id = manager.resolve_id(&id).map_err(anyhow::Error::from)?;
+        manager.add_dependency(&id, &request.id, request.force)?;
+        Ok(manager.load_ticket(&id)?)
+    }).await
+}
+
+async fn remove_dependency(
+    id: String,
+    dep_id: String,
+    tickets: Arc<LiveTickets>,
+    manager: Arc<RwLock<TicketManager>>,
+) -> Result<Response, Rejection> {
+    change(tickets, manager, StatusCode::OK, move |manager| {
+        let id = manager.resolve_id(&id).map_err(anyhow::Error::from)?;
+        manager.remove_dependency(&id, &dep_id)?;
+        Ok(manager.load_ticket(&id)?)
+    }).await
+}
+
+/// `POST /api/tickets/:id/links` with `{"id": ..., "type": ...}`; the
+/// inverse link is added to the other ticket.
+async fn add_link(
+    id: String,
+    request: LinkRequest,
+    tickets: Arc<LiveTickets>,
+    manager: Arc<RwLock<TicketManager>>,
+) -> Result<Response, Rejection> {
+    change(tickets, manager, StatusCode::OK, move |manager| {
+        let (id, _) = manager.link_tickets(&id, &request.id, request.kind)?;
+        Ok(manager.load_ticket(&id)?)
+    }).await
+}
 
-    // Load the ticket
-    let mut ticket = match manager.load_ticket(&id) {
-        Ok(t) => t,
-        Err(_) => return Ok(warp::reply::with_status("", warp::http::StatusCode::NOT_FOUND)),
+async fn remove_link(
+    id: String,
+    target_id: String,
+    tickets: Arc<LiveTickets>,
+    manager: Arc<RwLock<TicketManager>>,
+) -> Result<Response, Rejection> {
+    change(tickets, manager, StatusCode::OK, move |manager| {
+        let (id, _) = manager.unlink_tickets(&id, &target_id)?;
+        Ok(manager.load_ticket(&id)?)
+    }).await
+}
+
+/// The board (`web/`), compiled into the binary so `tkr web` works from any
+/// directory.
+#[derive(RustEmbed)]
+#[folder = "web/"]
+struct Assets;
+
+/// A built-in asset; `/` is `index.html`. Browsers revalidate on every load
+/// and get 304 while the asset is unchanged, so a new tkr is picked up
+/// straight away.
+async fn embedded_asset(path: warp::path::Tail, if_none_match: Option<String>) -> Result<Response, Rejection> {
+    let path = match path.as_str() {
+        "" => "index.html",
+        path => path,
     };
+    // Anything else is left to the API routes
+    let asset = Assets::get(path).ok_or_else(warp::reject::not_found)?;
+    let etag = format!("\"{}\"", asset.metadata.sha256_hash().iter().map(|byte| format!("{:02x}", byte)).collect::<String>());
 
-    // Apply updates
-    if let Some(status) = update.status {
-        ticket.status = status;
+    let mut response = if if_none_match.as_deref().is_some_and(|tags| etag_matches(tags, Some(etag.trim_matches('"')))) {
+        warp::reply::with_status(warp::reply(), StatusCode::NOT_MODIFIED).into_response()
+    } else {
+        let mut response = Response::new(asset.data.into_owned().into());
+        if let Ok(content_type) = HeaderValue::from_str(asset.metadata.mimetype()) {
+            response.headers_mut().insert(header::CONTENT_TYPE, content_type);
+        }
+        response
+    };
+    let headers = response.headers_mut();
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        headers.insert(header::ETAG, etag);
     }
-    if let Some(title) = update.title {
-        ticket.title = title;
+    Ok(response)
+}
+
+/// `GET /api/events` streams every change to the tickets as server-sent
+/// events named `created`, `updated` or `deleted`, with `{"id", "ticket"}`
+/// as data. A `reload` event means changes were missed and the client
+/// should fetch the tickets again.
+fn ticket_events(tickets: Arc<LiveTickets>) -> impl Reply {
+    let stream = futures_util::stream::unfold(tickets.subscribe(), |mut changes| async move {
+        let event = match changes.recv().await {
+            Ok(change) => change_event(change),
+            Err(RecvError::Lagged(_)) => warp::sse::Event::default().event("reload").data("{}"),
+            Err(RecvError::Closed) => return None,
+        };
+        Some((Ok::<_, Infallible>(event), changes))
+    });
+    warp::sse::reply(warp::sse::keep_alive().stream(stream))
+}
+
+fn change_event(change: TicketChange) -> warp::sse::Event {
+    let data = serde_json::json!({
+        "id": change.id,
+        "ticket": change.ticket.map(TicketApiResponse::from),
+    });
+    warp::sse::Event::default().event(change.kind.as_str()).data(data.to_string())
+}
+
+/// Apply a change and respond with the resulting ticket, or the error.
+///
+/// The change runs on a blocking thread: it does file I/O, runs git and may
+/// wait for the repository lock, none of which should stall the runtime.
+/// The lock and history operation it takes are per thread, which holds
+/// because the whole change runs on that one thread.
+async fn change(
+    tickets: Arc<LiveTickets>,
+    manager: Arc<RwLock<TicketManager>>,
+    status: StatusCode,
+    apply: impl FnOnce(&TicketManager) -> Result<Ticket, ApiError> + Send + 'static,
+) -> Result<Response, Rejection> {
+    let manager = manager.write_owned().await;
+    let (manager, result) = match tokio::task::spawn_blocking(move || {
+        let result = apply(&manager);
+        (manager, result)
+    }).await {
+        Ok(applied) => applied,
+        Err(e) => return Ok(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("The change failed: {}", e)).into_response()),
+    };
+
+    // Right away rather than when the watcher notices, so the response and
+    // the change events agree. Even a failed change may have written
+    // something (e.g. a closed ticket whose dependents couldn't be unblocked).
+    if let Err(e) = tickets.reload(&manager).await {
+        eprintln!("Failed to reload tickets: {}", e);
+    }
+
+    Ok(match result {
+        Ok(ticket) => ticket_reply(status, ticket),
+        Err(e) => e.into_response(),
+    })
+}
+
+/// A ticket as JSON, with its revision as the `ETag`.
+fn ticket_reply(status: StatusCode, ticket: Ticket) -> Response {
+    let etag = ticket.revision.as_deref().map(|revision| format!("\"{}\"", revision));
+    let mut response = warp::reply::with_status(warp::reply::json(&TicketApiResponse::from(ticket)), status).into_response();
+    if let Some(etag) = etag.and_then(|etag| etag.parse().ok()) {
+        response.headers_mut().insert(header::ETAG, etag);
     }
-    if let Some(description) = update.description {
-        ticket.description = Some(description);
+    response
+}
+
+/// Whether an `If-Match` header (`"rev"`, a list of them, or `*`) matches
+/// the current revision.
+fn etag_matches(if_match: &str, revision: Option<&str>) -> bool {
+    if_match.split(',')
+        .map(|tag| tag.trim().trim_start_matches("W/").trim_matches('"'))
+        .any(|tag| tag == "*" || Some(tag) == revision)
+}
+
+/// An error response: `{"error": message}` with an HTTP status, plus the
+/// current `ticket` when the client's copy was stale.
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+    current: Option<Box<Ticket>>,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl std::fmt::Display) -> Self {
+        Self { status, message: message.to_string(), current: None }
     }
-    if let Some(assignee) = update.assignee {
-        ticket.assignee = Some(assignee);
+
+    /// 412: the ticket changed since the client loaded it.
+    fn stale(current: Ticket) -> Self {
+        let message = format!("Ticket {} was changed by someone else; merge with the current version and retry", current.id);
+        Self { current: Some(Box::new(current)), ..Self::new(StatusCode::PRECONDITION_FAILED, message) }
     }
-    if let Some(priority) = update.priority {
-        ticket.priority = priority;
+
+    fn into_response(self) -> Response {
+        let mut body = serde_json::json!({ "error": self.message });
+        if let Some(current) = self.current {
+            body["ticket"] = serde_json::json!(TicketApiResponse::from(*current));
+        }
+        warp::reply::with_status(warp::reply::json(&body), self.status).into_response()
     }
+}
 
-    // Save the ticket
-    if let Err(e) = manager.save_ticket(&ticket) {
-        eprintln!("Failed to save ticket: {}", e);
-        return Ok(warp::reply::with_status("", warp::http::StatusCode::INTERNAL_SERVER_ERROR));
+/// Unknown tickets are 404 and concurrent changes 409. I/O failures are the
+/// server's fault; anything else the manager rejects (cycles, unknown custom
+/// fields, ...) is a bad request.
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        let status = if let Some(e) = error.downcast_ref::<ResolveError>() {
+            match e {
+                ResolveError::NotFound(_) => StatusCode::NOT_FOUND,
+                ResolveError::Ambiguous { .. } => StatusCode::BAD_REQUEST,
+            }
+        } else if error.is::<ConflictError>() {
+            StatusCode::CONFLICT
+        } else if error.is::<std::io::Error>() {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::BAD_REQUEST
+        };
+        Self::new(status, error)
     }
+}
 
-    Ok(warp::reply::with_status("", warp::http::StatusCode::OK))
+/// Turn warp's rejections (no such route, malformed JSON bodies, ...) into
+/// the same JSON error bodies as the handlers.
+async fn handle_rejection(rejection: Rejection) -> Result<Response, Infallible> {
+    let error = if let Some(denied) = rejection.find::<Denied>() {
+        match denied {
+            Denied::Unauthorized => {
+                // Lets browsers prompt for a user name and password (or the token)
+                let mut response = ApiError::new(StatusCode::UNAUTHORIZED, "Authentication required").into_response();
+                response.headers_mut().insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Basic realm=\"tkr\""));
+                return Ok(response);
+            }
+            Denied::ReadOnly => ApiError::new(StatusCode::FORBIDDEN, "The server is read-only"),
+        }
+    } else if rejection.is_not_found() {
+        ApiError::new(StatusCode::NOT_FOUND, "Not found")
+    } else if let Some(e) = rejection.find::<warp::filters::body::BodyDeserializeError>() {
+        ApiError::new(StatusCode::BAD_REQUEST, e)
+    } else if let Some(e) = rejection.find::<warp::reject::InvalidQuery>() {
+        ApiError::new(StatusCode::BAD_REQUEST, e)
+    } else if let Some(e) = rejection.find::<warp::reject::UnsupportedMediaType>() {
+        ApiError::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, e)
+    } else if let Some(e) = rejection.find::<warp::reject::MethodNotAllowed>() {
+        ApiError::new(StatusCode::METHOD_NOT_ALLOWED, e)
+    } else {
+        eprintln!("Unhandled rejection: {:?}", rejection);
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+    };
+    Ok(error.into_response())
+}
+
+async fn get_workflow(manager: Arc<RwLock<TicketManager>>) -> Result<impl Reply, warp::Rejection> {
+    let manager = manager.read().await;
+    Ok(warp::reply::json(&manager.config.workflow))
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -196,9 +790,17 @@ pub struct TicketApiResponse {
     pub priority: i32,
     pub issue_type: String,
     pub description: Option<String>,
+    pub design: Option<String>,
+    pub acceptance: Option<String>,
+    pub parent: Option<String>,
     pub created: chrono::DateTime<chrono::Utc>,
     pub deps: Vec<String>,
-    pub links: Vec<String>,
+    pub links: Vec<LinkResponse>,
+    pub notes: Vec<Note>,
+    /// Custom fields and other extra frontmatter keys
+    pub fields: BTreeMap<String, serde_yaml::Value>,
+    /// Changes whenever the ticket file does; send it back in `If-Match`
+    pub revision: Option<String>,
 }
 
 impl From<Ticket> for TicketApiResponse {
@@ -213,13 +815,42 @@ impl From<Ticket> for TicketApiResponse {
             priority: ticket.priority,
             issue_type: ticket.issue_type,
             description: ticket.description,
+            design: ticket.design,
+            acceptance: ticket.acceptance,
+            parent: ticket.parent,
             created: ticket.created,
             deps: ticket.deps,
-            links: ticket.links,
+            links: ticket.links.into_iter()
+                .map(|link| LinkResponse { id: link.id, kind: link.kind })
+                .collect(),
+            notes: ticket.notes.unwrap_or_default(),
+            fields: ticket.extra,
+            revision: ticket.revision,
         }
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LinkResponse {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: LinkKind,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TicketsQuery {
+    pub q: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchParams {
+    #[serde(default)]
+    pub q: String,
+    #[serde(rename = "where")]
+    pub where_query: Option<String>,
+    pub limit: Option<usize>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct TicketUpdate {
     pub status: Option<String>,
@@ -228,3 +859,57 @@ pub struct TicketUpdate {
     pub assignee: Option<String>,
     pub priority: Option<i32>,
 }
+
+/// Body of `POST /api/tickets`; only `title` is required.
+#[derive(Debug, Deserialize)]
+pub struct NewTicket {
+    pub title: String,
+    pub description: Option<String>,
+    pub design: Option<String>,
+    pub acceptance: Option<String>,
+    #[serde(rename = "type", default = "default_issue_type")]
+    pub issue_type: String,
+    #[serde(default = "default_priority")]
+    pub priority: i32,
+    pub assignee: Option<String>,
+    pub external_ref: Option<String>,
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Custom field values, validated like `--field KEY=VALUE`
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+}
+
+// Same defaults as `tkr create`
+fn default_issue_type() -> String {
+    "task".to_string()
+}
+
+fn default_priority() -> i32 {
+    2
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NoteRequest {
+    pub note: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DepRequest {
+    pub id: String,
+    /// Allow unknown tickets and cycles, like `tkr dep --force`
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LinkRequest {
+    pub id: String,
+    #[serde(rename = "type", default = "default_link_kind")]
+    pub kind: LinkKind,
+}
+
+fn default_link_kind() -> LinkKind {
+    LinkKind::RelatesTo
+}
@@ -1,4 +1,4 @@
-use assert_cmd::Command;
+use assert_cmd::cargo::cargo_bin_cmd;
 use predicates::prelude::*;
 use tempfile::TempDir;
 use std::fs;
@@ -32,9 +32,116 @@ fn find_ticket_files(tickets_dir: &PathBuf) -> Vec<PathBuf> {
     ticket_files
 }
 
+/// A `tkr web` server on a free port, stopped when dropped.
+struct WebServer {
+    child: std::process::Child,
+    port: u16,
+}
+
+impl WebServer {
+    fn start(tickets_dir: &std::path::Path, args: &[&str]) -> Self {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let child = std::process::Command::new(env!("CARGO_BIN_EXE_tkr"))
+            // Finds .config/tkr/config.yml next to the tickets
+            .current_dir(tickets_dir.parent().unwrap())
+            .env("TICKETS_DIR", tickets_dir)
+            .args(["web", "--port", &port.to_string()])
+            .args(args)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+
+        let server = WebServer { child, port };
+        for _ in 0..100 {
+            if std::net::TcpStream::connect(("127.0.0.1", port)).is_ok() {
+                return server;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        panic!("tkr web did not start on port {}", port);
+    }
+
+    /// Send a request and return the status code, headers and body.
+    fn request(&self, method: &str, path: &str, headers: &[(&str, &str)], body: Option<&str>) -> (u16, String, String) {
+        use std::io::{Read, Write};
+
+        let mut stream = std::net::TcpStream::connect(("127.0.0.1", self.port)).unwrap();
+        let body = body.unwrap_or("");
+        let mut request = format!("{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n", method, path);
+        for (name, value) in headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if !body.is_empty() {
+            request.push_str("Content-Type: application/json\r\n");
+        }
+        request.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+        let status = head.split_whitespace().nth(1).and_then(|code| code.parse().ok()).unwrap_or(0);
+        (status, head.to_string(), body.to_string())
+    }
+
+    /// Subscribe to `/api/events`.
+    fn events(&self) -> EventStream {
+        use std::io::Write;
+
+        let mut stream = std::net::TcpStream::connect(("127.0.0.1", self.port)).unwrap();
+        stream.set_read_timeout(Some(std::time::Duration::from_millis(100))).unwrap();
+        stream.write_all(b"GET /api/events HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut events = EventStream { stream, received: String::new() };
+        events.wait_for("200 OK");
+        events
+    }
+
+    fn json(&self, method: &str, path: &str, body: Option<serde_json::Value>) -> (u16, serde_json::Value) {
+        let (status, _, body) = self.json_with(method, path, &[], body);
+        (status, body)
+    }
+
+    fn json_with(&self, method: &str, path: &str, headers: &[(&str, &str)], body: Option<serde_json::Value>) -> (u16, String, serde_json::Value) {
+        let body = body.map(|body| body.to_string());
+        let (status, head, body) = self.request(method, path, headers, body.as_deref());
+        (status, head, serde_json::from_str(&body).unwrap_or(serde_json::Value::Null))
+    }
+}
+
+struct EventStream {
+    stream: std::net::TcpStream,
+    received: String,
+}
+
+impl EventStream {
+    /// Read until `text` arrives, returning everything up to and including it.
+    fn wait_for(&mut self, text: &str) -> String {
+        use std::io::Read;
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        let mut buf = [0u8; 4096];
+        while !self.received.contains(text) {
+            assert!(std::time::Instant::now() < deadline, "no {:?} in {:?}", text, self.received);
+            if let Ok(n) = self.stream.read(&mut buf) {
+                self.received.push_str(&String::from_utf8_lossy(&buf[..n]));
+            }
+        }
+        let end = self.received.find(text).unwrap() + text.len();
+        self.received.drain(..end).collect()
+    }
+}
+
+impl Drop for WebServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
 #[test]
 fn test_help() {
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.arg("--help")
         .assert()
         .success()
@@ -46,7 +153,7 @@ fn test_create_ticket() {
     let temp_dir = TempDir::new().unwrap();
     let tickets_dir = temp_dir.path().join(".tickets");
 
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Test ticket")
@@ -79,7 +186,7 @@ fn test_create_ticket_with_project_category() {
     let temp_dir = TempDir::new().unwrap();
     let tickets_dir = temp_dir.path().join(".tickets");
 
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("--project")
         .arg("test-project")
@@ -104,7 +211,7 @@ fn test_create_ticket_with_project_category() {
 fn test_list_tickets() {
     let temp_dir = TempDir::new().unwrap();
     let tickets_dir = temp_dir.path().join(".tickets");
-    fs::create_dir_all(&tickets_dir).unwrap();
+    fs::create_dir_all(tickets_dir.join("open")).unwrap();
 
     // Create a test ticket file using the same format as our app
     let ticket_content = r#"---
@@ -120,9 +227,9 @@ priority: 2
 # Test Ticket
 Test description
 "#;
-    fs::write(tickets_dir.join("test-123.md"), ticket_content).unwrap();
+    fs::write(tickets_dir.join("open").join("test-123.md"), ticket_content).unwrap();
 
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("list")
         .assert()
@@ -136,7 +243,7 @@ fn test_ticket_status_update() {
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // First create a ticket
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Status test ticket")
@@ -154,7 +261,7 @@ fn test_ticket_status_update() {
         .unwrap();
 
     // Update status
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("status")
         .arg(ticket_id)
@@ -164,7 +271,10 @@ fn test_ticket_status_update() {
         .stdout(predicate::str::contains("Updated"));
 
     // Verify status changed to in_progress
-    let content = fs::read_to_string(ticket_path).unwrap();
+    let moved_path = tickets_dir.join("in_progress").join(format!("{}.md", ticket_id));
+    assert!(!ticket_path.exists(), "old status file should be moved");
+    assert_eq!(find_ticket_files(&tickets_dir).len(), 1);
+    let content = fs::read_to_string(moved_path).unwrap();
     assert!(content.contains("status: in_progress"));
 }
 
@@ -174,7 +284,7 @@ fn test_add_note() {
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // First create a ticket
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Note test ticket")
@@ -192,7 +302,7 @@ fn test_add_note() {
         .unwrap();
 
     // Add a note
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("add-note")
         .arg(ticket_id)
@@ -207,13 +317,286 @@ fn test_add_note() {
     assert!(content.contains("Test note content"));
 }
 
+#[test]
+fn test_hand_written_markdown_survives_updates() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .arg("create")
+        .arg("Round-trip ticket")
+        .arg("--description")
+        .arg("Original description")
+        .assert()
+        .success();
+
+    let ticket_files = find_ticket_files(&tickets_dir);
+    let ticket_path = ticket_files[0].as_path();
+    let ticket_id = ticket_path
+        .file_stem()
+        .unwrap()
+        .to_str()
+        .unwrap();
+
+    // Append a hand-written section below the generated body
+    let mut content = fs::read_to_string(ticket_path).unwrap();
+    content.push_str("\n## Context\n\nWritten   by *hand*.\n\n```\n## not a heading\n```\n");
+    fs::write(ticket_path, &content).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .arg("add-note")
+        .arg(ticket_id)
+        .arg("A note")
+        .assert()
+        .success();
+
+    // Everything before the new notes section is untouched
+    let updated = fs::read_to_string(ticket_path).unwrap();
+    let body_start = content.find("\n# Round-trip ticket").unwrap();
+    let updated_body_start = updated.find("\n# Round-trip ticket").unwrap();
+    assert!(updated[updated_body_start..].starts_with(&content[body_start..]));
+    assert!(updated.contains("## Notes"));
+    assert!(updated.contains("A note"));
+}
+
+#[test]
+fn test_custom_fields_and_unknown_keys() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    let config_dir = temp_dir.path().join(".config").join("tkr");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(config_dir.join("config.yml"), r#"custom_fields:
+  estimate:
+    type: int
+  sprint:
+    type: enum
+    values: [s1, s2]
+    required: true
+"#).unwrap();
+
+    // Required and typed fields are validated
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.current_dir(temp_dir.path())
+        .env("TICKETS_DIR", &tickets_dir)
+        .arg("create")
+        .arg("Missing sprint")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Missing required field 'sprint'"));
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.current_dir(temp_dir.path())
+        .env("TICKETS_DIR", &tickets_dir)
+        .arg("create")
+        .arg("Custom fields")
+        .arg("--field")
+        .arg("sprint=s1")
+        .arg("--field")
+        .arg("estimate=3")
+        .assert()
+        .success();
+
+    let ticket_files = find_ticket_files(&tickets_dir);
+    let ticket_path = ticket_files[0].as_path();
+    let ticket_id = ticket_path.file_stem().unwrap().to_str().unwrap();
+
+    // Add a key tkr knows nothing about
+    let content = fs::read_to_string(ticket_path).unwrap();
+    fs::write(ticket_path, content.replacen("priority: 2\n", "priority: 2\ncomponent: billing\n", 1)).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.current_dir(temp_dir.path())
+        .env("TICKETS_DIR", &tickets_dir)
+        .arg("add-note")
+        .arg(ticket_id)
+        .arg("Keep my keys")
+        .assert()
+        .success();
+
+    let content = fs::read_to_string(ticket_path).unwrap();
+    assert!(content.contains("component: billing"));
+    assert!(content.contains("estimate: 3"));
+    assert!(content.contains("sprint: s1"));
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.current_dir(temp_dir.path())
+        .env("TICKETS_DIR", &tickets_dir)
+        .arg("list")
+        .arg("--field")
+        .arg("component=billing")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(ticket_id));
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.current_dir(temp_dir.path())
+        .env("TICKETS_DIR", &tickets_dir)
+        .arg("list")
+        .arg("--field")
+        .arg("sprint=s2")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No tickets found"));
+
+    // A typo in the config is reported rather than dropping the field rules
+    fs::write(config_dir.join("config.yml"), "custom_fields:\n  sprint:\n    type: enumm\n    required: true\n").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.current_dir(temp_dir.path())
+        .env("TICKETS_DIR", &tickets_dir)
+        .arg("create")
+        .arg("Missing sprint")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid config"))
+        .stderr(predicate::str::contains("enumm"));
+    let open: Vec<_> = fs::read_dir(tickets_dir.join("open")).unwrap().collect();
+    assert_eq!(open.len(), 1);
+}
+
+#[test]
+fn test_configured_workflow() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    let config_dir = temp_dir.path().join(".config").join("tkr");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(config_dir.join("config.yml"), r#"workflow:
+  statuses: [open, in_progress, review, closed]
+  transitions:
+    open: [in_progress]
+    in_progress: [review, open]
+  guards:
+    in_progress: [assignee]
+"#).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.current_dir(temp_dir.path())
+        .env("TICKETS_DIR", &tickets_dir)
+        .arg("create")
+        .arg("Workflow ticket")
+        .assert()
+        .success();
+
+    let ticket_files = find_ticket_files(&tickets_dir);
+    let ticket_id = ticket_files[0].file_stem().unwrap().to_str().unwrap().to_string();
+
+    // Status directories follow the configured statuses
+    assert!(tickets_dir.join("review").is_dir());
+    assert!(!tickets_dir.join("icebox").exists());
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.current_dir(temp_dir.path())
+        .env("TICKETS_DIR", &tickets_dir)
+        .arg("status")
+        .arg(&ticket_id)
+        .arg("icebox")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid status: icebox"));
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.current_dir(temp_dir.path())
+        .env("TICKETS_DIR", &tickets_dir)
+        .arg("close")
+        .arg(&ticket_id)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Cannot move"));
+
+    // Guard: in_progress requires an assignee
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.current_dir(temp_dir.path())
+        .env("TICKETS_DIR", &tickets_dir)
+        .arg("start")
+        .arg(&ticket_id)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("without an assignee"));
+}
+
+#[test]
+fn test_custom_workflow_statuses() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    let config_dir = temp_dir.path().join(".config").join("tkr");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(config_dir.join("config.yml"), r#"workflow:
+  statuses: [todo, doing, stuck, done]
+  closed_statuses: [done]
+  initial_status: todo
+  start_status: doing
+  close_status: done
+  active_statuses: [todo, doing]
+  blocked_status: stuck
+"#).unwrap();
+
+    let run = |args: &[&str]| {
+        let output = cargo_bin_cmd!("tkr")
+            .current_dir(temp_dir.path())
+            .env("TICKETS_DIR", &tickets_dir)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    };
+    let file = |status: &str, id: &str| tickets_dir.join(status).join(format!("{}.md", id));
+
+    let id = run(&["create", "Custom flow"]);
+    assert!(file("todo", &id).exists());
+    assert!(!tickets_dir.join("open").exists());
+    assert_eq!(run(&["ready", "--format", "template", "--template", "{id}"]), id);
+
+    run(&["start", &id]);
+    let content = fs::read_to_string(file("doing", &id)).unwrap();
+    assert!(content.contains("started_at: "), "{}", content);
+
+    // A blocked ticket waiting on this one moves back to the initial status
+    let waiting = run(&["create", "Waiting"]);
+    run(&["dep", &waiting, &id]);
+    run(&["status", &waiting, "stuck"]);
+    assert_eq!(run(&["blocked", "--format", "template", "--template", "{id}"]), waiting);
+
+    run(&["close", &id]);
+    let content = fs::read_to_string(file("done", &id)).unwrap();
+    assert!(content.contains("closed_at: "), "{}", content);
+    assert!(file("todo", &waiting).exists());
+
+    run(&["reopen", &id]);
+    assert!(file("todo", &id).exists());
+
+    // Roles must name configured statuses
+    fs::write(config_dir.join("config.yml"), "workflow:\n  statuses: [todo, done]\n  closed_statuses: [done]\n").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.current_dir(temp_dir.path())
+        .env("TICKETS_DIR", &tickets_dir)
+        .args(["create", "Nowhere"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("workflow.initial_status 'open' is not one of workflow.statuses (todo, done)"));
+
+    // A workflow that doesn't parse stops commands instead of falling back
+    // to the default statuses and losing its transitions
+    fs::write(config_dir.join("config.yml"), "workflow:\n  statuses: [todo, doing, done]\n  closed_statuses: done\n").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.current_dir(temp_dir.path())
+        .env("TICKETS_DIR", &tickets_dir)
+        .args(["close", &id])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid config"))
+        .stderr(predicate::str::contains("closed_statuses"));
+    assert!(file("todo", &id).exists());
+}
+
 #[test]
 fn test_dependency_management() {
     let temp_dir = TempDir::new().unwrap();
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // Create first ticket
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Parent ticket")
@@ -221,7 +604,7 @@ fn test_dependency_management() {
         .success();
 
     // Create second ticket
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Child ticket")
@@ -229,27 +612,18 @@ fn test_dependency_management() {
         .success();
 
     // Get ticket IDs
-    let ticket_files: Vec<_> = fs::read_dir(&tickets_dir)
-        .unwrap()
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| {
-            entry.path()
-                .extension()
-                .map(|ext| ext == "md")
-                .unwrap_or(false)
-        })
-        .collect();
+    let ticket_files = find_ticket_files(&tickets_dir);
 
     // Ensure we have 2 tickets
     assert_eq!(ticket_files.len(), 2, "Expected 2 tickets to be created, found {}", ticket_files.len());
 
-    let parent_path = ticket_files[0].path();
+    let parent_path = ticket_files[0].as_path();
     let parent_id = parent_path
         .file_stem()
         .unwrap()
         .to_str()
         .unwrap();
-    let child_path = ticket_files[1].path();
+    let child_path = ticket_files[1].as_path();
     let child_id = child_path
         .file_stem()
         .unwrap()
@@ -257,7 +631,7 @@ fn test_dependency_management() {
         .unwrap();
 
     // Add dependency
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("dep")
         .arg(child_id)
@@ -267,7 +641,7 @@ fn test_dependency_management() {
         .stdout(predicate::str::contains("Added dependency"));
 
     // Remove dependency
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("undep")
         .arg(child_id)
@@ -283,7 +657,7 @@ fn test_start_command() {
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // First create a ticket
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Test ticket for start")
@@ -301,7 +675,7 @@ fn test_start_command() {
         .unwrap();
 
     // Start the ticket
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("start")
         .arg(ticket_id)
@@ -310,7 +684,10 @@ fn test_start_command() {
         .stdout(predicate::str::contains("Started"));
 
     // Verify status changed to in_progress
-    let content = fs::read_to_string(ticket_path).unwrap();
+    let moved_path = tickets_dir.join("in_progress").join(format!("{}.md", ticket_id));
+    assert!(!ticket_path.exists(), "old status file should be moved");
+    assert_eq!(find_ticket_files(&tickets_dir).len(), 1);
+    let content = fs::read_to_string(moved_path).unwrap();
     assert!(content.contains("status: in_progress"));
 }
 
@@ -320,7 +697,7 @@ fn test_close_command() {
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // First create a ticket
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Test ticket for close")
@@ -338,7 +715,7 @@ fn test_close_command() {
         .unwrap();
 
     // Close the ticket
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("close")
         .arg(ticket_id)
@@ -347,17 +724,255 @@ fn test_close_command() {
         .stdout(predicate::str::contains("Closed"));
 
     // Verify status changed to closed
-    let content = fs::read_to_string(ticket_path).unwrap();
+    let moved_path = tickets_dir.join("closed").join(format!("{}.md", ticket_id));
+    assert!(!ticket_path.exists(), "old status file should be moved");
+    assert_eq!(find_ticket_files(&tickets_dir).len(), 1);
+    let content = fs::read_to_string(moved_path).unwrap();
     assert!(content.contains("status: closed"));
 }
 
+#[test]
+fn test_close_unblocks_dependents_without_duplicates() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+
+    let create = |title: &str| {
+        let output = cargo_bin_cmd!("tkr")
+            .env("TICKETS_DIR", &tickets_dir)
+            .arg("create")
+            .arg(title)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    };
+    let blocker_id = create("Blocker");
+    let blocked_id = create("Blocked");
+
+    for args in [
+        vec!["dep", blocked_id.as_str(), blocker_id.as_str()],
+        vec!["status", blocked_id.as_str(), "blocked"],
+        vec!["close", blocker_id.as_str()],
+    ] {
+        let mut cmd = cargo_bin_cmd!("tkr");
+        cmd.env("TICKETS_DIR", &tickets_dir).args(&args).assert().success();
+    }
+
+    // Each ticket exists exactly once, in the directory for its new status
+    let ticket_files = find_ticket_files(&tickets_dir);
+    assert_eq!(ticket_files.len(), 2);
+    assert!(tickets_dir.join("closed").join(format!("{}.md", blocker_id)).exists());
+    let unblocked = fs::read_to_string(tickets_dir.join("ready").join(format!("{}.md", blocked_id))).unwrap();
+    assert!(unblocked.contains("status: ready"));
+}
+
+#[test]
+fn test_doctor_reports_and_fixes_problems() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    fs::create_dir_all(tickets_dir.join("open")).unwrap();
+    fs::create_dir_all(tickets_dir.join("closed")).unwrap();
+
+    let ticket = |id: &str, status: &str, deps: &str| format!(
+        "---\nid: {}\ntitle: Ticket {}\nstatus: {}\ndeps: {}\nlinks: []\ncreated: 2023-01-01T00:00:00Z\ntype: task\npriority: 2\n---\n# Ticket {}\n",
+        id, id, status, deps, id
+    );
+
+    // Duplicate: a stale copy of dup-1 left behind in closed/
+    fs::write(tickets_dir.join("open").join("dup-1.md"), ticket("dup-1", "open", "[]")).unwrap();
+    fs::write(tickets_dir.join("closed").join("dup-1.md"), ticket("dup-1", "open", "[]")).unwrap();
+    // Status mismatch plus a dangling dependency
+    fs::write(tickets_dir.join("open").join("moved-1.md"), ticket("moved-1", "closed", "[gone-1]")).unwrap();
+    // Cycle
+    fs::write(tickets_dir.join("open").join("cyc-a.md"), ticket("cyc-a", "open", "[cyc-b]")).unwrap();
+    fs::write(tickets_dir.join("open").join("cyc-b.md"), ticket("cyc-b", "open", "[cyc-a]")).unwrap();
+    // Unparseable YAML and a leftover bash tk file
+    fs::write(tickets_dir.join("open").join("bad-1.md"), "---\nid: [unclosed\n---\n").unwrap();
+    fs::write(tickets_dir.join("legacy-1.md"), "# Legacy ticket\nStatus: open\n").unwrap();
+
+    let output = cargo_bin_cmd!("tkr")
+        .env("TICKETS_DIR", &tickets_dir)
+        .args(["doctor", "--json"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["ok"], false);
+    let kinds: Vec<&str> = report["problems"].as_array().unwrap().iter()
+        .map(|p| p["kind"].as_str().unwrap())
+        .collect();
+    for kind in ["duplicate_id", "status_mismatch", "dangling_reference", "invalid_ticket", "legacy_file", "dependency_cycle"] {
+        assert!(kinds.contains(&kind), "missing {} in {:?}", kind, kinds);
+    }
+    // Only --fix is an operation of the history
+    assert!(!tickets_dir.join(".history.jsonl").exists());
+
+    // --fix repairs everything except the broken file and the cycle
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .args(["doctor", "--fix"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("fixed: kept open/dup-1.md"))
+        .stdout(predicate::str::contains("Dependency cycle: cyc-a -> cyc-b -> cyc-a"));
+
+    assert!(!tickets_dir.join("closed").join("dup-1.md").exists());
+    assert!(!tickets_dir.join("legacy-1.md").exists());
+    assert!(tickets_dir.join("open").join("legacy-1.md").exists());
+    let moved = fs::read_to_string(tickets_dir.join("closed").join("moved-1.md")).unwrap();
+    assert!(!moved.contains("gone-1"));
+
+    let output = cargo_bin_cmd!("tkr")
+        .env("TICKETS_DIR", &tickets_dir)
+        .args(["doctor", "--json"])
+        .output()
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["problems"].as_array().unwrap().len(), 2);
+
+    // Undo puts back every file --fix moved or removed
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .arg("undo")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Undid 'doctor --fix'"));
+
+    assert_eq!(fs::read_to_string(tickets_dir.join("closed").join("dup-1.md")).unwrap(), ticket("dup-1", "open", "[]"));
+    assert_eq!(fs::read_to_string(tickets_dir.join("legacy-1.md")).unwrap(), "# Legacy ticket\nStatus: open\n");
+    assert!(!tickets_dir.join("open").join("legacy-1.md").exists());
+    assert!(!tickets_dir.join("closed").join("moved-1.md").exists());
+    let moved = fs::read_to_string(tickets_dir.join("open").join("moved-1.md")).unwrap();
+    assert!(moved.contains("gone-1"), "{}", moved);
+}
+
+#[test]
+fn test_partial_id_resolution() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    fs::create_dir_all(tickets_dir.join("open")).unwrap();
+
+    let ticket = |id: &str, title: &str, extra: &str| format!(
+        "---\nid: {}\ntitle: {}\nstatus: open\ndeps: []\nlinks: []\ncreated: 2023-01-01T00:00:00Z\ntype: task\npriority: 2\n{}---\n# {}\n",
+        id, title, extra, title
+    );
+    fs::write(tickets_dir.join("open").join("abc-111.md"), ticket("abc-111", "Fix login bug!", "")).unwrap();
+    fs::write(tickets_dir.join("open").join("abc-112.md"), ticket("abc-112", "Other work", "aliases:\n- perf\n")).unwrap();
+
+    // An ambiguous prefix lists every candidate instead of picking one
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .args(["close", "abc-11"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("'abc-11' matches 2 tickets"))
+        .stderr(predicate::str::contains("abc-111 - Fix login bug! (open)"))
+        .stderr(predicate::str::contains("abc-112 - Other work (open)"));
+
+    // Unknown references fail instead of resolving to a new file
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .args(["close", "zzz"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Ticket 'zzz' not found"));
+    assert_eq!(find_ticket_files(&tickets_dir).len(), 2);
+
+    // Title slugs and aliases resolve to the full ID
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .args(["close", "fix-login-bug"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Closed abc-111"));
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .args(["start", "PERF"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Started abc-112"));
+}
+
+#[test]
+fn test_concurrent_writers_do_not_lose_updates() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+
+    let output = cargo_bin_cmd!("tkr")
+        .env("TICKETS_DIR", &tickets_dir)
+        .args(["create", "Busy ticket"])
+        .output()
+        .unwrap();
+    let ticket_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+
+    // Every process reads, appends a note and writes back under the repo lock
+    let children: Vec<_> = (0..8)
+        .map(|i| {
+            std::process::Command::new(env!("CARGO_BIN_EXE_tkr"))
+                .env("TICKETS_DIR", &tickets_dir)
+                .args(["add-note", &ticket_id, &format!("note number {}", i)])
+                .stdout(std::process::Stdio::null())
+                .spawn()
+                .unwrap()
+        })
+        .collect();
+    for mut child in children {
+        assert!(child.wait().unwrap().success());
+    }
+
+    let content = fs::read_to_string(tickets_dir.join("open").join(format!("{}.md", ticket_id))).unwrap();
+    for i in 0..8 {
+        assert!(content.contains(&format!("note number {}", i)), "lost note {}", i);
+    }
+
+    // No temp files are left behind
+    let leftovers: Vec<_> = fs::read_dir(tickets_dir.join("open")).unwrap()
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().ends_with(".tmp"))
+        .collect();
+    assert!(leftovers.is_empty());
+}
+
+#[test]
+fn test_dep_rejects_self_unknown_and_cycles() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    fs::create_dir_all(tickets_dir.join("open")).unwrap();
+
+    let ticket = |id: &str, deps: &str| format!(
+        "---\nid: {}\ntitle: Ticket {}\nstatus: open\ndeps: {}\nlinks: []\ncreated: 2023-01-01T00:00:00Z\ntype: task\npriority: 2\n---\n# Ticket {}\n",
+        id, id, deps, id
+    );
+    fs::write(tickets_dir.join("open").join("a-1.md"), ticket("a-1", "[b-1]")).unwrap();
+    fs::write(tickets_dir.join("open").join("b-1.md"), ticket("b-1", "[c-1]")).unwrap();
+    fs::write(tickets_dir.join("open").join("c-1.md"), ticket("c-1", "[]")).unwrap();
+
+    let dep = |args: &[&str]| {
+        let mut cmd = cargo_bin_cmd!("tkr");
+        cmd.env("TICKETS_DIR", &tickets_dir).arg("dep").args(args);
+        cmd.assert()
+    };
+
+    dep(&["a-1", "a-1"]).failure().stderr(predicate::str::contains("a-1 cannot depend on itself"));
+    dep(&["a-1", "nope"]).failure().stderr(predicate::str::contains("Ticket 'nope' not found"));
+    dep(&["c-1", "a-1"])
+        .failure()
+        .stderr(predicate::str::contains("would create a dependency cycle: c-1 -> a-1 -> b-1 -> c-1"));
+    let unchanged = fs::read_to_string(tickets_dir.join("open").join("c-1.md")).unwrap();
+    assert!(unchanged.contains("deps: []"));
+
+    // --force adds the edge anyway
+    dep(&["c-1", "nope", "--force"]).success().stdout(predicate::str::contains("Added dependency: c-1 -> nope"));
+    dep(&["c-1", "a-1", "--force"]).success();
+}
+
 #[test]
 fn test_reopen_command() {
     let temp_dir = TempDir::new().unwrap();
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // First create and close a ticket
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Test ticket for reopen")
@@ -375,7 +990,7 @@ fn test_reopen_command() {
         .unwrap();
 
     // Close the ticket first
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("close")
         .arg(ticket_id)
@@ -383,7 +998,7 @@ fn test_reopen_command() {
         .success();
 
     // Reopen the ticket
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("reopen")
         .arg(ticket_id)
@@ -392,7 +1007,10 @@ fn test_reopen_command() {
         .stdout(predicate::str::contains("Reopened"));
 
     // Verify status changed to open
-    let content = fs::read_to_string(ticket_path).unwrap();
+    let moved_path = tickets_dir.join("open").join(format!("{}.md", ticket_id));
+    assert!(!tickets_dir.join("closed").join(format!("{}.md", ticket_id)).exists());
+    assert_eq!(find_ticket_files(&tickets_dir).len(), 1);
+    let content = fs::read_to_string(moved_path).unwrap();
     assert!(content.contains("status: open"));
 }
 
@@ -402,7 +1020,7 @@ fn test_status_command() {
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // First create a ticket
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Test ticket for status")
@@ -420,7 +1038,7 @@ fn test_status_command() {
         .unwrap();
 
     // Set status to blocked
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("status")
         .arg(ticket_id)
@@ -430,7 +1048,10 @@ fn test_status_command() {
         .stdout(predicate::str::contains("Updated"));
 
     // Verify status changed to blocked
-    let content = fs::read_to_string(ticket_path).unwrap();
+    let moved_path = tickets_dir.join("blocked").join(format!("{}.md", ticket_id));
+    assert!(!ticket_path.exists(), "old status file should be moved");
+    assert_eq!(find_ticket_files(&tickets_dir).len(), 1);
+    let content = fs::read_to_string(moved_path).unwrap();
     assert!(content.contains("status: blocked"));
 }
 
@@ -440,7 +1061,7 @@ fn test_show_command() {
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // Create a ticket with full details
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Show test ticket")
@@ -460,7 +1081,7 @@ fn test_show_command() {
         .unwrap();
 
     // Show the ticket
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("show")
         .arg(ticket_id)
@@ -477,7 +1098,7 @@ fn test_dep_tree_command() {
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // Create parent ticket
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Parent ticket")
@@ -485,7 +1106,7 @@ fn test_dep_tree_command() {
         .success();
 
     // Create child ticket
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Child ticket")
@@ -493,26 +1114,17 @@ fn test_dep_tree_command() {
         .success();
 
     // Get ticket IDs
-    let ticket_files: Vec<_> = fs::read_dir(&tickets_dir)
-        .unwrap()
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| {
-            entry.path()
-                .extension()
-                .map(|ext| ext == "md")
-                .unwrap_or(false)
-        })
-        .collect();
+    let ticket_files = find_ticket_files(&tickets_dir);
 
     assert_eq!(ticket_files.len(), 2);
 
-    let parent_path = ticket_files[0].path();
+    let parent_path = ticket_files[0].as_path();
     let parent_id = parent_path
         .file_stem()
         .unwrap()
         .to_str()
         .unwrap();
-    let child_path = ticket_files[1].path();
+    let child_path = ticket_files[1].as_path();
     let child_id = child_path
         .file_stem()
         .unwrap()
@@ -520,7 +1132,7 @@ fn test_dep_tree_command() {
         .unwrap();
 
     // Add dependency
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("dep")
         .arg(child_id)
@@ -529,12 +1141,93 @@ fn test_dep_tree_command() {
         .success();
 
     // Show dependency tree
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("dep-tree")
         .assert()
         .success()
         .stdout(predicate::str::contains("Dependency tree"));
+
+    // A single ticket's tree shows its dependencies with status and priority
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .arg("dep-tree")
+        .arg(child_id)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("Dependency tree for {}", child_id)))
+        .stdout(predicate::str::contains(format!("└── {} [open P2]", parent_id)));
+}
+
+#[test]
+fn test_dep_tree_formats() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    fs::create_dir_all(tickets_dir.join("open")).unwrap();
+    fs::create_dir_all(tickets_dir.join("closed")).unwrap();
+
+    let ticket = |id: &str, status: &str, deps: &str| format!(
+        "---\nid: {}\ntitle: Ticket {}\nstatus: {}\ndeps: {}\nlinks: []\ncreated: 2023-01-01T00:00:00Z\ntype: task\npriority: 1\n---\n# Ticket {}\n",
+        id, id, status, deps, id
+    );
+    fs::write(tickets_dir.join("open").join("app-1.md"), ticket("app-1", "open", "[lib-1, old-1]")).unwrap();
+    fs::write(tickets_dir.join("open").join("lib-1.md"), ticket("lib-1", "open", "[]")).unwrap();
+    fs::write(tickets_dir.join("closed").join("old-1.md"), ticket("old-1", "closed", "[]")).unwrap();
+    fs::write(tickets_dir.join("open").join("ui-1.md"), ticket("ui-1", "open", "[app-1]")).unwrap();
+
+    // Closed dependencies and dependents are hidden by default
+    let output = cargo_bin_cmd!("tkr")
+        .env("TICKETS_DIR", &tickets_dir)
+        .args(["dep-tree", "app-1", "--format", "json"])
+        .output()
+        .unwrap();
+    let tree: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(tree["id"], "app-1");
+    assert_eq!(tree["deps"].as_array().unwrap().len(), 1);
+    assert_eq!(tree["deps"][0]["id"], "lib-1");
+    assert!(tree.get("dependents").is_none());
+
+    let output = cargo_bin_cmd!("tkr")
+        .env("TICKETS_DIR", &tickets_dir)
+        .args(["dep-tree", "app-1", "--full", "--format", "json"])
+        .output()
+        .unwrap();
+    let tree: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(tree["deps"].as_array().unwrap().len(), 2);
+    assert_eq!(tree["dependents"][0]["id"], "ui-1");
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .args(["dep-tree", "app-1", "--full", "--format", "mermaid"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("graph TD"))
+        .stdout(predicate::str::contains("    n0[\"app-1: Ticket app-1<br/>open · P1\"]"))
+        .stdout(predicate::str::contains("n0 --> n1"))
+        .stdout(predicate::str::contains("n3 --> n0"));
+
+    // IDs that would sanitize alike stay apart, and labels can't break out
+    let tricky = "---\nid: x-1\ntitle: 'Pipe | \"quotes\" [x] #1 <b>'\nstatus: open\ndeps: [x_1]\nlinks: []\ncreated: 2023-01-01T00:00:00Z\ntype: task\npriority: 1\n---\n";
+    fs::write(tickets_dir.join("open").join("x-1.md"), tricky).unwrap();
+    fs::write(tickets_dir.join("open").join("x_1.md"), ticket("x_1", "open", "[]")).unwrap();
+    let output = cargo_bin_cmd!("tkr")
+        .env("TICKETS_DIR", &tickets_dir)
+        .args(["dep-tree", "x-1", "--format", "mermaid"])
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "graph TD\n    n0[\"x-1: Pipe #124; #quot;quotes#quot; #91;x#93; #35;1 #lt;b#gt;<br/>open · P1\"]\n    n1[\"x_1: Ticket x_1<br/>open · P1\"]\n    n0 --> n1\n"
+    );
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .args(["dep-tree", "app-1", "--format", "dot"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("digraph deps {"))
+        .stdout(predicate::str::contains("\"app-1\" -> \"lib-1\";"))
+        .stdout(predicate::str::contains("old-1").not());
 }
 
 #[test]
@@ -543,7 +1236,7 @@ fn test_link_unlink_commands() {
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // Create first ticket
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("First ticket")
@@ -551,7 +1244,7 @@ fn test_link_unlink_commands() {
         .success();
 
     // Create second ticket
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Second ticket")
@@ -559,26 +1252,17 @@ fn test_link_unlink_commands() {
         .success();
 
     // Get ticket IDs
-    let ticket_files: Vec<_> = fs::read_dir(&tickets_dir)
-        .unwrap()
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| {
-            entry.path()
-                .extension()
-                .map(|ext| ext == "md")
-                .unwrap_or(false)
-        })
-        .collect();
+    let ticket_files = find_ticket_files(&tickets_dir);
 
     assert_eq!(ticket_files.len(), 2);
 
-    let first_path = ticket_files[0].path();
+    let first_path = ticket_files[0].as_path();
     let first_id = first_path
         .file_stem()
         .unwrap()
         .to_str()
         .unwrap();
-    let second_path = ticket_files[1].path();
+    let second_path = ticket_files[1].as_path();
     let second_id = second_path
         .file_stem()
         .unwrap()
@@ -586,7 +1270,7 @@ fn test_link_unlink_commands() {
         .unwrap();
 
     // Link tickets
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("link")
         .arg(first_id)
@@ -596,7 +1280,7 @@ fn test_link_unlink_commands() {
         .stdout(predicate::str::contains("Linked"));
 
     // Unlink tickets
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("unlink")
         .arg(first_id)
@@ -606,13 +1290,68 @@ fn test_link_unlink_commands() {
         .stdout(predicate::str::contains("Unlinked"));
 }
 
+#[test]
+fn test_typed_links_are_symmetric() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    fs::create_dir_all(tickets_dir.join("open")).unwrap();
+
+    let ticket = |id: &str, links: &str| format!(
+        "---\nid: {}\ntitle: Ticket {}\nstatus: open\ndeps: []\nlinks: {}\ncreated: 2023-01-01T00:00:00Z\ntype: task\npriority: 2\n---\n# Ticket {}\n",
+        id, id, links, id
+    );
+    // Older files store links as plain IDs
+    fs::write(tickets_dir.join("open").join("a-1.md"), ticket("a-1", "[c-1]")).unwrap();
+    fs::write(tickets_dir.join("open").join("b-1.md"), ticket("b-1", "[]")).unwrap();
+    fs::write(tickets_dir.join("open").join("c-1.md"), ticket("c-1", "[a-1]")).unwrap();
+    let read = |id: &str| fs::read_to_string(tickets_dir.join("open").join(format!("{}.md", id))).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .args(["link", "a-1", "b-1", "--type", "duplicates"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Linked a-1 duplicates b-1"));
+
+    assert!(read("a-1").contains("- c-1\n- id: b-1\n  type: duplicates"));
+    assert!(read("b-1").contains("- id: a-1\n  type: duplicated-by"));
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .args(["show", "b-1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Links:"))
+        .stdout(predicate::str::contains("duplicated-by  a-1 - Ticket a-1 (open)"));
+
+    // Unlinking from either side removes both halves
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .args(["unlink", "b-1", "a-1"])
+        .assert()
+        .success();
+    assert!(!read("a-1").contains("b-1"));
+    assert!(!read("b-1").contains("a-1"));
+    assert!(read("a-1").contains("c-1"));
+
+    // A hand-edited one-sided link is reported and repaired by doctor
+    fs::write(tickets_dir.join("open").join("b-1.md"), ticket("b-1", "[{id: c-1, type: supersedes}]")).unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .args(["doctor", "--fix"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[one_sided_link] b-1 links to c-1 as supersedes"));
+    assert!(read("c-1").contains("- id: b-1\n  type: superseded-by"));
+}
+
 #[test]
 fn test_ready_command() {
     let temp_dir = TempDir::new().unwrap();
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // Create a ticket with no dependencies (should be ready)
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Ready ticket")
@@ -620,7 +1359,7 @@ fn test_ready_command() {
         .success();
 
     // Create a ticket with dependencies (should not be ready)
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Blocked ticket")
@@ -645,7 +1384,7 @@ fn test_ready_command() {
         .unwrap();
 
     // Add dependency to make second ticket blocked
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("dep")
         .arg(blocked_id)
@@ -654,10 +1393,982 @@ fn test_ready_command() {
         .success();
 
     // Test ready command - should only show the first ticket
-    let mut cmd = Command::cargo_bin("tkr").unwrap();
+    let mut cmd = cargo_bin_cmd!("tkr");
     cmd.env("TICKETS_DIR", &tickets_dir)
         .arg("ready")
         .assert()
         .success()
         .stdout(predicate::str::contains(ready_id));
 }
+
+#[test]
+fn test_query_language() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    fs::create_dir_all(tickets_dir.join("open")).unwrap();
+    fs::create_dir_all(tickets_dir.join("closed")).unwrap();
+
+    let created_today = chrono::Utc::now().to_rfc3339();
+    let ticket = |id: &str, title: &str, status: &str, priority: i32, created: &str, extra: &str| format!(
+        "---\nid: {}\ntitle: {}\nstatus: {}\ndeps: []\nlinks: []\ncreated: {}\ntype: task\npriority: {}\n{}---\n# {}\n",
+        id, title, status, created, priority, extra, title
+    );
+    fs::write(tickets_dir.join("open").join("q-1.md"),
+        ticket("q-1", "Fix auth timeout", "open", 1, &created_today, "assignee: alice\nteam: api\n")).unwrap();
+    fs::write(tickets_dir.join("open").join("q-2.md"),
+        ticket("q-2", "Write docs", "open", 3, "2020-01-01T00:00:00Z", "assignee: bob\n")).unwrap();
+    fs::write(tickets_dir.join("closed").join("q-3.md"),
+        ticket("q-3", "Old auth work", "closed", 0, "2020-01-01T00:00:00Z", "team: web\n")).unwrap();
+
+    let list = |query: &str| {
+        let output = cargo_bin_cmd!("tkr")
+            .env("TICKETS_DIR", &tickets_dir)
+            .env("TKR_USER", "alice")
+            .args(["list", "--format", "template", "--template", "{id}", "--sort", "id", "--", query])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(list("status:open priority<=1 assignee:me"), ["q-1"]);
+    assert_eq!(list("auth"), ["q-1", "q-3"]);
+    assert_eq!(list("text~\"auth timeout\""), ["q-1"]);
+    assert_eq!(list("team:api OR priority>2"), ["q-1", "q-2"]);
+    assert_eq!(list("-(status:closed OR assignee:bob)"), ["q-1"]);
+    assert_eq!(list("created>-14d"), ["q-1"]);
+    assert_eq!(list("has:team NOT status:open"), ["q-3"]);
+    assert_eq!(list("status:open,closed priority!=3"), ["q-1", "q-3"]);
+
+    // Flags and the query combine
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .args(["ls", "--status", "open", "auth"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("q-1"))
+        .stdout(predicate::str::contains("q-3").not());
+
+    // `query --where` prints the matching tickets as JSON
+    let output = cargo_bin_cmd!("tkr")
+        .env("TICKETS_DIR", &tickets_dir)
+        .args(["query", "--where", "team:web"])
+        .output()
+        .unwrap();
+    let tickets: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(tickets.as_array().unwrap().len(), 1);
+    assert_eq!(tickets[0]["id"], "q-3");
+
+    // Syntax errors point at the offending column
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .args(["list", "status:open (priority<2"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid query: unclosed '(' at column 13"))
+        .stderr(predicate::str::contains("  status:open (priority<2\n              ^"));
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .args(["list", "deps>2"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("'deps' cannot be compared with '>'"));
+}
+
+#[test]
+fn test_query_rejects_non_ascii_dates() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+
+    // Offsets too large for the calendar are rejected the same way
+    for query in ["created:é", "created>-1é", "updated<日", "closed>=-é", "created>-99999999999d", "created>-9999999999999999d", "updated<9999999999999999999y"] {
+        let mut cmd = cargo_bin_cmd!("tkr");
+        cmd.env("TICKETS_DIR", &tickets_dir)
+            .args(["list", query])
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("expected a date like 2024-01-31"));
+    }
+}
+
+#[test]
+fn test_query_jq_filters() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    fs::create_dir_all(tickets_dir.join("open")).unwrap();
+    fs::create_dir_all(tickets_dir.join("closed")).unwrap();
+
+    let ticket = |id: &str, title: &str, status: &str, priority: i32| format!(
+        "---\nid: {}\ntitle: {}\nstatus: {}\ndeps: []\nlinks: []\ncreated: 2023-01-01T00:00:00Z\ntype: task\npriority: {}\n---\n# {}\n",
+        id, title, status, priority, title
+    );
+    fs::write(tickets_dir.join("open").join("j-1.md"), ticket("j-1", "Login", "open", 1)).unwrap();
+    fs::write(tickets_dir.join("open").join("j-2.md"), ticket("j-2", "Docs", "open", 3)).unwrap();
+    fs::write(tickets_dir.join("closed").join("j-3.md"), ticket("j-3", "Old", "closed", 1)).unwrap();
+
+    let query = |args: &[&str]| {
+        let output = cargo_bin_cmd!("tkr")
+            .env("TICKETS_DIR", &tickets_dir)
+            .arg("query")
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // The default filter is `.`: every ticket as a JSON array
+    let all: serde_json::Value = serde_json::from_str(&query(&[])).unwrap();
+    assert_eq!(all.as_array().unwrap().len(), 3);
+
+    assert_eq!(query(&["length"]), "3\n");
+    assert_eq!(query(&["-c", "map(select(.priority <= 1)) | map(.id) | sort"]), "[\"j-1\",\"j-3\"]\n");
+    assert_eq!(
+        query(&["-c", "group_by(.status) | map({status: .[0].status, count: length})"]),
+        "[{\"count\":1,\"status\":\"closed\"},{\"count\":2,\"status\":\"open\"}]\n"
+    );
+    assert_eq!(query(&["-r", "sort_by(.id) | .[] | \"\\(.id) \\(.title)\""]), "j-1 Login\nj-2 Docs\nj-3 Old\n");
+    assert_eq!(query(&["-r", "--where", "status:open", "sort_by(.id) | .[] | [.id, .priority] | @csv"]), "\"j-1\",1\n\"j-2\",3\n");
+
+    // Syntax errors point at the column; runtime errors name the problem
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .args(["query", "map(.id"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("expected ')', found end of filter at column 8"));
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .args(["query", ".[0].title.first"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Cannot index string with \"first\""));
+}
+
+#[test]
+fn test_list_output_formats() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    fs::create_dir_all(tickets_dir.join("open")).unwrap();
+
+    let ticket = |id: &str, title: &str, priority: i32, created: &str| format!(
+        "---\nid: {}\ntitle: {}\nstatus: open\ndeps: []\nlinks: []\ncreated: {}\ntype: task\npriority: {}\n---\n# {}\n",
+        id, title, created, priority, title
+    );
+    fs::write(tickets_dir.join("open").join("f-1.md"), ticket("f-1", "Second, newest", 2, "2023-03-01T00:00:00Z")).unwrap();
+    fs::write(tickets_dir.join("open").join("f-2.md"), ticket("f-2", "Most urgent", 0, "2023-02-01T00:00:00Z")).unwrap();
+    fs::write(tickets_dir.join("open").join("f-3.md"), ticket("f-3", "Oldest", 3, "2023-01-01T00:00:00Z")).unwrap();
+
+    let list = |args: &[&str]| {
+        let output = cargo_bin_cmd!("tkr")
+            .env("TICKETS_DIR", &tickets_dir)
+            .arg("list")
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // The table is aligned and uncolored when piped
+    let table = list(&["--sort", "priority"]);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines[0], "ID   STATUS  PRIORITY  TYPE  TITLE");
+    assert_eq!(lines[1], "f-2  open    0         task  Most urgent");
+    assert_eq!(lines.len(), 4);
+
+    assert_eq!(
+        list(&["--format", "csv", "--fields", "id,title", "--sort", "created"]),
+        "id,title\nf-3,Oldest\nf-2,Most urgent\nf-1,\"Second, newest\"\n"
+    );
+    assert_eq!(
+        list(&["--format", "tsv", "--fields", "id,priority", "--sort", "priority", "--reverse", "--limit", "2"]),
+        "id\tpriority\nf-3\t3\nf-1\t2\n"
+    );
+    assert_eq!(
+        list(&["--format", "ndjson", "--fields", "id,priority", "--sort", "id", "--limit", "1"]),
+        "{\"id\":\"f-1\",\"priority\":2}\n"
+    );
+    assert_eq!(
+        list(&["--format", "template", "--template", "{id}: {title} ({priority})", "--sort", "id", "--limit", "1"]),
+        "f-1: Second, newest (2)\n"
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&list(&["--format", "json"])).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), 3);
+    assert_eq!(json[0]["type"], "task");
+
+    let yaml = list(&["--format", "yaml", "--fields", "id", "--sort", "id"]);
+    assert_eq!(yaml, "- id: f-1\n- id: f-2\n- id: f-3\n");
+
+    // Other listing commands share the options
+    let output = cargo_bin_cmd!("tkr")
+        .env("TICKETS_DIR", &tickets_dir)
+        .args(["ready", "--format", "csv", "--fields", "id", "--sort", "id"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "id\nf-1\nf-2\nf-3\n");
+}
+
+#[test]
+fn test_output_options_before_the_command() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    fs::create_dir_all(tickets_dir.join("open")).unwrap();
+
+    let ticket = |id: &str, title: &str, priority: i32| format!(
+        "---\nid: {}\ntitle: {}\nstatus: open\ndeps: []\nlinks: []\ncreated: 2023-01-01T00:00:00Z\ntype: task\npriority: {}\n---\n# {}\n",
+        id, title, priority, title
+    );
+    fs::write(tickets_dir.join("open").join("f-1.md"), ticket("f-1", "Parser crash", 2)).unwrap();
+    fs::write(tickets_dir.join("open").join("f-2.md"), ticket("f-2", "Parser docs", 0)).unwrap();
+
+    let run = |args: &[&str]| {
+        let output = cargo_bin_cmd!("tkr")
+            .env("TICKETS_DIR", &tickets_dir)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let json: serde_json::Value = serde_json::from_str(&run(&["--format", "json", "ready"])).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), 2);
+    assert_eq!(run(&["--format", "csv", "--fields", "id", "--sort", "priority", "list"]), "id\nf-2\nf-1\n");
+
+    // Options after the command win, and fill in the rest
+    assert_eq!(run(&["--format", "json", "--sort", "id", "ready", "--format", "template", "--template", "{id}"]), "f-1\nf-2\n");
+
+    // show, query and search print tickets the same way
+    assert_eq!(run(&["--format", "template", "--template", "{id}: {title}", "show", "f-1"]), "f-1: Parser crash\n");
+    assert_eq!(run(&["show", "f-2", "--format", "ndjson", "--fields", "id,priority"]), "{\"id\":\"f-2\",\"priority\":0}\n");
+    assert_eq!(
+        run(&["--format", "tsv", "--fields", "id", "query", ".[] | select(.priority < 1)"]),
+        "id\nf-2\n"
+    );
+    assert_eq!(run(&["query", "map(select(.id == \"f-1\"))", "--format", "template", "--template", "{title}"]), "Parser crash\n");
+    assert_eq!(run(&["search", "crash", "--format", "template", "--template", "{id}"]), "f-1\n");
+    assert_eq!(run(&["--format", "csv", "--fields", "id", "--sort", "id", "search", "parser"]), "id\nf-1\nf-2\n");
+
+    // Without --format, query still prints whatever the filter produces
+    assert_eq!(run(&["query", "-r", ".[0].status"]), "open\n");
+    cargo_bin_cmd!("tkr")
+        .env("TICKETS_DIR", &tickets_dir)
+        .args(["--format", "json", "query", "map(.title)"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--format needs the filter to produce tickets"));
+    cargo_bin_cmd!("tkr")
+        .env("TICKETS_DIR", &tickets_dir)
+        .args(["--format", "template", "ready"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("needs a --template"));
+}
+
+#[test]
+fn test_index_tracks_edits_and_is_disposable() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    fs::create_dir_all(tickets_dir.join("open")).unwrap();
+
+    let ticket = |title: &str| format!(
+        "---\nid: x-1\ntitle: {}\nstatus: open\ndeps: []\nlinks: []\ncreated: 2023-01-01T00:00:00Z\ntype: task\npriority: 2\n---\n# {}\n",
+        title, title
+    );
+    let path = tickets_dir.join("open").join("x-1.md");
+    fs::write(&path, ticket("Before")).unwrap();
+
+    let titles = || {
+        let output = cargo_bin_cmd!("tkr")
+            .env("TICKETS_DIR", &tickets_dir)
+            .args(["list", "--format", "template", "--template", "{title}"])
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert_eq!(titles(), "Before\n");
+    let index_path = tickets_dir.join(".index");
+    assert!(index_path.exists());
+
+    // An edit right after indexing, even with the same size, is picked up
+    fs::write(&path, ticket("After!")).unwrap();
+    assert_eq!(titles(), "After!\n");
+
+    // A corrupt or deleted index is rebuilt
+    fs::write(&index_path, "not json").unwrap();
+    assert_eq!(titles(), "After!\n");
+    fs::remove_file(&index_path).unwrap();
+    assert_eq!(titles(), "After!\n");
+    assert!(index_path.exists());
+
+    // Deleted tickets drop out
+    fs::remove_file(&path).unwrap();
+    assert_eq!(titles(), "");
+}
+
+#[test]
+fn test_search_ranks_and_highlights() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    fs::create_dir_all(tickets_dir.join("open")).unwrap();
+    fs::create_dir_all(tickets_dir.join("closed")).unwrap();
+
+    let ticket = |id: &str, title: &str, status: &str, body: &str| format!(
+        "---\nid: {}\ntitle: {}\nstatus: {}\ndeps: []\nlinks: []\ncreated: 2023-01-01T00:00:00Z\ntype: task\npriority: 2\ndescription: {}\n---\n# {}\n\n{}\n",
+        id, title, status, body, title, body
+    );
+    fs::write(tickets_dir.join("open").join("s-1.md"), ticket("s-1", "Fix login redirect", "open", "Users get a blank page after the SSO callback.")).unwrap();
+    fs::write(tickets_dir.join("open").join("s-2.md"), ticket("s-2", "Cache parsed tickets", "open", "Listing is slow; the login page waits on it.")).unwrap();
+    fs::write(tickets_dir.join("closed").join("s-3.md"), ticket("s-3", "Web server timeouts", "closed", "Requests hang while caching.")).unwrap();
+
+    let search = |args: &[&str]| {
+        let output = cargo_bin_cmd!("tkr")
+            .env("TICKETS_DIR", &tickets_dir)
+            .arg("search")
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let ids = |args: &[&str]| -> Vec<String> {
+        let hits: serde_json::Value = serde_json::from_str(&search(&[args, &["--json"]].concat())).unwrap();
+        hits.as_array().unwrap().iter().map(|hit| hit["id"].as_str().unwrap().to_string()).collect()
+    };
+
+    // A title match outranks a description match
+    assert_eq!(ids(&["login"]), ["s-1", "s-2"]);
+    // Stemming, typos and field scoping
+    assert_eq!(ids(&["cached"]), ["s-2", "s-3"]);
+    assert_eq!(ids(&["redirct"]), ["s-1"]);
+    assert_eq!(ids(&["title:login"]), ["s-1"]);
+    assert_eq!(ids(&["\"blank page\""]), ["s-1"]);
+    assert!(ids(&["\"page blank\""]).is_empty());
+    assert_eq!(ids(&["--where", "status:closed", "cache"]), ["s-3"]);
+
+    let output = search(&["blank"]);
+    assert!(output.contains("s-1  Fix login redirect (open)"), "{}", output);
+    assert!(output.contains("description: Users get a **blank** page after the SSO callback."), "{}", output);
+    assert_eq!(search(&["nothing-like-this"]), "No matching tickets found\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_edit_validates_and_moves() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    fs::create_dir_all(tickets_dir.join("open")).unwrap();
+    fs::write(
+        tickets_dir.join("open").join("e-1.md"),
+        "---\nid: e-1\ntitle: Edit me\nstatus: open\ndeps: []\nlinks: []\ncreated: 2023-01-01T00:00:00Z\ntype: task\npriority: 2\n---\n# Edit me\n\nHand-written text.\n",
+    ).unwrap();
+
+    // The first run breaks the YAML; the second sees the error and fixes it
+    let editor = temp_dir.path().join("editor.sh");
+    let seen = temp_dir.path().join("seen");
+    fs::write(&editor, format!(
+        "#!/bin/sh\nif [ -f '{seen}' ]; then\n  sed -i 's/^priority: \\[$/priority: 0/; s/^status: open/status: in_progress/' \"$1\"\nelse\n  sed -i 's/^priority: 2/priority: [/' \"$1\"\nfi\ncat \"$1\" >> '{seen}'\n",
+        seen = seen.display()
+    )).unwrap();
+    fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .env("EDITOR", &editor)
+        .env_remove("VISUAL")
+        .args(["edit", "e-1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Updated e-1 -> in_progress"));
+
+    let seen = fs::read_to_string(&seen).unwrap();
+    assert!(seen.contains("# This ticket was not saved:\n#   Failed to parse YAML"), "{}", seen);
+    assert!(!tickets_dir.join("open").join("e-1.md").exists());
+    let content = fs::read_to_string(tickets_dir.join("in_progress").join("e-1.md")).unwrap();
+    assert!(content.contains("priority: 0") && content.contains("Hand-written text."), "{}", content);
+    assert!(!content.contains("not saved"), "{}", content);
+
+    // The edit is one change in the history, status included
+    let output = cargo_bin_cmd!("tkr")
+        .env("TICKETS_DIR", &tickets_dir)
+        .args(["history", "e-1", "--json"])
+        .output()
+        .unwrap();
+    let events: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let events = events.as_array().unwrap();
+    assert_eq!(events.len(), 1, "{:?}", events);
+    assert_eq!(events[0]["changes"]["status"]["from"], "open");
+    assert_eq!(events[0]["changes"]["priority"]["to"], 0);
+
+    // Invalid statuses are never saved; emptying the file cancels
+    let editor = temp_dir.path().join("cancel.sh");
+    fs::write(&editor, "#!/bin/sh\nif grep -q 'not saved' \"$1\"; then : > \"$1\"; else sed -i 's/^status: .*/status: bogus/' \"$1\"; fi\n").unwrap();
+    fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .env("EDITOR", &editor)
+        .env_remove("VISUAL")
+        .args(["edit", "e-1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Edit cancelled"));
+    assert_eq!(fs::read_to_string(tickets_dir.join("in_progress").join("e-1.md")).unwrap(), content);
+
+    // A failing editor changes nothing
+    let mut cmd = cargo_bin_cmd!("tkr");
+    cmd.env("TICKETS_DIR", &tickets_dir)
+        .env("EDITOR", "false")
+        .env_remove("VISUAL")
+        .args(["edit", "e-1"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("ticket not changed"));
+}
+
+#[test]
+fn test_blocked_and_closed_commands() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    for status in ["open", "blocked", "closed"] {
+        fs::create_dir_all(tickets_dir.join(status)).unwrap();
+    }
+
+    let ticket = |id: &str, status: &str, deps: &str, extra: &str| format!(
+        "---\nid: {}\ntitle: Ticket {}\nstatus: {}\ndeps: {}\nlinks: []\ncreated: 2023-01-01T00:00:00Z\n{}type: task\npriority: 2\n---\n# Ticket {}\n",
+        id, id, status, deps, extra, id
+    );
+    let write = |status: &str, id: &str, content: String| {
+        fs::write(tickets_dir.join(status).join(format!("{}.md", id)), content).unwrap();
+    };
+    write("open", "b-1", ticket("b-1", "open", "[]", ""));
+    write("open", "b-2", ticket("b-2", "open", "[b-1, b-9]", ""));
+    write("blocked", "b-3", ticket("b-3", "blocked", "[]", ""));
+    write("closed", "b-4", ticket("b-4", "closed", "[]", "closed_at: 2024-03-01T12:00:00Z\n"));
+    write("closed", "b-5", ticket("b-5", "closed", "[]", "closed_at: 2024-05-01T12:00:00Z\n"));
+    write("open", "b-6", ticket("b-6", "open", "[b-5]", ""));
+
+    let run = |args: &[&str]| {
+        let output = cargo_bin_cmd!("tkr")
+            .env("TICKETS_DIR", &tickets_dir)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // Explicitly blocked, or held up by open and missing deps; b-6's dep is closed
+    assert_eq!(
+        run(&["blocked", "--format", "template", "--template", "{id}: {blocked_by}", "--sort", "id"]),
+        "b-2: b-1,b-9\nb-3: \n"
+    );
+
+    // Most recently closed first
+    assert_eq!(run(&["closed", "--format", "template", "--template", "{id}"]), "b-5\nb-4\n");
+    assert_eq!(run(&["closed", "--since", "2024-04-01", "--format", "template", "--template", "{id}"]), "b-5\n");
+    assert_eq!(run(&["closed", "--limit", "1", "--format", "template", "--template", "{id}"]), "b-5\n");
+
+    // Closing records closed_at; reopening clears it
+    run(&["close", "b-1"]);
+    let content = fs::read_to_string(tickets_dir.join("closed").join("b-1.md")).unwrap();
+    assert!(content.contains("closed_at: "), "{}", content);
+    assert_eq!(run(&["closed", "--since", "today", "--format", "template", "--template", "{id}"]), "b-1\n");
+    assert_eq!(run(&["list", "--format", "template", "--template", "{id}", "--", "closed>=today"]), "b-1\n");
+
+    run(&["reopen", "b-1"]);
+    let content = fs::read_to_string(tickets_dir.join("open").join("b-1.md")).unwrap();
+    assert!(!content.contains("closed_at"), "{}", content);
+}
+
+#[test]
+fn test_closed_since_rejects_non_ascii() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+
+    for since in ["é", "7é", "日d"] {
+        let mut cmd = cargo_bin_cmd!("tkr");
+        cmd.env("TICKETS_DIR", &tickets_dir)
+            .args(["closed", "--since", since])
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains(format!("Invalid --since '{}'", since)));
+    }
+}
+
+#[test]
+fn test_closed_since_rejects_huge_offsets() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+
+    for since in ["99999999999999d", "9999999999999999999w", "999999999y"] {
+        let mut cmd = cargo_bin_cmd!("tkr");
+        cmd.env("TICKETS_DIR", &tickets_dir)
+            .args(["closed", "--since", since])
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains(format!("Invalid --since '{}'", since)))
+            .stderr(predicate::str::contains("panicked").not());
+    }
+}
+
+#[test]
+fn test_lifecycle_timestamps_and_actors() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+
+    let run = |user: &str, args: &[&str]| {
+        let output = cargo_bin_cmd!("tkr")
+            .env("TICKETS_DIR", &tickets_dir)
+            .env("TKR_USER", user)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let ticket = || -> serde_json::Value {
+        serde_json::from_str(&run("alice", &["query", ".[0]"])).unwrap()
+    };
+
+    let id = run("alice", &["create", "Lifecycle"]).trim().to_string();
+    let created = ticket();
+    assert_eq!(created["created_by"], "alice");
+    assert_eq!(created["status_log"][0]["status"], "open");
+    assert!(created["started_at"].is_null() && created["closed_at"].is_null());
+
+    run("alice", &["start", &id]);
+    run("bob", &["close", &id]);
+    let closed = ticket();
+    assert_eq!(closed["closed_by"], "bob");
+    assert!(closed["started_at"].is_string() && closed["closed_at"].is_string());
+    let log: Vec<(&str, &str)> = closed["status_log"].as_array().unwrap().iter()
+        .map(|entry| (entry["status"].as_str().unwrap(), entry["by"].as_str().unwrap()))
+        .collect();
+    assert_eq!(log, [("open", "alice"), ("in_progress", "alice"), ("closed", "bob")]);
+
+    // Notes bump `updated`; reopening keeps the start but clears the close
+    let before = closed["updated"].as_str().unwrap().to_string();
+    run("carol", &["add-note", &id, "More to do"]);
+    run("carol", &["reopen", &id]);
+    let reopened = ticket();
+    assert!(reopened["updated"].as_str().unwrap() > before.as_str());
+    assert_eq!(reopened["started_at"], closed["started_at"]);
+    assert!(reopened["closed_at"].is_null() && reopened["closed_by"].is_null());
+    assert_eq!(reopened["status_log"].as_array().unwrap().len(), 4);
+}
+
+#[test]
+fn test_history_and_undo() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+
+    let tkr = |args: &[&str]| {
+        cargo_bin_cmd!("tkr")
+            .env("TICKETS_DIR", &tickets_dir)
+            .env("TKR_USER", "alice")
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    let run = |args: &[&str]| {
+        let output = tkr(args);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let ticket = |id: &str| -> serde_json::Value {
+        let filter = format!(".[] | select(.id == \"{}\")", id);
+        serde_json::from_str(&run(&["query", &filter])).unwrap()
+    };
+    let status = |id: &str| ticket(id)["status"].as_str().unwrap().to_string();
+
+    let parent = run(&["create", "Parent"]).trim().to_string();
+    let child = run(&["create", "Child"]).trim().to_string();
+    run(&["dep", &child, &parent]);
+    run(&["status", &child, "blocked"]);
+    run(&["close", &parent]);
+    assert_eq!(status(&child), "ready");
+
+    // Closing the parent unblocked the child in the same operation
+    let history = run(&["history", "--limit", "1"]);
+    assert!(history.contains("close by alice"), "{}", history);
+    assert!(history.contains(&format!("{}: status: open -> closed", parent)), "{}", history);
+    assert!(history.contains(&format!("{}: status: blocked -> ready", child)), "{}", history);
+    let events: serde_json::Value = serde_json::from_str(&run(&["history", &child, "--json"])).unwrap();
+    assert_eq!(events.as_array().unwrap().len(), 4);
+    assert_eq!(events[0]["kind"], "create");
+
+    // Undo reverts both tickets together
+    let undone = run(&["undo"]);
+    assert!(undone.contains("Undid 'close' by alice"), "{}", undone);
+    assert_eq!(status(&parent), "open");
+    assert_eq!(status(&child), "blocked");
+    assert_eq!(ticket(&child)["deps"][0], parent.as_str());
+
+    // Undoing a create deletes the ticket
+    let extra = run(&["create", "Extra"]).trim().to_string();
+    run(&["undo"]);
+    assert!(find_ticket_files(&tickets_dir).iter().all(|path| !path.ends_with(format!("{}.md", extra))));
+    assert!(run(&["history", &extra]).contains("deleted"));
+
+    // A hand edit since the change makes undo refuse instead of clobbering it
+    let path = find_ticket_files(&tickets_dir).into_iter()
+        .find(|path| path.ends_with(format!("{}.md", child)))
+        .unwrap();
+    let content = fs::read_to_string(&path).unwrap();
+    fs::write(&path, content.replace("status: blocked", "status: open")).unwrap();
+    let output = tkr(&["undo"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("has changed since (status)"));
+    assert_eq!(status(&child), "open");
+}
+
+#[test]
+fn test_git_auto_commit() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    let config_dir = root.join(".config").join("tkr");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(config_dir.join("config.yml"), "git:\n  auto_commit: true\n").unwrap();
+    fs::write(root.join(".gitignore"), ".tickets/.lock\n.tickets/.index\n.tickets/.pending\n").unwrap();
+
+    let git = |args: &[&str]| {
+        let output = std::process::Command::new("git")
+            .current_dir(root)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+    git(&["init", "-q"]);
+    git(&["config", "user.name", "Test"]);
+    git(&["config", "user.email", "test@example.com"]);
+    git(&["add", "-A"]);
+    git(&["commit", "-q", "-m", "init"]);
+
+    let run = |args: &[&str]| {
+        let output = cargo_bin_cmd!("tkr").current_dir(root).args(args).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let subjects = || git(&["log", "--format=%s"]);
+
+    let parent = run(&["create", "Parent"]).trim().to_string();
+    let child = run(&["create", "Child"]).trim().to_string();
+    run(&["dep", &child, &parent]);
+    run(&["status", &child, "blocked"]);
+    run(&["start", &parent]);
+    assert!(subjects().starts_with(&format!("tkr: {} start (open -> in_progress)\n", parent)));
+
+    // The move is committed as a rename, along with the ticket it unblocked
+    fs::write(root.join("unrelated.txt"), "staged by hand").unwrap();
+    git(&["add", "unrelated.txt"]);
+    run(&["close", &parent]);
+    let stat = git(&["show", "--name-status", "--format=%s%n%b", "HEAD"]);
+    assert!(stat.starts_with(&format!("tkr: {} close (in_progress -> closed)\n{}: blocked -> ready", parent, child)), "{}", stat);
+    assert!(stat.contains(&format!("in_progress/{}.md", parent)));
+    assert!(stat.contains(&format!("ready/{}.md", child)));
+    assert!(!stat.contains("unrelated.txt"));
+    assert_eq!(git(&["status", "--porcelain"]), "A  unrelated.txt\n");
+
+    // --no-commit queues changes until `tkr commit`
+    let before = subjects();
+    let batched = run(&["--no-commit", "create", "Batched"]).trim().to_string();
+    run(&["--no-commit", "start", &batched]);
+    assert_eq!(subjects(), before);
+    assert!(run(&["commit"]).contains("Committed: tkr: 2 changes"));
+    let message = git(&["log", "-1", "--format=%b"]);
+    assert!(message.contains(&format!("- {} start (open -> in_progress)", batched)), "{}", message);
+    assert_eq!(git(&["status", "--porcelain"]), "A  unrelated.txt\n");
+    assert!(run(&["commit"]).contains("Nothing to commit"));
+}
+
+#[test]
+fn test_web_api_endpoints() {
+    use serde_json::json;
+
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    let server = WebServer::start(&tickets_dir, &[]);
+
+    // Create
+    let (status, first) = server.json("POST", "/api/tickets", Some(json!({"title": "First", "priority": 1})));
+    assert_eq!(status, 201);
+    assert_eq!(first["priority"], 1);
+    assert_eq!(first["issue_type"], "task");
+    let first = first["id"].as_str().unwrap().to_string();
+    let (_, second) = server.json("POST", "/api/tickets", Some(json!({"title": "Second"})));
+    let second = second["id"].as_str().unwrap().to_string();
+
+    let (status, error) = server.json("POST", "/api/tickets", Some(json!({"priority": 1})));
+    assert_eq!(status, 400);
+    assert!(error["error"].as_str().unwrap().contains("title"));
+
+    // Read, including the list, which reflects the new tickets
+    let (status, ticket) = server.json("GET", &format!("/api/tickets/{}", first), None);
+    assert_eq!((status, ticket["title"].as_str()), (200, Some("First")));
+    let (status, error) = server.json("GET", "/api/tickets/missing", None);
+    assert_eq!(status, 404);
+    assert_eq!(error["error"], "Ticket 'missing' not found");
+    let (_, list) = server.json("GET", "/api/tickets", None);
+    assert_eq!(list.as_array().unwrap().len(), 2);
+
+    // Notes, deps and links
+    let (status, ticket) = server.json("POST", &format!("/api/tickets/{}/notes", first), Some(json!({"note": "Looked into it"})));
+    assert_eq!(status, 201);
+    assert_eq!(ticket["notes"][0]["content"], "Looked into it");
+
+    let (status, ticket) = server.json("POST", &format!("/api/tickets/{}/deps", second), Some(json!({"id": first})));
+    assert_eq!(status, 200);
+    assert_eq!(ticket["deps"], json!([first]));
+    let (status, error) = server.json("POST", &format!("/api/tickets/{}/deps", first), Some(json!({"id": second})));
+    assert_eq!(status, 400);
+    assert!(error["error"].as_str().unwrap().contains("cycle"));
+
+    let (_, ready) = server.json("GET", "/api/ready", None);
+    let ready: Vec<&str> = ready.as_array().unwrap().iter().map(|t| t["id"].as_str().unwrap()).collect();
+    assert_eq!(ready, [first.as_str()]);
+
+    let (status, ticket) = server.json("POST", &format!("/api/tickets/{}/links", first), Some(json!({"id": second, "type": "causes"})));
+    assert_eq!(status, 200);
+    assert_eq!(ticket["links"], json!([{"id": second, "type": "causes"}]));
+    let (_, ticket) = server.json("DELETE", &format!("/api/tickets/{}/links/{}", first, second), None);
+    assert_eq!(ticket["links"], json!([]));
+
+    // Status changes report workflow errors as JSON too
+    let any = [("If-Match", "*")];
+    let (status, _, error) = server.json_with("PUT", &format!("/api/tickets/{}", first), &any, Some(json!({"status": "bogus"})));
+    assert_eq!(status, 409);
+    assert!(error["error"].as_str().unwrap().contains("Invalid status"));
+    let (status, _, ticket) = server.json_with("PUT", &format!("/api/tickets/{}", first), &any, Some(json!({"status": "closed"})));
+    assert_eq!((status, ticket["status"].as_str()), (200, Some("closed")));
+
+    // Deleting drops the ticket from the deps that pointed at it
+    let (status, _) = server.json("DELETE", &format!("/api/tickets/{}", first), None);
+    assert_eq!(status, 200);
+    let (_, ticket) = server.json("GET", &format!("/api/tickets/{}", second), None);
+    assert_eq!(ticket["deps"], json!([]));
+    let (status, _) = server.json("DELETE", &format!("/api/tickets/{}", first), None);
+    assert_eq!(status, 404);
+
+    let (status, error) = server.json("GET", "/api/nothing", None);
+    assert_eq!((status, error["error"].as_str()), (404, Some("Not found")));
+}
+
+#[test]
+fn test_web_update_requires_current_etag() {
+    use serde_json::json;
+
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    let server = WebServer::start(&tickets_dir, &[]);
+    let (_, ticket) = server.json("POST", "/api/tickets", Some(json!({"title": "Shared"})));
+    let id = ticket["id"].as_str().unwrap().to_string();
+    let path = format!("/api/tickets/{}", id);
+
+    // The revision comes back in the body and as the ETag
+    let (status, head, ticket) = server.json_with("GET", &path, &[], None);
+    assert_eq!(status, 200);
+    let revision = ticket["revision"].as_str().unwrap().to_string();
+    let etag = format!("\"{}\"", revision);
+    assert!(head.to_lowercase().contains(&format!("etag: {}", etag)), "{}", head);
+
+    let (status, _) = server.json("PUT", &path, Some(json!({"title": "Mine"})));
+    assert_eq!(status, 428);
+
+    // Someone else changes it with the CLI
+    cargo_bin_cmd!("tkr")
+        .env("TICKETS_DIR", &tickets_dir)
+        .args(["add-note", &id, "Theirs"])
+        .assert()
+        .success();
+
+    let (status, _, stale) = server.json_with("PATCH", &path, &[("If-Match", &etag)], Some(json!({"title": "Mine"})));
+    assert_eq!(status, 412);
+    assert_eq!(stale["ticket"]["title"], "Shared");
+    assert_eq!(stale["ticket"]["notes"][0]["content"], "Theirs");
+    let current = stale["ticket"]["revision"].as_str().unwrap().to_string();
+    assert_ne!(current, revision);
+
+    // Retrying against the current revision goes through
+    let (status, head, ticket) = server.json_with("PATCH", &path, &[("If-Match", &format!("\"{}\"", current))], Some(json!({"title": "Mine"})));
+    assert_eq!((status, ticket["title"].as_str()), (200, Some("Mine")));
+    assert_eq!(ticket["notes"][0]["content"], "Theirs");
+    assert!(head.to_lowercase().contains(&format!("etag: \"{}\"", ticket["revision"].as_str().unwrap())));
+}
+
+#[test]
+fn test_web_auth_read_only_and_cors() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    let config_dir = temp_dir.path().join(".config").join("tkr");
+    fs::create_dir_all(&config_dir).unwrap();
+
+    // A config that doesn't parse stops the server rather than dropping auth
+    fs::write(config_dir.join("config.yml"), "auth:\n  token_sha256: [oops\n").unwrap();
+    cargo_bin_cmd!("tkr")
+        .current_dir(temp_dir.path())
+        .env("TICKETS_DIR", &tickets_dir)
+        .args(["web", "--port", "0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid config"))
+        .stderr(predicate::str::contains("config.yml"))
+        .stdout(predicate::str::contains("Starting").not());
+
+    // Passwords need a salted hash; a bare digest is only accepted for the token
+    let write_config = |alice: &str| fs::write(config_dir.join("config.yml"), format!(r#"auth:
+  token_sha256: 1ec1c26b50d5d3c58d9583181af8076655fe00756bf7285940ba3670f99fcba0
+  users:
+    alice: '{}'
+cors_origins:
+  - https://board.example.com
+"#, alice)).unwrap();
+    write_config("30c952fab122c3f9759f02a6d95c3758b246b4fee239957b2d4fee46e26170c4");
+    cargo_bin_cmd!("tkr")
+        .current_dir(temp_dir.path())
+        .env("TICKETS_DIR", &tickets_dir)
+        .args(["web", "--port", "0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("auth.users.alice must be an Argon2 hash"));
+
+    let output = cargo_bin_cmd!("tkr")
+        .args(["web", "--hash-password"])
+        .write_stdin("pw\n")
+        .output()
+        .unwrap();
+    let hash = String::from_utf8(output.stdout).unwrap();
+    assert!(hash.starts_with("$argon2id$"), "{}", hash);
+    // The token is "s3cret" and alice's password "pw"
+    write_config(hash.trim());
+    let server = WebServer::start(&tickets_dir, &[]);
+
+    // Every request needs the token or a user's password
+    let (status, head, _) = server.request("GET", "/api/tickets", &[], None);
+    assert_eq!(status, 401);
+    assert!(head.to_lowercase().contains("www-authenticate: basic realm=\"tkr\""), "{}", head);
+    assert_eq!(server.request("GET", "/", &[], None).0, 401);
+    assert_eq!(server.request("GET", "/api/tickets", &[("Authorization", "Bearer wrong")], None).0, 401);
+    assert_eq!(server.request("GET", "/api/tickets", &[("Authorization", "Basic YWxpY2U6d3Jvbmc=")], None).0, 401);
+
+    let token = [("Authorization", "Bearer s3cret")];
+    assert_eq!(server.request("GET", "/api/tickets", &token, None).0, 200);
+    // alice:pw, and the token as any user's password
+    assert_eq!(server.request("GET", "/api/tickets", &[("Authorization", "Basic YWxpY2U6cHc=")], None).0, 200);
+    assert_eq!(server.request("GET", "/api/tickets", &[("Authorization", "Basic Ym9hcmQ6czNjcmV0")], None).0, 200);
+
+    // The board's own writes carry its origin; allowlisted origins get CORS
+    // headers and others are refused
+    let own = format!("http://127.0.0.1:{}", server.port);
+    let same = [token[0], ("Origin", own.as_str())];
+    assert_eq!(server.request("POST", "/api/tickets", &same, Some(r#"{"title": "Allowed"}"#)).0, 201);
+    let local = format!("http://localhost:{}", server.port);
+    assert_eq!(server.request("GET", "/api/tickets", &[token[0], ("Origin", local.as_str())], None).0, 200);
+    // Matching the Host header, which the client picks, isn't enough
+    let (status, _, _) = server.request("POST", "/api/tickets", &[token[0], ("Origin", "http://localhost")], Some(r#"{"title": "Refused"}"#));
+    assert_eq!(status, 403);
+    let (status, head, _) = server.request("GET", "/api/tickets", &[token[0], ("Origin", "https://board.example.com")], None);
+    assert_eq!(status, 200);
+    assert!(head.to_lowercase().contains("access-control-allow-origin: https://board.example.com"), "{}", head);
+    let (status, head, _) = server.request("GET", "/api/tickets", &[token[0], ("Origin", "https://evil.example.com")], None);
+    assert_eq!(status, 403);
+    assert!(!head.to_lowercase().contains("access-control-allow-origin"));
+    drop(server);
+
+    // Read-only serves everything but changes
+    let server = WebServer::start(&tickets_dir, &["--read-only"]);
+    let (status, _, list) = server.request("GET", "/api/tickets", &token, None);
+    assert_eq!(status, 200);
+    assert!(list.contains("Allowed"));
+    let (status, _, body) = server.request("POST", "/api/tickets", &token, Some(r#"{"title": "Refused"}"#));
+    assert_eq!(status, 403);
+    assert!(body.contains("read-only"));
+    assert_eq!(server.request("POST", "/api/tickets", &[], Some(r#"{"title": "Refused"}"#)).0, 401);
+}
+
+#[test]
+fn test_web_serves_embedded_board() {
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    // Runs outside the repo, where there is no web/ directory
+    let server = WebServer::start(&tickets_dir, &[]);
+
+    let (status, head, body) = server.request("GET", "/", &[], None);
+    assert_eq!(status, 200);
+    let head = head.to_lowercase();
+    assert!(head.contains("content-type: text/html"), "{}", head);
+    assert!(head.contains("cache-control: no-cache"), "{}", head);
+    assert!(body.contains("<html"));
+    let etag = head.lines().find_map(|line| line.strip_prefix("etag: ")).unwrap().trim().to_string();
+
+    let (status, _, body) = server.request("GET", "/index.html", &[("If-None-Match", &etag)], None);
+    assert_eq!((status, body.as_str()), (304, ""));
+    assert_eq!(server.request("GET", "/missing.js", &[], None).0, 404);
+    assert_eq!(server.request("GET", "/api/tickets", &[], None).0, 200);
+    drop(server);
+
+    // A web root replaces the built-in board
+    let web_root = temp_dir.path().join("web");
+    fs::create_dir_all(&web_root).unwrap();
+    fs::write(web_root.join("index.html"), "<html>dev board</html>").unwrap();
+    fs::write(web_root.join("app.js"), "console.log('dev');").unwrap();
+    let server = WebServer::start(&tickets_dir, &["--web-root", web_root.to_str().unwrap()]);
+    let (status, head, body) = server.request("GET", "/", &[], None);
+    assert_eq!((status, body.as_str()), (200, "<html>dev board</html>"));
+    assert!(head.to_lowercase().contains("cache-control: no-store"), "{}", head);
+    let (status, head, _) = server.request("GET", "/app.js", &[], None);
+    assert_eq!(status, 200);
+    assert!(head.to_lowercase().contains("javascript"), "{}", head);
+}
+
+#[test]
+fn test_web_live_reload_and_events() {
+    use serde_json::json;
+
+    let temp_dir = TempDir::new().unwrap();
+    let tickets_dir = temp_dir.path().join(".tickets");
+    let server = WebServer::start(&tickets_dir, &[]);
+    let mut events = server.events();
+    let tkr = |args: &[&str]| {
+        let output = cargo_bin_cmd!("tkr").env("TICKETS_DIR", &tickets_dir).args(args).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    };
+
+    // Changes made outside the server show up in the list and as events
+    let id = tkr(&["create", "Made on the command line"]);
+    events.wait_for("event:created");
+    let data = events.wait_for("\n\n");
+    assert!(data.contains(&format!("\"id\":\"{}\"", id)), "{}", data);
+    let (_, list) = server.json("GET", "/api/tickets", None);
+    assert_eq!(list[0]["title"], "Made on the command line");
+
+    tkr(&["start", &id]);
+    events.wait_for("event:updated");
+    assert!(events.wait_for("\n\n").contains("\"status\":\"in_progress\""));
+    let (_, list) = server.json("GET", "/api/tickets", None);
+    assert_eq!(list[0]["status"], "in_progress");
+
+    // Changes made through the API are pushed too
+    server.json_with("PUT", &format!("/api/tickets/{}", id), &[("If-Match", "*")], Some(json!({"title": "Renamed"})));
+    events.wait_for("event:updated");
+    assert!(events.wait_for("\n\n").contains("Renamed"));
+
+    let path = find_ticket_files(&tickets_dir).pop().unwrap();
+    fs::remove_file(path).unwrap();
+    events.wait_for("event:deleted");
+    let (_, list) = server.json("GET", "/api/tickets", None);
+    assert_eq!(list, json!([]));
+}
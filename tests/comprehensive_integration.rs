@@ -34,12 +34,12 @@ fn find_ticket_files(tickets_dir: &PathBuf) -> Vec<PathBuf> {
 #[test]
 fn test_binary_exists() {
     // Simple test to verify the binary was built
-    assert!(std::path::Path::new("./target/release/tkr").exists());
+    assert!(std::path::Path::new(env!("CARGO_BIN_EXE_tkr")).exists());
 }
 
 #[test]
 fn test_help_command() {
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .arg("--help")
         .output()
         .expect("Failed to execute tkr --help command");
@@ -53,7 +53,7 @@ fn test_help_command() {
 
 #[test]
 fn test_version_command() {
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .arg("version")
         .output()
         .expect("Failed to execute tkr version command");
@@ -67,7 +67,7 @@ fn test_version_command() {
 
 #[test]
 fn test_ls_command_basic() {
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .arg("ls")
         .output()
         .expect("Failed to execute tkr ls command");
@@ -84,7 +84,7 @@ fn test_create_ticket() {
     let temp_dir = TempDir::new().unwrap();
     let tickets_dir = temp_dir.path().join(".tickets");
 
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Test ticket")
@@ -110,7 +110,7 @@ fn test_create_ticket_with_project_category() {
     let temp_dir = TempDir::new().unwrap();
     let tickets_dir = temp_dir.path().join(".tickets");
 
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("--project")
         .arg("test-project")
@@ -138,7 +138,7 @@ fn test_list_tickets() {
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // Create a ticket first
-    let _ = Command::new("./target/release/tkr")
+    let _ = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("List test ticket")
@@ -146,7 +146,7 @@ fn test_list_tickets() {
         .expect("Failed to execute tkr create command");
 
     // List tickets
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("list")
         .output()
@@ -164,7 +164,7 @@ fn test_ticket_status_update() {
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // First create a ticket
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Status test ticket")
@@ -183,7 +183,7 @@ fn test_ticket_status_update() {
         .unwrap();
 
     // Update status
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("status")
         .arg(ticket_id)
@@ -203,7 +203,7 @@ fn test_add_note() {
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // Create a ticket first
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Note test ticket")
@@ -222,7 +222,7 @@ fn test_add_note() {
         .unwrap();
 
     // Add a note
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("add-note")
         .arg(ticket_id)
@@ -242,7 +242,7 @@ fn test_start_command() {
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // Create a ticket first
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Start test ticket")
@@ -261,7 +261,7 @@ fn test_start_command() {
         .unwrap();
 
     // Start the ticket
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("start")
         .arg(ticket_id)
@@ -280,7 +280,7 @@ fn test_close_command() {
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // Create a ticket first
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Close test ticket")
@@ -299,7 +299,7 @@ fn test_close_command() {
         .unwrap();
 
     // Close the ticket
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("close")
         .arg(ticket_id)
@@ -318,7 +318,7 @@ fn test_ready_command() {
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // Create a ticket first
-    let _ = Command::new("./target/release/tkr")
+    let _ = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Ready test ticket")
@@ -326,7 +326,7 @@ fn test_ready_command() {
         .expect("Failed to execute tkr create command");
 
     // List ready tickets (should work since no dependencies)
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("ready")
         .output()
@@ -335,7 +335,7 @@ fn test_ready_command() {
     assert!(output.status.success());
 
     let output_str = String::from_utf8_lossy(&output.stdout);
-    assert!(output_str.contains("Ready") || output_str.contains("ready") || output_str.len() > 0);
+    assert!(output_str.contains("Ready") || output_str.contains("ready") || !output_str.is_empty());
 }
 
 #[test]
@@ -344,7 +344,7 @@ fn test_show_command() {
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // Create a ticket first
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Show test ticket")
@@ -363,7 +363,7 @@ fn test_show_command() {
         .unwrap();
 
     // Show ticket details
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("show")
         .arg(ticket_id)
@@ -382,7 +382,7 @@ fn test_dependency_management() {
     let tickets_dir = temp_dir.path().join(".tickets");
 
     // Create first ticket
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Parent ticket")
@@ -392,7 +392,7 @@ fn test_dependency_management() {
     assert!(output.status.success());
 
     // Create second ticket
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("create")
         .arg("Child ticket")
@@ -417,7 +417,7 @@ fn test_dependency_management() {
         .unwrap();
 
     // Add dependency
-    let output = Command::new("./target/release/tkr")
+    let output = Command::new(env!("CARGO_BIN_EXE_tkr"))
         .env("TICKETS_DIR", &tickets_dir)
         .arg("dep")
         .arg(child_id)